@@ -1,9 +1,20 @@
 use std::fmt::Display;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use puzzles::solver::fifteen_puzzle::heuristic::PatternDatabase;
+use puzzles::solver::fifteen_puzzle::FifteenPuzzleSolver;
 use puzzles::solver::hanoi::HanoiSolver;
 use puzzles::solver::nquene::NqueneSolver;
 use puzzles::solver::pentomino::PentominoSolver;
+use puzzles::solver::rubiks::cube::Cube;
+use puzzles::solver::rubiks::optimal::OptimalTables;
+use puzzles::solver::rubiks::pocket::PocketTable;
+use puzzles::solver::rubiks::two_phase::TwoPhaseTables;
+use puzzles::solver::rubiks::{CubeSolver, RubiksSolver};
+use puzzles::solver::sudoku::bitboard::BitboardSolver;
+use puzzles::solver::sudoku::generator::Symmetry;
+use puzzles::solver::sudoku::technique::Difficulty;
 use puzzles::solver::sudoku::SudokuSolver;
 use puzzles::solver::Solver;
 
@@ -41,6 +52,8 @@ fn main() {
     let run_hanoi = false;
     let run_queue = false;
     let run_pentomino = true;
+    let run_fifteen = false;
+    let run_rubiks = false;
 
     let mut timer = Time::new();
 
@@ -62,6 +75,8 @@ fn main() {
         println!("{}", sudoku);
         let flag = sudoku.num_search();
         println!("{}", flag);
+        // 候補の表示
+        println!("{:#}", sudoku);
         let flag = sudoku.num_search();
         println!("{}", flag);
         sudoku.search().unwrap();
@@ -79,6 +94,56 @@ fn main() {
             "589642317",
         ]);
         println!("{}", sudoku == ans);
+
+        // 解の数(唯一解かどうか)
+        println!("solutions: {}", ans.count_solutions(2));
+
+        // 解き方の記録と難易度
+        let mut sudoku = SudokuSolver::new(vec![
+            "000007000",
+            "020008040",
+            "103000000",
+            "000150000",
+            "000300070",
+            "000000089",
+            "090000000",
+            "080002000",
+            "000600100",
+        ]);
+        println!("difficulty: {:?}", sudoku.difficulty().unwrap());
+        // 次の一手のヒント
+        println!("hint: {}", sudoku.hint().unwrap());
+        sudoku.logical_solve();
+        for step in sudoku.steps().iter().take(5) {
+            println!("{}", step);
+        }
+
+        // 仮置きの必要な問題をビットボード版とDancing Linksでも解く
+        // 速度の比較は cargo test --release -- --ignored --nocapture bench_bitboard
+        let hard = vec![
+            "800000000",
+            "003600000",
+            "070090200",
+            "050007000",
+            "000045700",
+            "000100030",
+            "001000068",
+            "008500010",
+            "090000400",
+        ];
+        let mut sudoku = SudokuSolver::new(hard.clone());
+        sudoku.run().unwrap();
+        let mut bitboard = BitboardSolver::parse(hard.clone()).unwrap();
+        bitboard.run().unwrap();
+        println!("{}", sudoku == bitboard.to_solver());
+        let mut dlx = SudokuSolver::new(hard);
+        dlx.dlx_solve().unwrap();
+        println!("{}", sudoku == dlx);
+
+        // 問題の生成
+        let sudoku =
+            SudokuSolver::generate(3, Symmetry::Rotational, Some(Difficulty::Hard), 0).unwrap();
+        println!("{}", sudoku);
     }
     if run_hanoi {
         /////////////////////
@@ -139,7 +204,18 @@ fn main() {
         solver.run().unwrap();
         println!("{}", solver);
         solver.init();
+        timer.start();
         println!("{}", solver.run_all());
+        timer.end();
+        println!("run_all: {}", timer);
+        timer.start();
+        println!("{}", solver.par_run_all());
+        timer.end();
+        println!("par_run_all: {}", timer);
+        timer.start();
+        println!("{}", solver.dlx_run_all());
+        timer.end();
+        println!("dlx_run_all: {}", timer);
 
         let solver = PentominoSolver::meiji_black(puzzles::solver::pentomino::TargetType::ROTATE);
         println!("{}", solver.search_one_ans());
@@ -147,4 +223,103 @@ fn main() {
         solver.init();
         println!("{}", solver.run_all());
     }
+
+    if run_fifteen {
+        /////////////////////
+        // 15パズルソルバー
+        /////////////////////
+        let mut solver = FifteenPuzzleSolver::new(vec![
+            vec![0, 2, 4, 8],
+            vec![1, 7, 3, 6],
+            vec![10, 5, 11, 12],
+            vec![9, 14, 13, 15],
+        ]);
+        timer.start();
+        solver.run().unwrap();
+        timer.end();
+        println!("{:?}", solver.ans);
+        println!("ida*: {}", timer);
+
+        solver.init();
+        timer.start();
+        solver.par_run().unwrap();
+        timer.end();
+        println!("par ida*: {}", timer);
+
+        // 6-6-3のパターンデータベース，一度作ればファイルから読み込む
+        let groups = vec![
+            vec![1, 5, 6, 9, 10, 13],
+            vec![7, 8, 11, 12, 14, 15],
+            vec![2, 3, 4],
+        ];
+        let pdb = PatternDatabase::load_or_build("pdb_663.bin", 4, 4, groups).unwrap();
+        solver.set_heuristic(pdb);
+        timer.start();
+        solver.run().unwrap();
+        timer.end();
+        println!("{:?}", solver.ans);
+        println!("ida* with pdb: {}", timer);
+    }
+
+    if run_rubiks {
+        /////////////////////
+        // ルービックキューブソルバー
+        /////////////////////
+        // 表は一度作ればファイルから読み込む
+        timer.start();
+        let tables = Arc::new(TwoPhaseTables::load_or_build("rubiks_tables.bin").unwrap());
+        timer.end();
+        println!("tables: {}", timer);
+
+        let mut solver = RubiksSolver::from_scramble(
+            "R U R' U' F2 D L' B U2 R2 F' L D2 B' U F R2",
+            tables.clone(),
+        )
+        .unwrap();
+        println!("{}", solver);
+        timer.start();
+        solver.run().unwrap();
+        timer.end();
+        println!("{}", solver.ans_string().unwrap());
+        println!("two phase: {}", timer);
+
+        // 最短手数のモード，短いスクランブルに使う
+        timer.start();
+        let optimal = Arc::new(OptimalTables::load_or_build("rubiks_optimal.bin").unwrap());
+        timer.end();
+        println!("optimal tables: {}", timer);
+        let mut solver =
+            RubiksSolver::from_scramble("R U2 F' L D B2 R' U F2 L'", tables.clone()).unwrap();
+        solver.set_optimal(optimal);
+        timer.start();
+        solver.run().unwrap();
+        timer.end();
+        println!(
+            "{} ({} moves)",
+            solver.ans_string().unwrap(),
+            solver.move_count().unwrap()
+        );
+        println!("optimal: {}", timer);
+
+        // 2x2x2は全状態の表，4x4x4以上は3x3x3への帰着で解く
+        timer.start();
+        let pocket = Arc::new(PocketTable::build());
+        timer.end();
+        println!("pocket table: {}", timer);
+        let mut solver =
+            CubeSolver::new(Cube::from_scramble(2, "R U2 F' R' U F2 R U' F R2").unwrap());
+        solver.set_pocket_table(pocket);
+        solver.run().unwrap();
+        println!("{}", solver.ans_string().unwrap());
+
+        let mut solver =
+            CubeSolver::new(Cube::from_scramble(4, "R 2R U2 2F' L D 2B2 R' 2U F2 Lw' 2D").unwrap());
+        solver.set_two_phase_tables(tables);
+        println!("{}", solver);
+        timer.start();
+        solver.run().unwrap();
+        timer.end();
+        println!("{} moves", solver.move_count().unwrap());
+        println!("4x4x4 reduction: {}", timer);
+    }
 }
@@ -0,0 +1,292 @@
+//! Dancing Links(Algorithm X)による完全被覆問題のソルバー
+//! 各行は列の集合で，主列はちょうど一度，副列は高々一度だけ覆うように行を選ぶ
+//! 数独やポリオミノの敷き詰めを完全被覆に直して解くのに使う
+
+use super::{PuzzleError, PuzzleResult};
+
+/// 完全被覆問題
+/// ノードは配列の添字でつなぎ，0番は根，1からcolumns番までが列の見出し
+#[derive(Debug, Clone)]
+pub struct DancingLinks {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    /// ノードの属する列の見出し
+    col: Vec<usize>,
+    /// ノードの属する行の番号
+    row: Vec<usize>,
+    /// 列ごとの残っているノードの数(見出しの添字で引く)
+    size: Vec<usize>,
+    /// 主列の数，列の番号は0から主列，その後に副列が並ぶ
+    primary: usize,
+    columns: usize,
+    rows: usize,
+}
+
+impl DancingLinks {
+    /// primary個の主列とsecondary個の副列を持つ空の問題
+    pub fn new(primary: usize, secondary: usize) -> Self {
+        let columns = primary + secondary;
+        let mut dlx = DancingLinks {
+            left: Vec::new(),
+            right: Vec::new(),
+            up: Vec::new(),
+            down: Vec::new(),
+            col: Vec::new(),
+            row: Vec::new(),
+            size: vec![0; columns + 1],
+            primary,
+            columns,
+            rows: 0,
+        };
+        for i in 0..=columns {
+            // 主列だけ根から横につなぎ，副列は自分自身とつなぐ(選ぶ列の候補にならない)
+            let (left, right) = if i <= primary {
+                ((i + primary) % (primary + 1), (i + 1) % (primary + 1))
+            } else {
+                (i, i)
+            };
+            dlx.left.push(left);
+            dlx.right.push(right);
+            dlx.up.push(i);
+            dlx.down.push(i);
+            dlx.col.push(i);
+            dlx.row.push(usize::MAX);
+        }
+        dlx
+    }
+    /// 主列の数
+    pub fn primary(&self) -> usize {
+        self.primary
+    }
+    /// 列の数(主列と副列)
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+    /// 行の数
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// 列の番号の集合を行として加え，行の番号(0から加えた順)を返す
+    /// 列が範囲外か重複している時，空の時はPuzzleErrorを返す
+    pub fn add_row(&mut self, columns: &[usize]) -> PuzzleResult<usize> {
+        if columns.is_empty() {
+            return Err(PuzzleError::new("empty row"));
+        }
+        for (i, &c) in columns.iter().enumerate() {
+            if c >= self.columns {
+                return Err(PuzzleError::new(format!(
+                    "column {} is out of range {}",
+                    c, self.columns
+                )));
+            }
+            if columns[..i].contains(&c) {
+                return Err(PuzzleError::new(format!("column {} is duplicated", c)));
+            }
+        }
+        let row = self.rows;
+        let first = self.left.len();
+        for (i, &c) in columns.iter().enumerate() {
+            let node = first + i;
+            let header = c + 1;
+            // 横は行の中で輪につなぐ
+            self.left.push(if i == 0 {
+                first + columns.len() - 1
+            } else {
+                node - 1
+            });
+            self.right.push(if i + 1 == columns.len() {
+                first
+            } else {
+                node + 1
+            });
+            // 縦は列の一番下に入れる
+            self.up.push(self.up[header]);
+            self.down.push(header);
+            let last = self.up[header];
+            self.down[last] = node;
+            self.up[header] = node;
+            self.col.push(header);
+            self.row.push(row);
+            self.size[header] += 1;
+        }
+        self.rows += 1;
+        Ok(row)
+    }
+
+    /// 全ての解を見つけた順にcallbackに渡す，解は選んだ行の番号
+    /// callbackがfalseを返したら打ち切る
+    pub fn solve(&mut self, mut callback: impl FnMut(&[usize]) -> bool) {
+        self.search(&mut Vec::new(), &mut callback);
+    }
+    /// 解の数をlimit個まで数える
+    pub fn count_solutions(&mut self, limit: usize) -> usize {
+        let mut count = 0;
+        if limit > 0 {
+            self.solve(|_| {
+                count += 1;
+                count < limit
+            });
+        }
+        count
+    }
+    /// 最初に見つかった解
+    pub fn first_solution(&mut self) -> Option<Vec<usize>> {
+        let mut solution = None;
+        self.solve(|rows| {
+            solution = Some(rows.to_vec());
+            false
+        });
+        solution
+    }
+
+    /// 列cを外し，cを含む行を他の列からも外す
+    fn cover(&mut self, c: usize) {
+        let (l, r) = (self.left[c], self.right[c]);
+        self.right[l] = r;
+        self.left[r] = l;
+        let mut i = self.down[c];
+        while i != c {
+            let mut j = self.right[i];
+            while j != i {
+                let (u, d) = (self.up[j], self.down[j]);
+                self.down[u] = d;
+                self.up[d] = u;
+                self.size[self.col[j]] -= 1;
+                j = self.right[j];
+            }
+            i = self.down[i];
+        }
+    }
+    /// coverの逆順で元に戻す
+    fn uncover(&mut self, c: usize) {
+        let mut i = self.up[c];
+        while i != c {
+            let mut j = self.left[i];
+            while j != i {
+                let (u, d) = (self.up[j], self.down[j]);
+                self.down[u] = j;
+                self.up[d] = j;
+                self.size[self.col[j]] += 1;
+                j = self.left[j];
+            }
+            i = self.up[i];
+        }
+        let (l, r) = (self.left[c], self.right[c]);
+        self.right[l] = c;
+        self.left[r] = c;
+    }
+    /// 残っている主列のうちノードが最も少ない列
+    fn choose_column(&self) -> usize {
+        let mut best = self.right[0];
+        let mut c = self.right[best];
+        while c != 0 {
+            if self.size[c] < self.size[best] {
+                best = c;
+            }
+            c = self.right[c];
+        }
+        best
+    }
+    /// 打ち切る時はfalseを返す，どちらの場合も構造は元に戻す
+    fn search(
+        &mut self,
+        partial: &mut Vec<usize>,
+        callback: &mut impl FnMut(&[usize]) -> bool,
+    ) -> bool {
+        if self.right[0] == 0 {
+            return callback(partial);
+        }
+        let c = self.choose_column();
+        if self.size[c] == 0 {
+            return true;
+        }
+        self.cover(c);
+        let mut r = self.down[c];
+        let mut go_on = true;
+        while r != c && go_on {
+            partial.push(self.row[r]);
+            let mut j = self.right[r];
+            while j != r {
+                self.cover(self.col[j]);
+                j = self.right[j];
+            }
+            go_on = self.search(partial, callback);
+            let mut j = self.left[r];
+            while j != r {
+                self.uncover(self.col[j]);
+                j = self.left[j];
+            }
+            partial.pop();
+            r = self.down[r];
+        }
+        self.uncover(c);
+        go_on
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DancingLinks;
+
+    #[test]
+    fn test_dlx() {
+        // Knuthの論文の例
+        let mut dlx = DancingLinks::new(7, 0);
+        for row in [
+            vec![2, 4, 5],
+            vec![0, 3, 6],
+            vec![1, 2, 5],
+            vec![0, 3],
+            vec![1, 6],
+            vec![3, 4, 6],
+        ] {
+            dlx.add_row(&row).unwrap();
+        }
+        assert_eq!(dlx.rows(), 6);
+        let mut solution = dlx.first_solution().unwrap();
+        solution.sort();
+        assert_eq!(solution, vec![0, 3, 4]);
+        assert_eq!(dlx.count_solutions(10), 1);
+        // 探索の後は元に戻っている
+        assert_eq!(dlx.count_solutions(10), 1);
+
+        // 1x4を1x1と1x2で埋める
+        let mut dlx = DancingLinks::new(4, 0);
+        for i in 0..4 {
+            dlx.add_row(&[i]).unwrap();
+        }
+        for i in 0..3 {
+            dlx.add_row(&[i, i + 1]).unwrap();
+        }
+        // 長さ4の並べ方はフィボナッチ数で5通り
+        assert_eq!(dlx.count_solutions(100), 5);
+        assert_eq!(dlx.count_solutions(3), 3);
+        let mut all = Vec::new();
+        dlx.solve(|rows| {
+            all.push(rows.len());
+            true
+        });
+        all.sort();
+        assert_eq!(all, vec![2, 3, 3, 3, 4]);
+
+        // 副列は覆わなくてもいい
+        let mut dlx = DancingLinks::new(2, 1);
+        dlx.add_row(&[0, 2]).unwrap();
+        dlx.add_row(&[1, 2]).unwrap();
+        dlx.add_row(&[1]).unwrap();
+        assert_eq!(dlx.count_solutions(10), 1);
+        assert_eq!(dlx.primary(), 2);
+        assert_eq!(dlx.columns(), 3);
+
+        let mut dlx = DancingLinks::new(2, 0);
+        assert!(dlx.add_row(&[0, 2]).is_err());
+        assert!(dlx.add_row(&[1, 1]).is_err());
+        assert!(dlx.add_row(&[]).is_err());
+        dlx.add_row(&[0]).unwrap();
+        assert_eq!(dlx.count_solutions(10), 0);
+        assert_eq!(dlx.first_solution(), None);
+    }
+}
@@ -0,0 +1,141 @@
+//! ランダムな解ける盤面の生成
+//! ベンチマークで再現できるように乱数のシードを受け取る
+
+use std::collections::HashSet;
+
+use super::super::random::Random;
+use super::super::{PuzzleError, PuzzleResult};
+use super::{Bound, Data, Direction, FifteenPuzzleSolver};
+
+impl FifteenPuzzleSolver {
+    /// 一様ランダムな解ける盤面を作る
+    /// ランダムな順列のうち偶奇の条件を満たすものだけを採用する
+    pub fn random(width: usize, height: usize, seed: u64) -> Self {
+        let mut random = Random::new(seed);
+        loop {
+            let mut board: Vec<Data> = (0..width * height).collect();
+            random.shuffle(&mut board);
+            let solver = Self::from_flat(width, height, board);
+            if solver.is_solvable() {
+                return solver;
+            }
+        }
+    }
+
+    /// ゴールからの最短手数がちょうどdistanceの盤面を作る
+    /// ゴールから後ろ向きに，最短手数が一つ増える方向だけを選んでランダムに進む
+    /// そのような盤面が存在しなければPuzzleErrorを返す
+    pub fn with_distance(
+        width: usize,
+        height: usize,
+        distance: usize,
+        seed: u64,
+    ) -> PuzzleResult<Self> {
+        let mut random = Random::new(seed);
+        let mut goal: Vec<Data> = (1..width * height).collect();
+        goal.push(0);
+        let mut solver = Self::from_flat(width, height, goal);
+
+        let shuffled = |random: &mut Random| {
+            let mut dirs = Direction::ALL;
+            random.shuffle(&mut dirs);
+            dirs.to_vec()
+        };
+        // これ以上遠くに行けないとわかった盤面
+        let mut dead = HashSet::new();
+        // 各手数でまだ試していない方向
+        let mut stack = vec![shuffled(&mut random)];
+        while solver.history.len() < distance {
+            let depth = solver.history.len();
+            match stack[depth].pop() {
+                Some(dir) => {
+                    if solver.apply(dir).is_err() {
+                        continue;
+                    }
+                    if dead.contains(&solver.board) || !solver.is_farther(depth) {
+                        solver.undo_one();
+                        continue;
+                    }
+                    stack.push(shuffled(&mut random));
+                }
+                None => {
+                    // 行き止まりなので一手戻る
+                    if depth == 0 {
+                        return Err(PuzzleError::new(format!(
+                            "no board at distance {} on {}x{}",
+                            distance, width, height
+                        )));
+                    }
+                    dead.insert(solver.board.clone());
+                    stack.pop();
+                    solver.undo_one();
+                }
+            }
+        }
+        solver.history.clear();
+        solver.init();
+        Ok(solver)
+    }
+
+    /// 最短手数がdepthの盤面から一手動かした現在の盤面が，depth+1手であるか
+    /// 一手で最短手数はちょうど1変わるので，depth-1手以内で解けないかを調べればいい
+    fn is_farther(&self, depth: usize) -> bool {
+        if depth == 0 {
+            return true;
+        }
+        let mut board = self.board.clone();
+        let h = self.heuristic.estimate(&board);
+        let bound = depth as u32 - 1;
+        let result = self.dfs(
+            &mut board,
+            self.blank,
+            0,
+            h,
+            bound,
+            &mut Vec::new(),
+            &|| false,
+        );
+        !matches!(result, Bound::Found)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FifteenPuzzleSolver;
+    use crate::solver::Solver;
+
+    #[test]
+    fn test_random() {
+        for seed in 0..20 {
+            let solver = FifteenPuzzleSolver::random(4, 4, seed);
+            assert!(solver.is_solvable());
+            assert_eq!(solver.board, FifteenPuzzleSolver::random(4, 4, seed).board);
+        }
+        assert_ne!(
+            FifteenPuzzleSolver::random(4, 4, 0).board,
+            FifteenPuzzleSolver::random(4, 4, 1).board
+        );
+        let mut solver = FifteenPuzzleSolver::random(3, 3, 7);
+        solver.run().unwrap();
+    }
+    #[test]
+    fn test_with_distance() {
+        for (distance, seed) in [(0, 0), (1, 1), (8, 2), (15, 3), (24, 4)] {
+            let mut solver = FifteenPuzzleSolver::with_distance(3, 3, distance, seed).unwrap();
+            let board = solver.board.clone();
+            assert!(solver.history.is_empty());
+            solver.run().unwrap();
+            assert_eq!(solver.ans.unwrap().len(), distance);
+
+            let solver = FifteenPuzzleSolver::with_distance(3, 3, distance, seed).unwrap();
+            assert_eq!(solver.board, board);
+        }
+        let mut solver = FifteenPuzzleSolver::with_distance(4, 4, 30, 5).unwrap();
+        solver.run().unwrap();
+        assert_eq!(solver.ans.unwrap().len(), 30);
+
+        // 2x2は最大6手
+        assert!(FifteenPuzzleSolver::with_distance(2, 2, 6, 0).is_ok());
+        assert!(FifteenPuzzleSolver::with_distance(2, 2, 7, 0).is_err());
+    }
+}
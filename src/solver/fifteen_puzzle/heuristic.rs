@@ -0,0 +1,637 @@
+//! スライドパズルのヒューリスティック関数
+//! マンハッタン距離，linear conflict，walking distance，加算的パターンデータベースを実装する
+//! どれも許容的(実際の手数を超えない)なのでIDA*で最短手順が得られる
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use super::super::{PuzzleError, PuzzleResult};
+use super::Data;
+
+/// 数字valのゴールの位置
+/// 1から順に並べて最後が空白
+#[inline]
+fn goal_pos(val: Data) -> usize {
+    val - 1
+}
+
+/// 空白の隣接マス
+fn neighbors(width: usize, height: usize, pos: usize) -> impl Iterator<Item = usize> {
+    let row = pos / width;
+    let col = pos % width;
+    IntoIterator::into_iter([
+        (row > 0).then(|| pos - width),
+        (row + 1 < height).then(|| pos + width),
+        (col > 0).then(|| pos - 1),
+        (col + 1 < width).then(|| pos + 1),
+    ])
+    .flatten()
+}
+
+/// IDA*で使うヒューリスティック関数
+pub trait Heuristic: Debug + Send + Sync {
+    /// 盤面boardからゴールまでの手数の下界を返す
+    fn estimate(&self, board: &[Data]) -> u32;
+
+    /// タイルvalをfromからtoへ動かした後の盤面boardの値を返す
+    /// hは動かす前の盤面の値で，差分で計算できるものは上書きする
+    fn update(&self, board: &[Data], _h: u32, _val: Data, _from: usize, _to: usize) -> u32 {
+        self.estimate(board)
+    }
+}
+
+/// 各タイルのゴールまでのマンハッタン距離の和
+#[derive(Debug, Clone)]
+pub struct Manhattan {
+    width: usize,
+}
+
+impl Manhattan {
+    pub fn new(width: usize, _height: usize) -> Self {
+        Manhattan { width }
+    }
+    /// 位置aと位置bのマンハッタン距離
+    #[inline]
+    fn distance(&self, a: usize, b: usize) -> u32 {
+        ((a / self.width).abs_diff(b / self.width) + (a % self.width).abs_diff(b % self.width))
+            as u32
+    }
+}
+
+impl Heuristic for Manhattan {
+    fn estimate(&self, board: &[Data]) -> u32 {
+        board
+            .iter()
+            .enumerate()
+            .filter(|&(_, &val)| val != 0)
+            .map(|(pos, &val)| self.distance(pos, goal_pos(val)))
+            .sum()
+    }
+    fn update(&self, _board: &[Data], h: u32, val: Data, from: usize, to: usize) -> u32 {
+        // 一つのタイルしか動かないのでその差分だけ
+        let goal = goal_pos(val);
+        h + self.distance(to, goal) - self.distance(from, goal)
+    }
+}
+
+/// マンハッタン距離にlinear conflictを加えたもの
+/// 同じ行(列)がゴールで順番が逆になっているタイルは
+/// どちらかが一度その行(列)から出る必要があるので2手ずつ加える
+#[derive(Debug, Clone)]
+pub struct LinearConflict {
+    width: usize,
+    height: usize,
+    manhattan: Manhattan,
+}
+
+impl LinearConflict {
+    pub fn new(width: usize, height: usize) -> Self {
+        LinearConflict {
+            width,
+            height,
+            manhattan: Manhattan::new(width, height),
+        }
+    }
+    /// 一列に並んだゴールの位置から，順番を保つために列から出す必要のある最小のタイル数
+    /// (全体の数 - 最長増加部分列の長さ)
+    fn conflict(goals: &[usize]) -> u32 {
+        let mut lis = vec![1; goals.len()];
+        for i in 0..goals.len() {
+            for j in 0..i {
+                if goals[j] < goals[i] {
+                    lis[i] = lis[i].max(lis[j] + 1);
+                }
+            }
+        }
+        (goals.len() - lis.iter().copied().max().unwrap_or(0)) as u32
+    }
+}
+
+impl Heuristic for LinearConflict {
+    fn estimate(&self, board: &[Data]) -> u32 {
+        let mut sum = 0;
+        for row in 0..self.height {
+            let goals: Vec<usize> = (0..self.width)
+                .map(|col| board[row * self.width + col])
+                .filter(|&val| val != 0 && goal_pos(val) / self.width == row)
+                .map(|val| goal_pos(val) % self.width)
+                .collect();
+            sum += Self::conflict(&goals);
+        }
+        for col in 0..self.width {
+            let goals: Vec<usize> = (0..self.height)
+                .map(|row| board[row * self.width + col])
+                .filter(|&val| val != 0 && goal_pos(val) % self.width == col)
+                .map(|val| goal_pos(val) / self.width)
+                .collect();
+            sum += Self::conflict(&goals);
+        }
+        self.manhattan.estimate(board) + 2 * sum
+    }
+}
+
+/// walking distance
+/// 各行(列)にゴールの行(列)ごとのタイルが何枚あるかだけを見て，
+/// 空白を縦(横)に動かす手数を事前にBFSで求めておく
+#[derive(Debug, Clone)]
+pub struct WalkingDistance {
+    width: usize,
+    height: usize,
+    /// 縦方向の移動のテーブル
+    vertical: HashMap<Vec<u8>, u8>,
+    /// 横方向の移動のテーブル
+    horizontal: HashMap<Vec<u8>, u8>,
+}
+
+impl WalkingDistance {
+    pub fn new(width: usize, height: usize) -> Self {
+        WalkingDistance {
+            width,
+            height,
+            vertical: Self::build_table(height, width),
+            horizontal: Self::build_table(width, height),
+        }
+    }
+    /// lines本の列にそれぞれcellsマスある時のテーブルをゴールからのBFSで作る
+    /// 状態はlines x linesの行列(列iにあるゴールが列jのタイルの数)と空白のある列
+    fn build_table(lines: usize, cells: usize) -> HashMap<Vec<u8>, u8> {
+        let mut goal = vec![0; lines * lines + 1];
+        for i in 0..lines {
+            goal[i * lines + i] = cells as u8;
+        }
+        // 空白は最後の列
+        goal[lines * lines - 1] -= 1;
+        goal[lines * lines] = (lines - 1) as u8;
+
+        let mut table = HashMap::new();
+        table.insert(goal.clone(), 0);
+        let mut queue = VecDeque::from([goal]);
+        while let Some(state) = queue.pop_front() {
+            let dist = table[&state];
+            let blank = state[lines * lines] as usize;
+            for next in [blank.wrapping_sub(1), blank + 1] {
+                if next >= lines {
+                    continue;
+                }
+                // 隣の列のタイルを空白の列に移す
+                for g in 0..lines {
+                    if state[next * lines + g] == 0 {
+                        continue;
+                    }
+                    let mut new_state = state.clone();
+                    new_state[next * lines + g] -= 1;
+                    new_state[blank * lines + g] += 1;
+                    new_state[lines * lines] = next as u8;
+                    if !table.contains_key(&new_state) {
+                        table.insert(new_state.clone(), dist + 1);
+                        queue.push_back(new_state);
+                    }
+                }
+            }
+        }
+        table
+    }
+}
+
+impl Heuristic for WalkingDistance {
+    fn estimate(&self, board: &[Data]) -> u32 {
+        let mut vertical = vec![0; self.height * self.height + 1];
+        let mut horizontal = vec![0; self.width * self.width + 1];
+        for (pos, &val) in board.iter().enumerate() {
+            let (row, col) = (pos / self.width, pos % self.width);
+            if val == 0 {
+                vertical[self.height * self.height] = row as u8;
+                horizontal[self.width * self.width] = col as u8;
+                continue;
+            }
+            let goal = goal_pos(val);
+            vertical[row * self.height + goal / self.width] += 1;
+            horizontal[col * self.width + goal % self.width] += 1;
+        }
+        (self.vertical[&vertical] + self.horizontal[&horizontal]) as u32
+    }
+}
+
+/// ファイルの先頭に書くマジックナンバー
+const PDB_MAGIC: &[u8; 4] = b"SPDB";
+
+/// 加算的な互いに素なパターンデータベース
+/// タイルをいくつかのグループに分け，グループごとに
+/// そのグループのタイルを動かす手数だけを数えた最短手数を持つ
+#[derive(Clone, PartialEq, Eq)]
+pub struct PatternDatabase {
+    width: usize,
+    height: usize,
+    /// 各グループのタイル
+    groups: Vec<Vec<Data>>,
+    /// groups[i]の位置の順列のランクから手数を引くテーブル
+    tables: Vec<Vec<u8>>,
+    /// タイルがどのグループに属するか
+    group_of: Vec<Option<usize>>,
+}
+
+impl Debug for PatternDatabase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // テーブルは大きいので表示しない
+        f.debug_struct("PatternDatabase")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("groups", &self.groups)
+            .finish()
+    }
+}
+
+impl PatternDatabase {
+    /// groupsに分けたパターンデータベースを後ろ向きのBFSで作る
+    /// グループは互いに素で1..width*heightのタイルでなければpanicする
+    pub fn build(width: usize, height: usize, groups: Vec<Vec<Data>>) -> Self {
+        let group_of = Self::group_of(width * height, &groups)
+            .unwrap_or_else(|| panic!("invalid groups: {:?}", groups));
+        let tables = groups
+            .iter()
+            .map(|tiles| Self::build_table(width, height, tiles))
+            .collect();
+        PatternDatabase {
+            width,
+            height,
+            groups,
+            tables,
+            group_of,
+        }
+    }
+    /// pathにあればそれを読み込み，なければ作ってpathに保存する
+    pub fn load_or_build(
+        path: impl AsRef<Path>,
+        width: usize,
+        height: usize,
+        groups: Vec<Vec<Data>>,
+    ) -> PuzzleResult<Self> {
+        let path = path.as_ref();
+        if path.exists() {
+            let pdb = Self::load(path)?;
+            if pdb.width == width && pdb.height == height && pdb.groups == groups {
+                return Ok(pdb);
+            }
+        }
+        let pdb = Self::build(width, height, groups);
+        pdb.save(path)?;
+        Ok(pdb)
+    }
+
+    /// タイルごとにグループの番号を割り当てる，不正なグループならNone
+    fn group_of(size: usize, groups: &[Vec<Data>]) -> Option<Vec<Option<usize>>> {
+        let mut group_of = vec![None; size];
+        for (i, tiles) in groups.iter().enumerate() {
+            for &val in tiles {
+                if val == 0 || val >= size || group_of[val].is_some() {
+                    return None;
+                }
+                group_of[val] = Some(i);
+            }
+        }
+        Some(group_of)
+    }
+
+    /// n個のマスからk個選んで並べる順列の数，usizeに収まらなければNone
+    fn permutations(n: usize, k: usize) -> Option<usize> {
+        (n - k + 1..=n).try_fold(1usize, |acc, x| acc.checked_mul(x))
+    }
+    /// n個のマスからk個選んで並べる順列のランクに使う重み
+    fn weights(n: usize, k: usize) -> Vec<usize> {
+        let mut weights = vec![1; k];
+        for i in (0..k.saturating_sub(1)).rev() {
+            weights[i] = weights[i + 1] * (n - 1 - i);
+        }
+        weights
+    }
+    /// 位置の列positionsの順列のランク
+    fn rank(weights: &[usize], positions: &[usize]) -> usize {
+        let mut rank = 0;
+        for (i, &pos) in positions.iter().enumerate() {
+            let smaller = positions[..i].iter().filter(|&&x| x < pos).count();
+            rank += (pos - smaller) * weights[i];
+        }
+        rank
+    }
+    /// rankから位置の列を復元する
+    fn unrank(n: usize, weights: &[usize], mut rank: usize, positions: &mut [usize]) {
+        let mut used = vec![false; n];
+        for (i, &weight) in weights.iter().enumerate() {
+            let mut nth = rank / weight;
+            rank %= weight;
+            let pos = (0..n)
+                .find(|&x| {
+                    if used[x] {
+                        return false;
+                    }
+                    if nth == 0 {
+                        return true;
+                    }
+                    nth -= 1;
+                    false
+                })
+                .unwrap();
+            used[pos] = true;
+            positions[i] = pos;
+        }
+    }
+
+    /// tilesのタイルの配置ごとに，tilesを動かす回数の最小値を0-1 BFSで求める
+    /// tiles以外のタイルを動かすのはコスト0として空白の位置も状態に含める
+    fn build_table(width: usize, height: usize, tiles: &[Data]) -> Vec<u8> {
+        let n = width * height;
+        let weights = Self::weights(n, tiles.len());
+        let size = Self::permutations(n, tiles.len()).expect("pattern database is too large");
+        let mut table = vec![u8::MAX; size];
+        let mut dist = vec![u8::MAX; size * n];
+
+        let goal: Vec<usize> = tiles.iter().map(|&val| goal_pos(val)).collect();
+        let start = Self::rank(&weights, &goal);
+        dist[start * n + n - 1] = 0;
+        let mut queue = VecDeque::from([(start, n - 1)]);
+        let mut positions = vec![0; tiles.len()];
+        while let Some((rank, blank)) = queue.pop_front() {
+            let d = dist[rank * n + blank];
+            table[rank] = table[rank].min(d);
+            Self::unrank(n, &weights, rank, &mut positions);
+            for next in neighbors(width, height, blank) {
+                let (next_rank, cost) = match positions.iter().position(|&x| x == next) {
+                    Some(i) => {
+                        // グループのタイルが空白の位置に動く
+                        positions[i] = blank;
+                        let next_rank = Self::rank(&weights, &positions);
+                        positions[i] = next;
+                        (next_rank, 1)
+                    }
+                    None => (rank, 0),
+                };
+                let index = next_rank * n + next;
+                if d + cost < dist[index] {
+                    dist[index] = d + cost;
+                    if cost == 0 {
+                        queue.push_front((next_rank, next));
+                    } else {
+                        queue.push_back((next_rank, next));
+                    }
+                }
+            }
+        }
+        table
+    }
+
+    /// 盤面からgroupのタイルの位置のランクを求める
+    /// movedが与えられた時はそのタイルをその位置にあるとみなす
+    fn board_rank(&self, group: usize, board: &[Data], moved: Option<(Data, usize)>) -> usize {
+        let tiles = &self.groups[group];
+        let mut positions = vec![0; tiles.len()];
+        for (pos, &val) in board.iter().enumerate() {
+            if let Some(i) = tiles.iter().position(|&x| x == val) {
+                positions[i] = pos;
+            }
+        }
+        if let Some((val, pos)) = moved {
+            if let Some(i) = tiles.iter().position(|&x| x == val) {
+                positions[i] = pos;
+            }
+        }
+        let weights = Self::weights(self.width * self.height, tiles.len());
+        Self::rank(&weights, &positions)
+    }
+
+    /// 小さなバイナリ形式でファイルに保存する
+    /// magic, width, height, グループ数, (グループの大きさ, タイル, テーブルの長さ, テーブル)...
+    /// 幅，高さ，グループの数と大きさ，タイルの番号が255を超える時はPuzzleErrorを返す
+    pub fn save(&self, path: impl AsRef<Path>) -> PuzzleResult<()> {
+        let max = u8::MAX as usize;
+        if self.width > max
+            || self.height > max
+            || self.groups.len() > max
+            || self
+                .groups
+                .iter()
+                .any(|tiles| tiles.len() > max || tiles.iter().any(|&x| x > max))
+        {
+            return Err(PuzzleError::new(
+                "cannot save pdb file: sizes and tiles must be at most 255",
+            ));
+        }
+        let file = File::create(path.as_ref())
+            .map_err(|e| PuzzleError::new(format!("cannot create pdb file: {}", e)))?;
+        let mut writer = BufWriter::new(file);
+        let mut header = Vec::new();
+        header.extend_from_slice(PDB_MAGIC);
+        header.push(self.width as u8);
+        header.push(self.height as u8);
+        header.push(self.groups.len() as u8);
+        let mut write = |bytes: &[u8]| {
+            writer
+                .write_all(bytes)
+                .map_err(|e| PuzzleError::new(format!("cannot write pdb file: {}", e)))
+        };
+        write(&header)?;
+        for (tiles, table) in self.groups.iter().zip(&self.tables) {
+            let mut group = vec![tiles.len() as u8];
+            group.extend(tiles.iter().map(|&x| x as u8));
+            group.extend_from_slice(&(table.len() as u32).to_le_bytes());
+            write(&group)?;
+            write(table)?;
+        }
+        writer
+            .flush()
+            .map_err(|e| PuzzleError::new(format!("cannot write pdb file: {}", e)))
+    }
+    /// saveで保存したファイルを読み込む
+    pub fn load(path: impl AsRef<Path>) -> PuzzleResult<Self> {
+        let file = File::open(path.as_ref())
+            .map_err(|e| PuzzleError::new(format!("cannot open pdb file: {}", e)))?;
+        let mut reader = BufReader::new(file);
+        // 壊れたファイルの長さで先に大きな領域を取らないように，読めた分だけ伸ばす
+        let mut read = |len: usize| -> PuzzleResult<Vec<u8>> {
+            let mut buf = Vec::new();
+            (&mut reader)
+                .take(len as u64)
+                .read_to_end(&mut buf)
+                .map_err(|e| PuzzleError::new(format!("cannot read pdb file: {}", e)))?;
+            if buf.len() != len {
+                return Err(PuzzleError::new(
+                    "cannot read pdb file: unexpected end of file",
+                ));
+            }
+            Ok(buf)
+        };
+        let header = read(7)?;
+        if &header[..4] != PDB_MAGIC {
+            return Err(PuzzleError::new("invalid pdb file"));
+        }
+        let width = header[4] as usize;
+        let height = header[5] as usize;
+        let mut groups = Vec::new();
+        let mut tables = Vec::new();
+        for _ in 0..header[6] {
+            let len = read(1)?[0] as usize;
+            let tiles: Vec<Data> = read(len)?.into_iter().map(|x| x as Data).collect();
+            let mut table_len = [0; 4];
+            table_len.copy_from_slice(&read(4)?);
+            let table_len = u32::from_le_bytes(table_len) as usize;
+            let n = width * height;
+            if tiles.len() > n || Self::permutations(n, tiles.len()) != Some(table_len) {
+                return Err(PuzzleError::new("invalid pdb file"));
+            }
+            groups.push(tiles);
+            tables.push(read(table_len)?);
+        }
+        let group_of = Self::group_of(width * height, &groups)
+            .ok_or_else(|| PuzzleError::new("invalid pdb file"))?;
+        Ok(PatternDatabase {
+            width,
+            height,
+            groups,
+            tables,
+            group_of,
+        })
+    }
+}
+
+impl Heuristic for PatternDatabase {
+    fn estimate(&self, board: &[Data]) -> u32 {
+        (0..self.groups.len())
+            .map(|group| self.tables[group][self.board_rank(group, board, None)] as u32)
+            .sum()
+    }
+    fn update(&self, board: &[Data], h: u32, val: Data, from: usize, _to: usize) -> u32 {
+        // 動いたタイルのグループだけ計算し直す
+        match self.group_of[val] {
+            Some(group) => {
+                let before = self.tables[group][self.board_rank(group, board, Some((val, from)))];
+                let after = self.tables[group][self.board_rank(group, board, None)];
+                h + after as u32 - before as u32
+            }
+            None => h,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{HashMap, VecDeque};
+
+    use super::{
+        neighbors, Heuristic, LinearConflict, Manhattan, PatternDatabase, WalkingDistance,
+        PDB_MAGIC,
+    };
+
+    /// ゴールからBFSして全ての盤面の最短手数を求める
+    fn all_distances(width: usize, height: usize) -> HashMap<Vec<usize>, u32> {
+        let mut goal: Vec<usize> = (1..width * height).collect();
+        goal.push(0);
+        let mut dist = HashMap::new();
+        dist.insert(goal.clone(), 0);
+        let mut queue = VecDeque::from([goal]);
+        while let Some(board) = queue.pop_front() {
+            let d = dist[&board];
+            let blank = board.iter().position(|&x| x == 0).unwrap();
+            for next in neighbors(width, height, blank) {
+                let mut new_board = board.clone();
+                new_board.swap(blank, next);
+                if !dist.contains_key(&new_board) {
+                    dist.insert(new_board.clone(), d + 1);
+                    queue.push_back(new_board);
+                }
+            }
+        }
+        dist
+    }
+
+    /// 全ての盤面で許容的であり，差分計算が全体の計算と一致することを確かめる
+    fn check_admissible(heuristic: &dyn Heuristic, width: usize, dist: &HashMap<Vec<usize>, u32>) {
+        for (board, &d) in dist {
+            let h = heuristic.estimate(board);
+            assert!(h <= d, "board: {:?}, h: {}, dist: {}", board, h, d);
+
+            let blank = board.iter().position(|&x| x == 0).unwrap();
+            for next in neighbors(width, board.len() / width, blank) {
+                let mut new_board = board.clone();
+                new_board.swap(blank, next);
+                let val = new_board[blank];
+                assert_eq!(
+                    heuristic.update(&new_board, h, val, next, blank),
+                    heuristic.estimate(&new_board)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_admissible() {
+        let dist = all_distances(3, 3);
+        assert_eq!(dist.len(), 181440);
+        assert_eq!(dist.values().max(), Some(&31));
+        check_admissible(&Manhattan::new(3, 3), 3, &dist);
+        check_admissible(&LinearConflict::new(3, 3), 3, &dist);
+        check_admissible(&WalkingDistance::new(3, 3), 3, &dist);
+        check_admissible(
+            &PatternDatabase::build(3, 3, vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]]),
+            3,
+            &dist,
+        );
+
+        let dist = all_distances(3, 2);
+        check_admissible(&LinearConflict::new(3, 2), 3, &dist);
+        check_admissible(&WalkingDistance::new(3, 2), 3, &dist);
+    }
+    #[test]
+    fn test_linear_conflict() {
+        let board = vec![2, 1, 3, 4, 5, 6, 7, 8, 0];
+        assert_eq!(Manhattan::new(3, 3).estimate(&board), 2);
+        assert_eq!(LinearConflict::new(3, 3).estimate(&board), 4);
+
+        let board = vec![3, 2, 1, 4, 5, 6, 7, 8, 0];
+        assert_eq!(LinearConflict::new(3, 3).estimate(&board), 8);
+    }
+    #[test]
+    fn test_pattern_database_single_group() {
+        // 全てのタイルを一つのグループにすると最短手数そのものになる
+        let dist = all_distances(3, 2);
+        let pdb = PatternDatabase::build(3, 2, vec![vec![1, 2, 3, 4, 5]]);
+        for (board, &d) in &dist {
+            assert_eq!(pdb.estimate(board), d);
+        }
+    }
+    #[test]
+    fn test_pattern_database_file() {
+        let path = std::env::temp_dir().join("puzzles_pattern_database_test.bin");
+        let _ = std::fs::remove_file(&path);
+        let groups = vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]];
+
+        let pdb = PatternDatabase::load_or_build(&path, 3, 3, groups.clone()).unwrap();
+        assert!(path.exists());
+        let loaded = PatternDatabase::load(&path).unwrap();
+        assert_eq!(pdb, loaded);
+        let loaded = PatternDatabase::load_or_build(&path, 3, 3, groups).unwrap();
+        assert_eq!(pdb, loaded);
+
+        std::fs::write(&path, b"broken").unwrap();
+        assert!(PatternDatabase::load(&path).is_err());
+        // 255x255の盤面で255個のタイルのグループは順列の数が溢れる
+        let mut bytes = PDB_MAGIC.to_vec();
+        bytes.extend_from_slice(&[255, 255, 1, 255]);
+        bytes.extend(1..=255);
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(PatternDatabase::load(&path).is_err());
+        // 表の長さは合っているがファイルが途中で終わる
+        let mut bytes = PDB_MAGIC.to_vec();
+        bytes.extend_from_slice(&[255, 255, 1, 2, 1, 2]);
+        bytes.extend_from_slice(&(65025u32 * 65024).to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(PatternDatabase::load(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+        // u8に収まらない幅は保存できない
+        let wide = PatternDatabase::build(256, 1, vec![]);
+        assert!(wide.save(&path).is_err());
+        assert!(!path.exists());
+    }
+}
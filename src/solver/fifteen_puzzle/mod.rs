@@ -0,0 +1,743 @@
+//! 15パズル(スライドパズル)のソルバー
+//! IDA*で最短手順を求める，ヒューリスティック関数はheuristicから選べる
+//! 盤面の大きさは任意の長方形(8パズルや5x5など)を扱える
+
+mod generator;
+pub mod heuristic;
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::{PuzzleError, PuzzleResult, Solver};
+use heuristic::{Heuristic, Manhattan};
+use rayon::prelude::*;
+
+type Data = usize;
+
+/// 並行処理で探索する時に部分木に分ける深さ
+const PAR_DEPTH: usize = 8;
+
+/// 並行に探索する部分木の根(盤面，空白の位置，ヒューリスティック値，そこまでの手順)
+type Task = (Vec<Data>, usize, u32, Vec<Direction>);
+
+/// 空白を動かす方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    UP,
+    DOWN,
+    LEFT,
+    RIGHT,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::UP,
+        Direction::DOWN,
+        Direction::LEFT,
+        Direction::RIGHT,
+    ];
+    /// 逆向きの移動
+    pub fn reverse(&self) -> Self {
+        match self {
+            Direction::UP => Direction::DOWN,
+            Direction::DOWN => Direction::UP,
+            Direction::LEFT => Direction::RIGHT,
+            Direction::RIGHT => Direction::LEFT,
+        }
+    }
+    /// U/D/L/Rの文字から変換する
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            'U' => Some(Direction::UP),
+            'D' => Some(Direction::DOWN),
+            'L' => Some(Direction::LEFT),
+            'R' => Some(Direction::RIGHT),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let c = match self {
+            Direction::UP => 'U',
+            Direction::DOWN => 'D',
+            Direction::LEFT => 'L',
+            Direction::RIGHT => 'R',
+        };
+        write!(f, "{}", c)
+    }
+}
+
+/// IDA*の一回の反復の結果
+enum Bound {
+    /// 解が見つかった
+    Found,
+    /// 閾値を超えたf値の最小値(これ以上探索できない時はNone)
+    Over(Option<u32>),
+}
+
+#[derive(Debug)]
+pub struct FifteenPuzzleSolver {
+    /// 盤面の横幅
+    width: usize,
+    /// 盤面の高さ
+    height: usize,
+    /// 現在の盤面 board[row * width + col]，0は空白
+    board: Vec<Data>,
+    /// 空白の位置
+    blank: usize,
+    /// 履歴(空白を動かした方向)
+    pub history: Vec<Direction>,
+    /// 最短手順のうち現在何手目まで進めたか
+    state: usize,
+    /// IDA*で使うヒューリスティック関数
+    heuristic: Box<dyn Heuristic>,
+    /// 現在のIDA*の閾値
+    threshold: u32,
+    /// 見つかった最短手順(探索を始めた盤面から空白を動かす方向)
+    pub ans: Option<Vec<Direction>>,
+}
+
+impl FifteenPuzzleSolver {
+    /// boardは行ごとに並べたもので，0を空白とする
+    /// 盤面は長方形で，0..height*widthがちょうど一つずつ含まれていない場合はpanicする
+    pub fn new(board: Vec<Vec<Data>>) -> Self {
+        let height = board.len();
+        let width = board.first().map_or(0, |x| x.len());
+        if height == 0 || width == 0 {
+            panic!("invalid data");
+        }
+        let mut flat = Vec::with_capacity(width * height);
+        let mut used = vec![false; width * height];
+        for vec in &board {
+            if vec.len() != width {
+                panic!("invalid data");
+            }
+            for &val in vec {
+                if val >= width * height || used[val] {
+                    panic!("invalid data");
+                }
+                used[val] = true;
+                flat.push(val);
+            }
+        }
+        Self::from_flat(width, height, flat)
+    }
+    /// 一列に並べた盤面から作る
+    fn from_flat(width: usize, height: usize, board: Vec<Data>) -> Self {
+        let blank = board.iter().position(|&x| x == 0).unwrap();
+        let mut solver = FifteenPuzzleSolver {
+            width,
+            height,
+            board,
+            blank,
+            history: Vec::new(),
+            state: 0,
+            heuristic: Box::new(Manhattan::new(width, height)),
+            threshold: 0,
+            ans: None,
+        };
+        solver.init();
+        solver
+    }
+    /// 探索を初期化する，次の探索は現在の盤面から始まる
+    pub fn init(&mut self) {
+        self.threshold = self.heuristic.estimate(&self.board);
+        self.ans = None;
+        self.state = 0;
+    }
+    /// ヒューリスティック関数を変更する(デフォルトはマンハッタン距離)
+    /// 盤面と同じ大きさで作ったものを渡す
+    pub fn set_heuristic(&mut self, heuristic: impl Heuristic + 'static) {
+        self.heuristic = Box::new(heuristic);
+        self.init();
+    }
+
+    /// 転倒数と空白の行から解けるかどうかを判定する
+    /// 空白を縦に動かすと転倒数がwidth-1だけ変わるので，
+    /// widthが偶数の時は空白のゴールからの行の差も合わせた偶奇が不変量になる
+    pub fn is_solvable(&self) -> bool {
+        let tiles: Vec<Data> = self.board.iter().copied().filter(|&x| x != 0).collect();
+        let mut inversion = 0;
+        for i in 0..tiles.len() {
+            for j in (i + 1)..tiles.len() {
+                if tiles[i] > tiles[j] {
+                    inversion += 1;
+                }
+            }
+        }
+        let blank_row_diff = self.height - 1 - self.blank / self.width;
+        if self.width.is_multiple_of(2) {
+            (inversion + blank_row_diff).is_multiple_of(2)
+        } else {
+            inversion.is_multiple_of(2)
+        }
+    }
+
+    /// ゴールの盤面
+    /// 1から順に並べて最後が空白
+    pub fn goal(&self) -> Vec<Data> {
+        let mut goal: Vec<Data> = (1..self.width * self.height).collect();
+        goal.push(0);
+        goal
+    }
+
+    /// 盤面がゴールかどうか
+    fn is_goal(&self, board: &[Data]) -> bool {
+        let last = board.len() - 1;
+        board[last] == 0 && board[..last].iter().enumerate().all(|(i, &x)| x == i + 1)
+    }
+
+    /// 空白をdirに動かす，動かせない時はPuzzleErrorを返す
+    /// 最短手順とは無関係な操作なので探索は初期化される
+    pub fn move_blank(&mut self, dir: Direction) -> PuzzleResult<()> {
+        self.apply(dir)?;
+        self.init();
+        Ok(())
+    }
+    /// U/D/L/Rの文字列で与えられた操作を順に適用する(空白文字は無視する)
+    /// 不正な文字や動かせない操作があればPuzzleErrorを返し，盤面は元のままにする
+    pub fn apply_moves(&mut self, moves: &str) -> PuzzleResult<()> {
+        let len = self.history.len();
+        for (i, c) in moves.chars().filter(|c| !c.is_whitespace()).enumerate() {
+            let result = match Direction::from_char(c) {
+                Some(dir) => self.apply(dir),
+                None => Err(PuzzleError::new(format!(
+                    "invalid move: '{}' at {}: it must be one of U, D, L, R",
+                    c, i
+                ))),
+            };
+            if let Err(e) = result {
+                // 途中まで適用したものを戻す
+                while self.history.len() > len {
+                    self.undo_one();
+                }
+                return Err(e);
+            }
+        }
+        self.init();
+        Ok(())
+    }
+    /// 一手巻き戻す
+    /// 最短手順を進めている途中であれば一手前に戻る
+    pub fn undo(&mut self) -> PuzzleResult<Direction> {
+        let dir = self
+            .undo_one()
+            .ok_or_else(|| PuzzleError::new("The state have initiarized"))?;
+        if self.state > 0 {
+            self.state -= 1;
+        } else {
+            self.init();
+        }
+        Ok(dir)
+    }
+    /// 最短手順を一手進める
+    pub fn step_forward(&mut self) -> PuzzleResult<Direction> {
+        let dir = match &self.ans {
+            Some(ans) if self.state < ans.len() => ans[self.state],
+            Some(_) => return Err(PuzzleError::new("already reached the goal")),
+            None => return Err(PuzzleError::new("not solved yet")),
+        };
+        self.apply(dir)?;
+        self.state += 1;
+        Ok(dir)
+    }
+    /// 最短手順を一手戻す
+    pub fn step_backward(&mut self) -> PuzzleResult<Direction> {
+        if self.state == 0 {
+            return Err(PuzzleError::new("at the start of the answer"));
+        }
+        self.undo()
+    }
+    /// 最短手順の残りの手数
+    pub fn remaining(&self) -> Option<usize> {
+        self.ans.as_ref().map(|ans| ans.len() - self.state)
+    }
+    /// 最短手順をU/D/L/Rの文字列にしたもの
+    pub fn ans_string(&self) -> Option<String> {
+        self.ans
+            .as_ref()
+            .map(|ans| ans.iter().map(|dir| dir.to_string()).collect())
+    }
+
+    /// 盤面の空白をdirに動かして履歴に残す
+    fn apply(&mut self, dir: Direction) -> PuzzleResult<()> {
+        let next = self.neighbor(self.blank, dir).ok_or_else(|| {
+            PuzzleError::new(format!(
+                "cannot move the blank {:?}: blank is at ({}, {})",
+                dir,
+                self.blank / self.width,
+                self.blank % self.width
+            ))
+        })?;
+        self.board.swap(self.blank, next);
+        self.blank = next;
+        self.history.push(dir);
+        Ok(())
+    }
+    /// 履歴から一手戻す
+    fn undo_one(&mut self) -> Option<Direction> {
+        let dir = self.history.pop()?;
+        // 逆に適用する
+        let prev = self.neighbor(self.blank, dir.reverse()).unwrap();
+        self.board.swap(self.blank, prev);
+        self.blank = prev;
+        Some(dir)
+    }
+
+    /// 空白をdirに動かした時の空白の位置，動かせなければNone
+    #[inline]
+    fn neighbor(&self, blank: usize, dir: Direction) -> Option<usize> {
+        let row = blank / self.width;
+        let col = blank % self.width;
+        match dir {
+            Direction::UP if row > 0 => Some(blank - self.width),
+            Direction::DOWN if row < self.height - 1 => Some(blank + self.width),
+            Direction::LEFT if col > 0 => Some(blank - 1),
+            Direction::RIGHT if col < self.width - 1 => Some(blank + 1),
+            _ => None,
+        }
+    }
+
+    /// IDA*の一回の反復を並行処理で行う
+    /// 深さPAR_DEPTHまでの盤面を列挙し，その先の部分木をそれぞれ並行に探索する
+    /// 逐次版と同じ解になるように，解が見つかった部分木より後ろの部分木は打ち切る
+    pub fn par_search(&mut self) -> PuzzleResult<()> {
+        if self.ans.is_some() {
+            return Ok(());
+        }
+        if !self.is_solvable() {
+            return Err(PuzzleError::new("unsolvable board"));
+        }
+        let bound = self.threshold;
+        let mut board = self.board.clone();
+        let h = self.heuristic.estimate(&board);
+        let mut tasks = Vec::new();
+        let mut min = None;
+        self.frontier(
+            &mut board,
+            self.blank,
+            h,
+            bound,
+            &mut Vec::new(),
+            &mut tasks,
+            &mut min,
+        );
+
+        // 解が見つかった部分木の番号の最小値
+        let found = AtomicUsize::new(usize::MAX);
+        let results: Vec<(Bound, Vec<Direction>)> = tasks
+            .into_par_iter()
+            .enumerate()
+            .map(|(i, (mut board, blank, h, mut path))| {
+                let g = path.len() as u32;
+                let abort = || found.load(Ordering::Relaxed) < i;
+                let result = self.dfs(&mut board, blank, g, h, bound, &mut path, &abort);
+                if let Bound::Found = result {
+                    found.fetch_min(i, Ordering::Relaxed);
+                }
+                (result, path)
+            })
+            .collect();
+
+        // 部分木は逐次版の探索順に並んでいるので最初に見つかったものを採用する
+        for (result, path) in results {
+            match result {
+                Bound::Found => {
+                    self.ans = Some(path);
+                    return Ok(());
+                }
+                Bound::Over(Some(x)) => min = Some(min.map_or(x, |m: u32| m.min(x))),
+                Bound::Over(None) => (),
+            }
+        }
+        match min {
+            Some(next) => {
+                self.threshold = next;
+                Ok(())
+            }
+            None => Err(PuzzleError::new("not found path")),
+        }
+    }
+    /// par_searchを解が見つかるまで繰り返す
+    pub fn par_run(&mut self) -> PuzzleResult<()> {
+        while !self.has_finished()? {
+            self.par_search()?;
+        }
+        Ok(())
+    }
+
+    /// 深さPAR_DEPTHの盤面(それより浅くてもゴールの時はその盤面)を探索順にtasksに集める
+    /// 閾値を超えたものはminを更新する
+    #[allow(clippy::too_many_arguments)]
+    fn frontier(
+        &self,
+        board: &mut [Data],
+        blank: usize,
+        h: u32,
+        bound: u32,
+        path: &mut Vec<Direction>,
+        tasks: &mut Vec<Task>,
+        min: &mut Option<u32>,
+    ) {
+        let f = path.len() as u32 + h;
+        if f > bound {
+            *min = Some(min.map_or(f, |m| m.min(f)));
+            return;
+        }
+        if path.len() == PAR_DEPTH || (h == 0 && self.is_goal(board)) {
+            tasks.push((board.to_vec(), blank, h, path.clone()));
+            return;
+        }
+        for dir in Direction::ALL {
+            if path.last() == Some(&dir.reverse()) {
+                continue;
+            }
+            let next = match self.neighbor(blank, dir) {
+                Some(x) => x,
+                None => continue,
+            };
+            let val = board[next];
+            board.swap(blank, next);
+            let next_h = self.heuristic.update(board, h, val, next, blank);
+            path.push(dir);
+            self.frontier(board, next, next_h, bound, path, tasks, min);
+            path.pop();
+            board.swap(blank, next);
+        }
+    }
+
+    /// IDA*の深さ優先探索
+    /// g: ここまでの手数, h: 現在の盤面のヒューリスティック値
+    /// abortがtrueを返したら探索を打ち切る
+    #[allow(clippy::too_many_arguments)]
+    fn dfs<F: Fn() -> bool>(
+        &self,
+        board: &mut [Data],
+        blank: usize,
+        g: u32,
+        h: u32,
+        bound: u32,
+        path: &mut Vec<Direction>,
+        abort: &F,
+    ) -> Bound {
+        if abort() {
+            return Bound::Over(None);
+        }
+        let f = g + h;
+        if f > bound {
+            return Bound::Over(Some(f));
+        }
+        if h == 0 && self.is_goal(board) {
+            return Bound::Found;
+        }
+        let mut min = None;
+        for dir in Direction::ALL {
+            // 直前の移動を打ち消す移動は探索しない
+            if path.last() == Some(&dir.reverse()) {
+                continue;
+            }
+            let next = match self.neighbor(blank, dir) {
+                Some(x) => x,
+                None => continue,
+            };
+            // 動くタイルはnextからblankへ移動する
+            let val = board[next];
+            board.swap(blank, next);
+            let next_h = self.heuristic.update(board, h, val, next, blank);
+            path.push(dir);
+            match self.dfs(board, next, g + 1, next_h, bound, path, abort) {
+                Bound::Found => return Bound::Found,
+                Bound::Over(Some(x)) => min = Some(min.map_or(x, |m: u32| m.min(x))),
+                Bound::Over(None) => (),
+            }
+            path.pop();
+            board.swap(blank, next);
+        }
+        Bound::Over(min)
+    }
+}
+
+impl Solver for FifteenPuzzleSolver {
+    fn has_finished(&self) -> PuzzleResult<bool> {
+        Ok(self.ans.is_some())
+    }
+    /// 現在の閾値でIDA*を一回反復する
+    /// 見つからなければ閾値を次の値に更新する
+    fn search(&mut self) -> PuzzleResult<()> {
+        if self.ans.is_some() {
+            return Ok(());
+        }
+        if !self.is_solvable() {
+            return Err(PuzzleError::new("unsolvable board"));
+        }
+        let mut board = self.board.clone();
+        let mut path = Vec::new();
+        let h = self.heuristic.estimate(&board);
+        match self.dfs(
+            &mut board,
+            self.blank,
+            0,
+            h,
+            self.threshold,
+            &mut path,
+            &|| false,
+        ) {
+            Bound::Found => {
+                self.ans = Some(path);
+                Ok(())
+            }
+            Bound::Over(Some(next)) => {
+                self.threshold = next;
+                Ok(())
+            }
+            Bound::Over(None) => Err(PuzzleError::new("not found path")),
+        }
+    }
+}
+
+impl Display for FifteenPuzzleSolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // 一番大きい数字の桁数で揃える
+        let digit = (self.width * self.height - 1).to_string().len();
+        for row in self.board.chunks(self.width) {
+            let line: Vec<String> = row
+                .iter()
+                .map(|&val| match val {
+                    0 => format!("{:>1$}", "*", digit),
+                    val => format!("{:>1$}", val, digit),
+                })
+                .collect();
+            writeln!(f, "{}", line.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::heuristic::{LinearConflict, PatternDatabase, WalkingDistance};
+    use super::{Direction, FifteenPuzzleSolver};
+    use crate::solver::Solver;
+
+    /// 盤面に手順を適用する
+    fn apply(solver: &FifteenPuzzleSolver, path: &[Direction]) -> Vec<usize> {
+        let mut board = solver.board.clone();
+        let mut blank = solver.blank;
+        for &dir in path {
+            let next = solver.neighbor(blank, dir).unwrap();
+            board.swap(blank, next);
+            blank = next;
+        }
+        board
+    }
+
+    #[test]
+    fn test_solved() {
+        let mut solver = FifteenPuzzleSolver::new(vec![
+            vec![1, 2, 3, 4],
+            vec![5, 6, 7, 8],
+            vec![9, 10, 11, 12],
+            vec![13, 14, 15, 0],
+        ]);
+        solver.run().unwrap();
+        assert_eq!(solver.ans, Some(vec![]));
+    }
+    #[test]
+    fn test_easy() {
+        let mut solver = FifteenPuzzleSolver::new(vec![
+            vec![1, 2, 3, 4],
+            vec![5, 6, 0, 8],
+            vec![9, 10, 7, 11],
+            vec![13, 14, 15, 12],
+        ]);
+        solver.run().unwrap();
+        assert_eq!(
+            solver.ans,
+            Some(vec![Direction::DOWN, Direction::RIGHT, Direction::DOWN])
+        );
+    }
+    #[test]
+    fn test_optimal() {
+        // 最短手数が知られている盤面
+        let mut solver = FifteenPuzzleSolver::new(vec![
+            vec![5, 1, 3, 4],
+            vec![9, 2, 7, 8],
+            vec![13, 6, 10, 11],
+            vec![0, 14, 15, 12],
+        ]);
+        solver.run().unwrap();
+        let path = solver.ans.clone().unwrap();
+        let goal = solver.goal();
+        assert_eq!(apply(&solver, &path), goal);
+        assert_eq!(path.len(), 9);
+
+        let mut solver = FifteenPuzzleSolver::new(vec![
+            vec![0, 2, 4, 8],
+            vec![1, 7, 3, 6],
+            vec![10, 5, 11, 12],
+            vec![9, 14, 13, 15],
+        ]);
+        assert!(solver.is_solvable());
+        while !solver.has_finished().unwrap() {
+            solver.search().unwrap();
+        }
+        let path = solver.ans.clone().unwrap();
+        assert_eq!(apply(&solver, &path), goal);
+        assert_eq!(path.len(), 28);
+    }
+    #[test]
+    fn test_unsolvable() {
+        let mut solver = FifteenPuzzleSolver::new(vec![
+            vec![1, 2, 3, 4],
+            vec![5, 6, 7, 8],
+            vec![9, 10, 11, 12],
+            vec![13, 15, 14, 0],
+        ]);
+        assert!(!solver.is_solvable());
+        assert!(solver.run().is_err());
+    }
+    #[test]
+    fn test_eight_puzzle() {
+        // 8パズルで最も手数がかかる盤面
+        let mut solver =
+            FifteenPuzzleSolver::new(vec![vec![8, 6, 7], vec![2, 5, 4], vec![3, 0, 1]]);
+        solver.run().unwrap();
+        let path = solver.ans.clone().unwrap();
+        assert_eq!(apply(&solver, &path), solver.goal());
+        assert_eq!(path.len(), 31);
+
+        // 幅が奇数の時は転倒数の偶奇のみで決まる
+        let mut solver =
+            FifteenPuzzleSolver::new(vec![vec![2, 1, 3], vec![4, 5, 6], vec![7, 8, 0]]);
+        assert!(!solver.is_solvable());
+        assert!(solver.run().is_err());
+    }
+    #[test]
+    fn test_rectangle() {
+        let mut solver =
+            FifteenPuzzleSolver::new(vec![vec![9, 3, 7, 2], vec![6, 10, 0, 5], vec![11, 1, 8, 4]]);
+        assert!(solver.is_solvable());
+        solver.run().unwrap();
+        let path = solver.ans.clone().unwrap();
+        assert_eq!(apply(&solver, &path), solver.goal());
+        assert_eq!(path.len(), 32);
+
+        let solver =
+            FifteenPuzzleSolver::new(vec![vec![3, 9, 7, 2], vec![6, 10, 0, 5], vec![11, 1, 8, 4]]);
+        assert!(!solver.is_solvable());
+    }
+    #[test]
+    #[should_panic]
+    fn test_invalid_board() {
+        FifteenPuzzleSolver::new(vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8]]);
+    }
+    #[test]
+    fn test_heuristics() {
+        let board = vec![
+            vec![0, 2, 4, 8],
+            vec![1, 7, 3, 6],
+            vec![10, 5, 11, 12],
+            vec![9, 14, 13, 15],
+        ];
+        let mut solver = FifteenPuzzleSolver::new(board.clone());
+        solver.set_heuristic(LinearConflict::new(4, 4));
+        solver.run().unwrap();
+        assert_eq!(solver.ans.clone().unwrap().len(), 28);
+
+        let mut solver = FifteenPuzzleSolver::new(board.clone());
+        solver.set_heuristic(WalkingDistance::new(4, 4));
+        solver.run().unwrap();
+        assert_eq!(solver.ans.clone().unwrap().len(), 28);
+
+        let mut solver = FifteenPuzzleSolver::new(board);
+        let groups = vec![
+            vec![1, 2, 3],
+            vec![4, 5, 6],
+            vec![7, 8, 9],
+            vec![10, 11, 12],
+            vec![13, 14, 15],
+        ];
+        solver.set_heuristic(PatternDatabase::build(4, 4, groups));
+        solver.run().unwrap();
+        let path = solver.ans.clone().unwrap();
+        assert_eq!(apply(&solver, &path), solver.goal());
+        assert_eq!(path.len(), 28);
+    }
+    #[test]
+    fn test_step() {
+        let board = vec![vec![8, 6, 7], vec![2, 5, 4], vec![3, 0, 1]];
+        let mut solver = FifteenPuzzleSolver::new(board.clone());
+        assert!(solver.step_forward().is_err());
+        solver.run().unwrap();
+        let ans = solver.ans.clone().unwrap();
+
+        for (i, &dir) in ans.iter().enumerate() {
+            assert_eq!(solver.remaining(), Some(ans.len() - i));
+            assert_eq!(solver.step_forward().unwrap(), dir);
+        }
+        assert_eq!(solver.board, solver.goal());
+        assert!(solver.step_forward().is_err());
+        assert_eq!(solver.history, ans);
+
+        for &dir in ans.iter().rev() {
+            assert_eq!(solver.step_backward().unwrap(), dir);
+        }
+        assert!(solver.step_backward().is_err());
+        assert_eq!(solver.board, FifteenPuzzleSolver::new(board).board);
+        assert!(solver.history.is_empty());
+    }
+    #[test]
+    fn test_apply_moves() {
+        let mut solver =
+            FifteenPuzzleSolver::new(vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 0]]);
+        solver.apply_moves("UL UL").unwrap();
+        assert_eq!(solver.board, vec![0, 1, 3, 4, 2, 5, 7, 8, 6]);
+        assert_eq!(solver.ans_string(), None);
+        assert_eq!(format!("{}", solver), "* 1 3\n4 2 5\n7 8 6\n");
+
+        // 動かせない操作の時は盤面は変わらない
+        assert!(solver.apply_moves("RRR").is_err());
+        assert!(solver.apply_moves("RX").is_err());
+        assert!(solver.move_blank(Direction::UP).is_err());
+        assert_eq!(solver.board, vec![0, 1, 3, 4, 2, 5, 7, 8, 6]);
+
+        solver.run().unwrap();
+        assert_eq!(solver.ans_string(), Some("RDRD".to_string()));
+
+        assert_eq!(solver.undo().unwrap(), Direction::LEFT);
+        assert_eq!(solver.ans, None);
+        solver.run().unwrap();
+        assert_eq!(solver.ans_string(), Some("DRD".to_string()));
+        solver.step_forward().unwrap();
+        // 最短手順の途中でundoすると最短手順を一手戻る
+        assert_eq!(solver.undo().unwrap(), Direction::DOWN);
+        assert_eq!(solver.remaining(), Some(3));
+    }
+    #[test]
+    fn test_par_run() {
+        for (distance, seed) in [(0, 0), (3, 1), (12, 2), (26, 3), (34, 4)] {
+            let mut solver = FifteenPuzzleSolver::with_distance(4, 4, distance, seed).unwrap();
+            let mut par_solver = FifteenPuzzleSolver::new(
+                solver
+                    .board
+                    .chunks(4)
+                    .map(|row| row.to_vec())
+                    .collect::<Vec<_>>(),
+            );
+            solver.run().unwrap();
+            par_solver.par_run().unwrap();
+            assert_eq!(solver.ans, par_solver.ans);
+            assert_eq!(par_solver.ans.unwrap().len(), distance);
+        }
+        let mut solver =
+            FifteenPuzzleSolver::new(vec![vec![8, 6, 7], vec![2, 5, 4], vec![3, 0, 1]]);
+        solver.par_run().unwrap();
+        assert_eq!(solver.ans.unwrap().len(), 31);
+
+        let mut solver =
+            FifteenPuzzleSolver::new(vec![vec![2, 1, 3], vec![4, 5, 6], vec![7, 8, 0]]);
+        assert!(solver.par_run().is_err());
+    }
+}
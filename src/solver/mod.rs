@@ -1,9 +1,12 @@
 use std::fmt::Display;
 
+pub mod dlx;
 pub mod fifteen_puzzle;
 pub mod hanoi;
 pub mod nquene;
 pub mod pentomino;
+mod random;
+pub mod rubiks;
 pub mod sudoku;
 
 #[derive(Debug)]
@@ -5,15 +5,21 @@ use std::collections::BTreeSet;
 use std::fmt::Display;
 use std::hash::Hash;
 
+use rayon::prelude::*;
+
+use super::dlx::DancingLinks;
 use super::PuzzleResult;
 use super::Solver;
 
+/// par_run_allで独立な部分問題に分ける時に先に置くブロックの数
+const PAR_DEPTH: usize = 2;
+
 /// 左上のブロックを基準にピースを配置する
 /// ------>x
 /// |
 /// ↓
 /// y
-#[derive(Debug, Hash, PartialOrd, Ord)]
+#[derive(Debug, Clone, Hash, PartialOrd, Ord)]
 pub struct Block {
     block: Vec<(i32, i32)>,
 }
@@ -111,7 +117,7 @@ pub enum TargetType {
 }
 
 /// ブロックの反転や回転を一つにまとめたもの
-#[derive(Debug, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct TargetBlock {
     block: Vec<Block>,
     id: char,
@@ -171,7 +177,7 @@ impl TargetBlock {
 type Field = Vec<Vec<Option<char>>>;
 /// ペントミノのソルば
 /// ガチガチの最適化はしてない(似たようなパズルも解けるように)
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PentominoSolver {
     blocks: Vec<TargetBlock>,
     // field[h][l]
@@ -349,6 +355,41 @@ impl PentominoSolver {
     pub fn run_all(&self) -> u32 {
         self._run_all(0, 0)
     }
+    /// run_allを並列に行う，個数はrun_allと同じ
+    /// 左上からPAR_DEPTH個のブロックの置き方で分け，fieldとused flagを複製した部分問題をrayonで数える
+    pub fn par_run_all(&self) -> u32 {
+        let mut tasks = Vec::new();
+        self.split(PAR_DEPTH, 0, 0, &mut tasks);
+        tasks
+            .into_par_iter()
+            .map(|(solver, h, l)| solver._run_all(h, l))
+            .sum()
+    }
+    /// _run_allと同じ順番でdepth個のブロックを置き，その時点の複製と次に探す位置をtasksに入れる
+    fn split(&self, depth: usize, pre_h: i32, pre_l: i32, tasks: &mut Vec<(Self, i32, i32)>) {
+        let (h, l) = match self.find_upper_left(pre_h, pre_l) {
+            Some(x) if depth > 0 => x,
+            _ => {
+                // 埋まっていれば複製した方の_run_allが1を返す
+                tasks.push((self.clone(), pre_h, pre_l));
+                return;
+            }
+        };
+        for targetblock in &self.blocks {
+            if *targetblock.used.borrow() {
+                continue;
+            }
+            *targetblock.used.borrow_mut() = true;
+            for one_kind_block in &targetblock.block {
+                if self.check(one_kind_block, h, l) {
+                    self.place(one_kind_block, targetblock.id, h, l);
+                    self.split(depth - 1, h, l, tasks);
+                    self.place_back(one_kind_block, h, l);
+                }
+            }
+            *targetblock.used.borrow_mut() = false;
+        }
+    }
 
     // 左上の空白を探す，前回の空白の位置をヒントにできる
     fn find_upper_left(&self, pre_h: i32, pre_l: i32) -> Option<(i32, i32)> {
@@ -429,6 +470,70 @@ impl PentominoSolver {
         }
         false
     }
+
+    /// Dancing Linksで解の個数を数える，run_allと同じ個数になる
+    pub fn dlx_run_all(&self) -> u32 {
+        let (mut dlx, _) = self.exact_cover();
+        dlx.count_solutions(usize::MAX) as u32
+    }
+    /// Dancing Linksで正解を一つ得てfieldに配置する
+    pub fn dlx_search_one_ans(&self) -> bool {
+        let (mut dlx, placements) = self.exact_cover();
+        let rows = match dlx.first_solution() {
+            Some(rows) => rows,
+            None => return false,
+        };
+        for row in rows {
+            let (ind, kind, h, l) = placements[row];
+            let targetblock = &self.blocks[ind];
+            self.place(&targetblock.block[kind], targetblock.id, h, l);
+            *targetblock.used.borrow_mut() = true;
+        }
+        true
+    }
+    /// 完全被覆問題と，その行ごとの(ブロックの番号, 形の番号, h, l)
+    /// 空白のマスごとの列はちょうど一度，ブロックごとの列は高々一度覆う(使わないブロックがあってもいい)
+    fn exact_cover(&self) -> (DancingLinks, Vec<(usize, usize, i32, i32)>) {
+        let field = self.field.borrow().clone();
+        let mut cells = vec![vec![None; field[0].len()]; field.len()];
+        let mut vacant = 0;
+        for (h, vec) in field.iter().enumerate() {
+            for (l, c) in vec.iter().enumerate() {
+                if c.is_none() {
+                    cells[h][l] = Some(vacant);
+                    vacant += 1;
+                }
+            }
+        }
+        let mut dlx = DancingLinks::new(vacant, self.blocks.len());
+        let mut placements = Vec::new();
+        for (ind, targetblock) in self.blocks.iter().enumerate() {
+            if *targetblock.used.borrow() {
+                continue;
+            }
+            for (kind, one_kind_block) in targetblock.block.iter().enumerate() {
+                for (h, vec) in cells.iter().enumerate() {
+                    for (l, cell) in vec.iter().enumerate() {
+                        let (h, l) = (h as i32, l as i32);
+                        let cell = match cell {
+                            Some(cell) => *cell,
+                            None => continue,
+                        };
+                        if !self.check(one_kind_block, h, l) {
+                            continue;
+                        }
+                        let mut columns = vec![cell, vacant + ind];
+                        for &(dx, dy) in &one_kind_block.block {
+                            columns.push(cells[(h + dy) as usize][(l + dx) as usize].unwrap());
+                        }
+                        dlx.add_row(&columns).unwrap();
+                        placements.push((ind, kind, h, l));
+                    }
+                }
+            }
+        }
+        (dlx, placements)
+    }
 }
 
 impl Solver for PentominoSolver {
@@ -452,6 +557,7 @@ impl Solver for PentominoSolver {
 #[cfg(test)]
 mod test {
     use super::{Block, PentominoSolver, TargetBlock, TargetType};
+    use crate::solver::Solver;
     /// Block tests
     #[test]
     fn pentomino_block_test() {
@@ -482,6 +588,45 @@ mod test {
         assert_eq!(cnt, 2339);
     }
     #[test]
+    fn pentomino_par_test() {
+        let solver = PentominoSolver::new(6, 10);
+        assert_eq!(solver.par_run_all(), 2339);
+        // 元の盤面は変わらない
+        assert_eq!(solver, PentominoSolver::new(6, 10));
+
+        let solver = PentominoSolver::from_vec(vec![("11", 2, 1); 4], TargetType::ROTATE, 2, 4);
+        assert_eq!(solver.par_run_all(), solver.run_all());
+        // 分ける前に埋まる盤面
+        let solver = PentominoSolver::from_vec(vec![("11", 2, 1); 4], TargetType::ROTATE, 1, 2);
+        assert_eq!(solver.par_run_all(), solver.run_all());
+        let solver = PentominoSolver::new(1, 4);
+        assert_eq!(solver.par_run_all(), 0);
+    }
+    #[test]
+    fn pentomino_dlx_test() {
+        // run_allと同じ個数
+        let solver = PentominoSolver::new(6, 10);
+        assert_eq!(solver.dlx_run_all(), 2339);
+        let solver = PentominoSolver::new(3, 20);
+        assert_eq!(solver.dlx_run_all(), 2);
+        assert!(solver.dlx_search_one_ans());
+        assert!(solver.has_finished().unwrap());
+
+        // 2x4を4つのドミノで敷き詰める，置いてあるブロックと使ったブロックは除いて数える
+        let solver = PentominoSolver::from_vec(vec![("11", 2, 1); 4], TargetType::ROTATE, 2, 4);
+        assert_eq!(solver.dlx_run_all(), solver.run_all());
+        let targetblock = &solver.blocks[0];
+        *targetblock.used.borrow_mut() = true;
+        solver.place(&targetblock.block[0], targetblock.id, 0, 0);
+        assert_eq!(solver.dlx_run_all(), solver.run_all());
+        assert!(solver.dlx_run_all() > 0);
+
+        // 埋められない盤面
+        let solver = PentominoSolver::new(1, 4);
+        assert_eq!(solver.dlx_run_all(), 0);
+        assert!(!solver.dlx_search_one_ans());
+    }
+    #[test]
     fn pentomino_place_test() {
         let solver = PentominoSolver::new(6, 10);
         let block = Block::new("110111", 3, 2);
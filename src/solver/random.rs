@@ -0,0 +1,68 @@
+//! シードから再現できる乱数
+//! ベンチマークや問題生成で同じ結果を得るために使う(xorshift64*)
+
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        // 0だと同じ値しか出ないのでsplitmix64で混ぜる
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        Random { state: z.max(1) }
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+    /// [0, n)の一様な乱数
+    pub fn gen_range(&mut self, n: usize) -> usize {
+        assert!(n > 0);
+        let n = n as u64;
+        // 偏りが出ないようにnの倍数に収まらない部分は捨てる
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return (x % n) as usize;
+            }
+        }
+    }
+    /// Fisher-Yatesで並び替える
+    pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+        for i in (1..slice.len()).rev() {
+            let j = self.gen_range(i + 1);
+            slice.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Random;
+
+    #[test]
+    fn test_random() {
+        let mut a = Random::new(0);
+        let mut b = Random::new(0);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        let mut count = [0; 6];
+        for _ in 0..6000 {
+            count[a.gen_range(6)] += 1;
+        }
+        assert!(count.iter().all(|&c| c > 800 && c < 1200), "{:?}", count);
+
+        let mut vec: Vec<usize> = (0..10).collect();
+        a.shuffle(&mut vec);
+        vec.sort();
+        assert_eq!(vec, (0..10).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,415 @@
+//! 任意の大きさ(NxNxN)のキューブ
+//! ステッカー(フェイスレット)の色を並べて表し，大きさは実行時に決める
+//! ステッカーの並びは3x3x3と同じくURFDLBの順で，各面は左上から行ごとに並べる
+
+use std::fmt::Display;
+
+use super::super::{PuzzleError, PuzzleResult};
+use super::{Face, Move};
+
+/// 一つの層を回す操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LayerMove {
+    face: Face,
+    /// 面faceから数えた層の番号(0が一番外側)
+    layer: usize,
+    /// 時計回りに90度回す回数(1..=3)
+    turns: u8,
+}
+
+impl LayerMove {
+    pub fn new(face: Face, layer: usize, turns: u8) -> Self {
+        assert!((1..=3).contains(&turns));
+        LayerMove { face, layer, turns }
+    }
+    pub fn face(&self) -> Face {
+        self.face
+    }
+    pub fn layer(&self) -> usize {
+        self.layer
+    }
+    pub fn turns(&self) -> u8 {
+        self.turns
+    }
+    /// 逆操作
+    pub fn inverse(&self) -> Self {
+        LayerMove::new(self.face, self.layer, 4 - self.turns)
+    }
+}
+
+impl From<Move> for LayerMove {
+    fn from(m: Move) -> Self {
+        LayerMove::new(m.face(), 0, m.turns())
+    }
+}
+
+impl Display for LayerMove {
+    /// 外側の層はR，内側の層は2R, 3Rのように書く
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.layer > 0 {
+            write!(f, "{}", self.layer + 1)?;
+        }
+        match self.turns {
+            1 => write!(f, "{}", self.face),
+            2 => write!(f, "{}2", self.face),
+            _ => write!(f, "{}'", self.face),
+        }
+    }
+}
+
+/// "Rw U 2R' 3Fw2"のような手順を読み込む
+/// 数字のみは内側の一つの層，wは外側からその層までをまとめて回す(数字がなければ2層)
+/// 層はsize x size x sizeのキューブにあるものに限る
+pub fn parse_layer_moves(str: &str, size: usize) -> PuzzleResult<Vec<LayerMove>> {
+    let mut moves = Vec::new();
+    for token in str.split_whitespace() {
+        let invalid = || PuzzleError::new(format!("invalid move: {}", token));
+        let digits = token.chars().take_while(|c| c.is_ascii_digit()).count();
+        let depth: Option<usize> = if digits > 0 {
+            Some(token[..digits].parse().map_err(|_| invalid())?)
+        } else {
+            None
+        };
+        let mut chars = token[digits..].chars();
+        let face = chars.next().and_then(Face::from_char).ok_or_else(invalid)?;
+        let mut rest = chars.as_str();
+        let wide = rest.starts_with('w');
+        if wide {
+            rest = &rest[1..];
+        }
+        let turns = match rest {
+            "" => 1,
+            "2" | "2'" => 2,
+            "'" => 3,
+            _ => return Err(invalid()),
+        };
+        // 外側から数えた層の深さ，大きなキューブ用の手順で層を並べる前に確かめる
+        let depth = depth.unwrap_or(if wide { 2 } else { 1 });
+        if depth == 0 {
+            return Err(invalid());
+        }
+        if depth > size {
+            return Err(PuzzleError::new(format!(
+                "invalid move for {}x{}x{}: {}",
+                size, size, size, token
+            )));
+        }
+        if wide {
+            for layer in 0..depth {
+                moves.push(LayerMove::new(face, layer, turns));
+            }
+        } else {
+            moves.push(LayerMove::new(face, depth - 1, turns));
+        }
+    }
+    Ok(moves)
+}
+
+/// 手順を空白区切りの文字列にする
+pub fn layer_moves_to_string(moves: &[LayerMove]) -> String {
+    moves
+        .iter()
+        .map(|m| m.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 続けて同じ層を回す操作をまとめる
+pub fn simplify(moves: &[LayerMove]) -> Vec<LayerMove> {
+    let mut result: Vec<LayerMove> = Vec::with_capacity(moves.len());
+    for &m in moves {
+        match result.last() {
+            Some(last) if last.face == m.face && last.layer == m.layer => {
+                let turns = (last.turns + m.turns) % 4;
+                result.pop();
+                if turns != 0 {
+                    result.push(LayerMove::new(m.face, m.layer, turns));
+                }
+            }
+            _ => result.push(m),
+        }
+    }
+    result
+}
+
+/// 3次元の座標(x: 右, y: 上, z: 前)
+/// パーツの中心は各軸-(n-1)..=(n-1)の2刻みに置く
+type Vec3 = [i32; 3];
+
+/// 面の外向きの法線
+fn normal(face: Face) -> Vec3 {
+    match face {
+        Face::U => [0, 1, 0],
+        Face::R => [1, 0, 0],
+        Face::F => [0, 0, 1],
+        Face::D => [0, -1, 0],
+        Face::L => [-1, 0, 0],
+        Face::B => [0, 0, -1],
+    }
+}
+
+/// 面faceから見て時計回りに90度回す
+fn rotate(face: Face, [x, y, z]: Vec3) -> Vec3 {
+    match face {
+        Face::U => [-z, y, x],
+        Face::D => [z, y, -x],
+        Face::R => [x, z, -y],
+        Face::L => [x, -z, y],
+        Face::F => [y, -x, z],
+        Face::B => [-y, x, z],
+    }
+}
+
+/// NxNxNのキューブ
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cube {
+    size: usize,
+    /// 各ステッカーの色
+    facelets: Vec<Face>,
+}
+
+impl Cube {
+    /// 揃った状態
+    pub fn solved(size: usize) -> Self {
+        assert!(size >= 2);
+        let facelets = Face::ALL
+            .iter()
+            .flat_map(|&face| std::iter::repeat_n(face, size * size))
+            .collect();
+        Cube { size, facelets }
+    }
+    /// URFDLBの順に並べた6*size*size文字のフェイスレット文字列から作る
+    /// 色の数だけを確かめ，実際に回して作れる状態かはソルバーで確かめる
+    pub fn from_facelets(size: usize, str: &str) -> PuzzleResult<Self> {
+        if size < 2 {
+            return Err(PuzzleError::new(format!("invalid cube size: {}", size)));
+        }
+        let mut facelets = Vec::with_capacity(6 * size * size);
+        for c in str.chars().filter(|c| !c.is_whitespace()) {
+            let face = Face::from_char(c).ok_or_else(|| {
+                PuzzleError::new(format!(
+                    "invalid facelet: '{}': it must be one of URFDLB",
+                    c
+                ))
+            })?;
+            facelets.push(face);
+        }
+        if facelets.len() != 6 * size * size {
+            return Err(PuzzleError::new(format!(
+                "invalid facelet length: {}: it must be {}",
+                facelets.len(),
+                6 * size * size
+            )));
+        }
+        for face in Face::ALL {
+            let count = facelets.iter().filter(|&&x| x == face).count();
+            if count != size * size {
+                return Err(PuzzleError::new(format!(
+                    "invalid facelet count: {:?} appears {} times",
+                    face, count
+                )));
+            }
+        }
+        Ok(Cube { size, facelets })
+    }
+    /// 揃った状態にscrambleを適用したキューブ
+    pub fn from_scramble(size: usize, scramble: &str) -> PuzzleResult<Self> {
+        let mut cube = Self::solved(size);
+        let moves = parse_layer_moves(scramble, size)?;
+        cube.apply_all(&moves);
+        Ok(cube)
+    }
+    pub fn size(&self) -> usize {
+        self.size
+    }
+    pub fn facelets(&self) -> &[Face] {
+        &self.facelets
+    }
+    /// フェイスレット文字列にする
+    pub fn to_facelets(&self) -> String {
+        self.facelets.iter().map(|face| face.to_string()).collect()
+    }
+    /// 全ての面が一色か
+    pub fn is_solved(&self) -> bool {
+        self.facelets
+            .chunks(self.size * self.size)
+            .all(|face| face.iter().all(|&x| x == face[0]))
+    }
+
+    /// 面faceのrow行column列のステッカーの番号
+    pub fn index(&self, face: Face, row: usize, column: usize) -> usize {
+        (face as usize * self.size + row) * self.size + column
+    }
+    /// ステッカーの面
+    pub fn face_of(&self, index: usize) -> Face {
+        Face::ALL[index / (self.size * self.size)]
+    }
+    /// ステッカーが貼られているパーツの中心の座標
+    pub(super) fn position(&self, index: usize) -> Vec3 {
+        let n = self.size as i32;
+        let face = self.face_of(index);
+        let row = (index / self.size % self.size) as i32;
+        let column = (index % self.size) as i32;
+        // 面の左上から右と下に進む向き
+        let (a, b) = (-(n - 1) + 2 * column, (n - 1) - 2 * row);
+        match face {
+            Face::U => [a, n - 1, -b],
+            Face::R => [n - 1, b, -a],
+            Face::F => [a, b, n - 1],
+            Face::D => [a, -(n - 1), b],
+            Face::L => [-(n - 1), b, a],
+            Face::B => [-a, b, -(n - 1)],
+        }
+    }
+    /// パーツの中心の座標と面からステッカーの番号を求める
+    pub(super) fn index_at(&self, face: Face, [x, y, z]: Vec3) -> usize {
+        let n = self.size as i32;
+        let (a, b) = match face {
+            Face::U => (x, -z),
+            Face::R => (-z, y),
+            Face::F => (x, y),
+            Face::D => (x, z),
+            Face::L => (z, y),
+            Face::B => (-x, y),
+        };
+        let column = ((a + n - 1) / 2) as usize;
+        let row = ((n - 1 - b) / 2) as usize;
+        self.index(face, row, column)
+    }
+    /// 操作mでステッカーiが移る先perm[i]
+    pub(super) fn move_permutation(&self, m: LayerMove) -> Vec<usize> {
+        let n = self.size as i32;
+        let axis = normal(m.face);
+        let depth = (n - 1) - 2 * m.layer as i32;
+        (0..self.facelets.len())
+            .map(|i| {
+                let mut pos = self.position(i);
+                let dot: i32 = (0..3).map(|k| pos[k] * axis[k]).sum();
+                if dot != depth {
+                    return i;
+                }
+                let mut dir = normal(self.face_of(i));
+                for _ in 0..m.turns {
+                    pos = rotate(m.face, pos);
+                    dir = rotate(m.face, dir);
+                }
+                let face = Face::ALL
+                    .iter()
+                    .copied()
+                    .find(|&face| normal(face) == dir)
+                    .unwrap();
+                self.index_at(face, pos)
+            })
+            .collect()
+    }
+    /// ステッカーの並べ替えを適用する
+    pub(super) fn permute(&mut self, perm: &[usize]) {
+        let mut facelets = self.facelets.clone();
+        for (i, &j) in perm.iter().enumerate() {
+            facelets[j] = self.facelets[i];
+        }
+        self.facelets = facelets;
+    }
+    /// 一手回す
+    pub fn apply(&mut self, m: LayerMove) {
+        assert!(m.layer < self.size);
+        let perm = self.move_permutation(m);
+        self.permute(&perm);
+    }
+    /// 手順を順に適用する
+    pub fn apply_all(&mut self, moves: &[LayerMove]) {
+        for &m in moves {
+            self.apply(m);
+        }
+    }
+    /// 色を付け替える
+    pub(super) fn recolor(&mut self, scheme: &[Face; 6]) {
+        for face in self.facelets.iter_mut() {
+            *face = scheme[*face as usize];
+        }
+    }
+}
+
+impl Display for Cube {
+    /// 展開図で表示する
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let n = self.size;
+        let row = |face: Face, r: usize| -> String {
+            (0..n)
+                .map(|c| self.facelets[self.index(face, r, c)].to_string())
+                .collect()
+        };
+        let pad = " ".repeat(n);
+        for r in 0..n {
+            writeln!(f, "{}{}", pad, row(Face::U, r))?;
+        }
+        for r in 0..n {
+            writeln!(
+                f,
+                "{}{}{}{}",
+                row(Face::L, r),
+                row(Face::F, r),
+                row(Face::R, r),
+                row(Face::B, r)
+            )?;
+        }
+        for r in 0..n {
+            writeln!(f, "{}{}", pad, row(Face::D, r))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::cubie::CubieCube;
+    use super::super::parse_moves;
+    use super::{layer_moves_to_string, parse_layer_moves, simplify, Cube, LayerMove};
+
+    #[test]
+    fn test_same_as_cubie() {
+        let moves = parse_moves("R U2 F' L D B' R2 U' F").unwrap();
+        let mut cubie = CubieCube::solved();
+        let mut cube = Cube::solved(3);
+        for &m in &moves {
+            cubie.apply(m);
+            cube.apply(LayerMove::from(m));
+            assert_eq!(cube.to_facelets(), cubie.to_facelets());
+        }
+        let parsed = Cube::from_facelets(3, &cube.to_facelets()).unwrap();
+        assert_eq!(parsed, cube);
+        assert!(Cube::from_facelets(3, "UUU").is_err());
+    }
+    #[test]
+    fn test_layer_moves() {
+        let moves = parse_layer_moves("Rw U' 2F2 3Lw' B", 3).unwrap();
+        assert_eq!(layer_moves_to_string(&moves), "R 2R U' 2F2 L' 2L' 3L' B");
+        assert!(parse_layer_moves("0R", 3).is_err());
+        assert!(parse_layer_moves("Rx", 3).is_err());
+        assert!(parse_layer_moves("4Rw", 3).is_err());
+        // 層を並べる前に大きさで弾く
+        assert!(parse_layer_moves("30000000Rw", 7).is_err());
+        assert!(Cube::from_scramble(3, "4R").is_err());
+
+        for size in 2..=6 {
+            let mut cube = Cube::solved(size);
+            // 各層を4回ずつ回すと戻る
+            let moves: Vec<_> = (0..size)
+                .map(|layer| LayerMove::new(super::Face::F, layer, 1))
+                .collect();
+            for _ in 0..4 {
+                cube.apply_all(&moves);
+            }
+            assert!(cube.is_solved());
+            // Rから数えて一番奥の層はL'と同じ
+            let mut a = Cube::solved(size);
+            a.apply(LayerMove::new(super::Face::R, size - 1, 1));
+            let mut b = Cube::solved(size);
+            b.apply(LayerMove::new(super::Face::L, 0, 3));
+            assert_eq!(a, b);
+            assert!(!a.is_solved());
+        }
+        let moves = parse_layer_moves("R R 2U 2U' F2 F2 L", 3).unwrap();
+        assert_eq!(layer_moves_to_string(&simplify(&moves)), "R2 L");
+    }
+}
@@ -0,0 +1,419 @@
+//! キューブをコーナー8個とエッジ12個の位置と向きで表す
+//! 各パーツの番号や面の番号はKociembaの定義にそろえている
+
+use std::fmt::Display;
+
+use super::super::{PuzzleError, PuzzleResult};
+use super::{Face, Move};
+
+/// コーナーの番号 URF, UFL, ULB, UBR, DFR, DLF, DBL, DRB
+pub const CORNER_NUM: usize = 8;
+/// エッジの番号 UR, UF, UL, UB, DR, DF, DL, DB, FR, FL, BL, BR
+pub const EDGE_NUM: usize = 12;
+
+/// 各コーナーのステッカーのフェイスレット番号(U/Dの面から時計回り)
+/// フェイスレットはU1..U9, R1..R9, F1..F9, D1..D9, L1..L9, B1..B9の順に0..54
+const CORNER_FACELET: [[usize; 3]; CORNER_NUM] = [
+    [8, 9, 20],
+    [6, 18, 38],
+    [0, 36, 47],
+    [2, 45, 11],
+    [29, 26, 15],
+    [27, 44, 24],
+    [33, 53, 42],
+    [35, 17, 51],
+];
+/// 各エッジのステッカーのフェイスレット番号
+const EDGE_FACELET: [[usize; 2]; EDGE_NUM] = [
+    [5, 10],
+    [7, 19],
+    [3, 37],
+    [1, 46],
+    [32, 16],
+    [28, 25],
+    [30, 43],
+    [34, 52],
+    [23, 12],
+    [21, 41],
+    [50, 39],
+    [48, 14],
+];
+/// 各コーナーの色
+const CORNER_COLOR: [[Face; 3]; CORNER_NUM] = [
+    [Face::U, Face::R, Face::F],
+    [Face::U, Face::F, Face::L],
+    [Face::U, Face::L, Face::B],
+    [Face::U, Face::B, Face::R],
+    [Face::D, Face::F, Face::R],
+    [Face::D, Face::L, Face::F],
+    [Face::D, Face::B, Face::L],
+    [Face::D, Face::R, Face::B],
+];
+/// 各エッジの色
+const EDGE_COLOR: [[Face; 2]; EDGE_NUM] = [
+    [Face::U, Face::R],
+    [Face::U, Face::F],
+    [Face::U, Face::L],
+    [Face::U, Face::B],
+    [Face::D, Face::R],
+    [Face::D, Face::F],
+    [Face::D, Face::L],
+    [Face::D, Face::B],
+    [Face::F, Face::R],
+    [Face::F, Face::L],
+    [Face::B, Face::L],
+    [Face::B, Face::R],
+];
+
+/// 各面を時計回りに90度回した時のキューブ(U, R, F, D, L, Bの順)
+/// cp[i]はその操作で位置iに来るコーナーの元の位置
+const BASIC_MOVES: [CubieCube; 6] = [
+    CubieCube {
+        cp: [3, 0, 1, 2, 4, 5, 6, 7],
+        co: [0, 0, 0, 0, 0, 0, 0, 0],
+        ep: [3, 0, 1, 2, 4, 5, 6, 7, 8, 9, 10, 11],
+        eo: [0; EDGE_NUM],
+    },
+    CubieCube {
+        cp: [4, 1, 2, 0, 7, 5, 6, 3],
+        co: [2, 0, 0, 1, 1, 0, 0, 2],
+        ep: [8, 1, 2, 3, 11, 5, 6, 7, 4, 9, 10, 0],
+        eo: [0; EDGE_NUM],
+    },
+    CubieCube {
+        cp: [1, 5, 2, 3, 0, 4, 6, 7],
+        co: [1, 2, 0, 0, 2, 1, 0, 0],
+        ep: [0, 9, 2, 3, 4, 8, 6, 7, 1, 5, 10, 11],
+        eo: [0, 1, 0, 0, 0, 1, 0, 0, 1, 1, 0, 0],
+    },
+    CubieCube {
+        cp: [0, 1, 2, 3, 5, 6, 7, 4],
+        co: [0, 0, 0, 0, 0, 0, 0, 0],
+        ep: [0, 1, 2, 3, 5, 6, 7, 4, 8, 9, 10, 11],
+        eo: [0; EDGE_NUM],
+    },
+    CubieCube {
+        cp: [0, 2, 6, 3, 4, 1, 5, 7],
+        co: [0, 1, 2, 0, 0, 2, 1, 0],
+        ep: [0, 1, 10, 3, 4, 5, 9, 7, 8, 2, 6, 11],
+        eo: [0; EDGE_NUM],
+    },
+    CubieCube {
+        cp: [0, 1, 3, 7, 4, 5, 2, 6],
+        co: [0, 0, 1, 2, 0, 0, 2, 1],
+        ep: [0, 1, 2, 11, 4, 5, 6, 10, 8, 9, 3, 7],
+        eo: [0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 1, 1],
+    },
+];
+
+/// コーナーとエッジの位置と向きで表したキューブ
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CubieCube {
+    /// 位置iにあるコーナーの番号
+    pub cp: [u8; CORNER_NUM],
+    /// 位置iにあるコーナーのねじれ(0..3)
+    pub co: [u8; CORNER_NUM],
+    /// 位置iにあるエッジの番号
+    pub ep: [u8; EDGE_NUM],
+    /// 位置iにあるエッジの反転(0..2)
+    pub eo: [u8; EDGE_NUM],
+}
+
+impl Default for CubieCube {
+    fn default() -> Self {
+        Self::solved()
+    }
+}
+
+impl CubieCube {
+    /// 揃った状態
+    pub fn solved() -> Self {
+        CubieCube {
+            cp: [0, 1, 2, 3, 4, 5, 6, 7],
+            co: [0; CORNER_NUM],
+            ep: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            eo: [0; EDGE_NUM],
+        }
+    }
+
+    /// URFDLBの順に並べた54文字のフェイスレット文字列から作る
+    /// 色は各面の中心の面の名前(U, R, F, D, L, B)で書く
+    pub fn from_facelets(str: &str) -> PuzzleResult<Self> {
+        let mut facelets = Vec::with_capacity(54);
+        for c in str.chars().filter(|c| !c.is_whitespace()) {
+            let face = Face::from_char(c).ok_or_else(|| {
+                PuzzleError::new(format!(
+                    "invalid facelet: '{}': it must be one of URFDLB",
+                    c
+                ))
+            })?;
+            facelets.push(face);
+        }
+        if facelets.len() != 54 {
+            return Err(PuzzleError::new(format!(
+                "invalid facelet length: {}: it must be 54",
+                facelets.len()
+            )));
+        }
+        for face in Face::ALL {
+            let count = facelets.iter().filter(|&&x| x == face).count();
+            if count != 9 {
+                return Err(PuzzleError::new(format!(
+                    "invalid facelet count: {:?} appears {} times",
+                    face, count
+                )));
+            }
+        }
+        // 色は中心の面の名前で書くので，中心はその面自身でなければならない
+        for (k, &face) in Face::ALL.iter().enumerate() {
+            let center = facelets[4 + 9 * k];
+            if center != face {
+                return Err(PuzzleError::new(format!(
+                    "invalid center: {:?} face has {:?} at the center",
+                    face, center
+                )));
+            }
+        }
+        let cube = Self::read_pieces(&facelets)?;
+        cube.verify()?;
+        Ok(cube)
+    }
+    /// 54個のフェイスレットの色から各パーツを読み取る
+    /// 実際に回して作れる状態かは確かめない
+    pub(super) fn read_pieces(facelets: &[Face]) -> PuzzleResult<Self> {
+        let mut cube = CubieCube::solved();
+        for i in 0..CORNER_NUM {
+            // U/Dの色のステッカーがどこにあるかでねじれが決まる
+            let ori = (0..3)
+                .find(|&ori| matches!(facelets[CORNER_FACELET[i][ori]], Face::U | Face::D))
+                .ok_or_else(|| PuzzleError::new(format!("invalid corner at {}", i)))?;
+            let col1 = facelets[CORNER_FACELET[i][(ori + 1) % 3]];
+            let col2 = facelets[CORNER_FACELET[i][(ori + 2) % 3]];
+            let j = (0..CORNER_NUM)
+                .find(|&j| CORNER_COLOR[j][1] == col1 && CORNER_COLOR[j][2] == col2)
+                .ok_or_else(|| PuzzleError::new(format!("invalid corner at {}", i)))?;
+            cube.cp[i] = j as u8;
+            cube.co[i] = ori as u8;
+        }
+        for i in 0..EDGE_NUM {
+            let col0 = facelets[EDGE_FACELET[i][0]];
+            let col1 = facelets[EDGE_FACELET[i][1]];
+            let (j, ori) = (0..EDGE_NUM)
+                .find_map(|j| {
+                    if EDGE_COLOR[j] == [col0, col1] {
+                        Some((j, 0))
+                    } else if EDGE_COLOR[j] == [col1, col0] {
+                        Some((j, 1))
+                    } else {
+                        None
+                    }
+                })
+                .ok_or_else(|| PuzzleError::new(format!("invalid edge at {}", i)))?;
+            cube.ep[i] = j as u8;
+            cube.eo[i] = ori;
+        }
+        Ok(cube)
+    }
+    /// URFDLBの順に並べた54文字のフェイスレット文字列にする
+    pub fn to_facelets(&self) -> String {
+        let mut facelets = [Face::U; 54];
+        for (i, face) in Face::ALL.iter().enumerate() {
+            // 中心
+            facelets[9 * i + 4] = *face;
+        }
+        for i in 0..CORNER_NUM {
+            let j = self.cp[i] as usize;
+            let ori = self.co[i] as usize;
+            for n in 0..3 {
+                facelets[CORNER_FACELET[i][(n + ori) % 3]] = CORNER_COLOR[j][n];
+            }
+        }
+        for i in 0..EDGE_NUM {
+            let j = self.ep[i] as usize;
+            let ori = self.eo[i] as usize;
+            for n in 0..2 {
+                facelets[EDGE_FACELET[i][(n + ori) % 2]] = EDGE_COLOR[j][n];
+            }
+        }
+        facelets.iter().map(|face| face.to_string()).collect()
+    }
+
+    /// 実際に回して作れる状態かを確かめる
+    pub fn verify(&self) -> PuzzleResult<()> {
+        let mut used = [false; EDGE_NUM];
+        for &e in &self.ep {
+            if e as usize >= EDGE_NUM || used[e as usize] {
+                return Err(PuzzleError::new("invalid edge permutation"));
+            }
+            used[e as usize] = true;
+        }
+        let mut used = [false; CORNER_NUM];
+        for &c in &self.cp {
+            if c as usize >= CORNER_NUM || used[c as usize] {
+                return Err(PuzzleError::new("invalid corner permutation"));
+            }
+            used[c as usize] = true;
+        }
+        if self.eo.iter().map(|&x| x as usize).sum::<usize>() % 2 != 0 {
+            return Err(PuzzleError::new("an edge is flipped"));
+        }
+        if self.co.iter().map(|&x| x as usize).sum::<usize>() % 3 != 0 {
+            return Err(PuzzleError::new("a corner is twisted"));
+        }
+        if parity(&self.cp) != parity(&self.ep) {
+            return Err(PuzzleError::new("two pieces are swapped"));
+        }
+        Ok(())
+    }
+
+    /// selfの後にotherを適用したキューブ
+    pub fn multiply(&self, other: &CubieCube) -> CubieCube {
+        let mut cube = CubieCube::solved();
+        self.corner_multiply(other, &mut cube);
+        self.edge_multiply(other, &mut cube);
+        cube
+    }
+    /// コーナーだけ掛け算する
+    pub fn corner_multiply(&self, other: &CubieCube, cube: &mut CubieCube) {
+        for i in 0..CORNER_NUM {
+            let from = other.cp[i] as usize;
+            cube.cp[i] = self.cp[from];
+            cube.co[i] = (self.co[from] + other.co[i]) % 3;
+        }
+    }
+    /// エッジだけ掛け算する
+    pub fn edge_multiply(&self, other: &CubieCube, cube: &mut CubieCube) {
+        for i in 0..EDGE_NUM {
+            let from = other.ep[i] as usize;
+            cube.ep[i] = self.ep[from];
+            cube.eo[i] = (self.eo[from] + other.eo[i]) % 2;
+        }
+    }
+    /// 逆操作のキューブ
+    pub fn inverse(&self) -> CubieCube {
+        let mut cube = CubieCube::solved();
+        for i in 0..CORNER_NUM {
+            let c = self.cp[i] as usize;
+            cube.cp[c] = i as u8;
+            cube.co[c] = (3 - self.co[i]) % 3;
+        }
+        for i in 0..EDGE_NUM {
+            let e = self.ep[i] as usize;
+            cube.ep[e] = i as u8;
+            cube.eo[e] = self.eo[i];
+        }
+        cube
+    }
+
+    /// 一手回す
+    pub fn apply(&mut self, m: Move) {
+        let basic = &BASIC_MOVES[m.face() as usize];
+        for _ in 0..m.turns() {
+            *self = self.multiply(basic);
+        }
+    }
+    /// 手順を順に適用する
+    pub fn apply_all(&mut self, moves: &[Move]) {
+        for &m in moves {
+            self.apply(m);
+        }
+    }
+    /// 面faceを時計回りに90度回すキューブ
+    pub fn basic_move(face: Face) -> &'static CubieCube {
+        &BASIC_MOVES[face as usize]
+    }
+
+    /// 揃っているか
+    pub fn is_solved(&self) -> bool {
+        *self == CubieCube::solved()
+    }
+}
+
+impl Display for CubieCube {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_facelets())
+    }
+}
+
+/// 順列の偶奇(偶置換なら0)
+pub fn parity(perm: &[u8]) -> u8 {
+    let mut count = 0;
+    for i in 0..perm.len() {
+        for j in (i + 1)..perm.len() {
+            if perm[i] > perm[j] {
+                count += 1;
+            }
+        }
+    }
+    count % 2
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{parse_moves, Face, Move};
+    use super::CubieCube;
+
+    const SOLVED: &str = "UUUUUUUUURRRRRRRRRFFFFFFFFFDDDDDDDDDLLLLLLLLLBBBBBBBBB";
+
+    #[test]
+    fn test_facelets() {
+        let cube = CubieCube::from_facelets(SOLVED).unwrap();
+        assert!(cube.is_solved());
+        assert_eq!(cube.to_facelets(), SOLVED);
+
+        let mut cube = CubieCube::solved();
+        cube.apply(Move::new(Face::R, 1));
+        assert_eq!(
+            cube.to_facelets(),
+            "UUFUUFUUFRRRRRRRRRFFDFFDFFDDDBDDBDDBLLLLLLLLLUBBUBBUBB"
+        );
+        cube.apply_all(&parse_moves("U F' D2 L B R2").unwrap());
+        let facelets = cube.to_facelets();
+        assert_eq!(CubieCube::from_facelets(&facelets).unwrap(), cube);
+    }
+    #[test]
+    fn test_moves() {
+        for face in Face::ALL {
+            let mut cube = CubieCube::solved();
+            for _ in 0..4 {
+                cube.apply(Move::new(face, 1));
+            }
+            assert!(cube.is_solved());
+        }
+        // セクシームーブは6回で戻る
+        let sexy = parse_moves("R U R' U'").unwrap();
+        let mut cube = CubieCube::solved();
+        for i in 0..6 {
+            assert_eq!(cube.is_solved(), i == 0);
+            cube.apply_all(&sexy);
+        }
+        assert!(cube.is_solved());
+
+        let mut cube = CubieCube::solved();
+        cube.apply_all(&parse_moves("R U2 F' L D B'").unwrap());
+        cube.verify().unwrap();
+        assert!(cube.multiply(&cube.inverse()).is_solved());
+    }
+    #[test]
+    fn test_invalid_facelets() {
+        assert!(CubieCube::from_facelets(&SOLVED[1..]).is_err());
+        assert!(CubieCube::from_facelets(&SOLVED.replace('B', "X")).is_err());
+        // コーナーを一つねじる
+        let mut facelets: Vec<char> = SOLVED.chars().collect();
+        facelets[8] = 'R';
+        facelets[9] = 'F';
+        facelets[20] = 'U';
+        let twisted: String = facelets.into_iter().collect();
+        assert!(CubieCube::from_facelets(&twisted).is_err());
+        // エッジを二つ入れ替える
+        let mut facelets: Vec<char> = SOLVED.chars().collect();
+        facelets.swap(10, 19);
+        let swapped: String = facelets.into_iter().collect();
+        assert!(CubieCube::from_facelets(&swapped).is_err());
+        // UとRの中心を入れ替える
+        let mut facelets: Vec<char> = SOLVED.chars().collect();
+        facelets.swap(4, 13);
+        let centers: String = facelets.into_iter().collect();
+        assert!(CubieCube::from_facelets(&centers).is_err());
+    }
+}
@@ -0,0 +1,514 @@
+//! ルービックキューブ(3x3x3)のソルバー
+//! キューブはコーナーとエッジの位置と向き(cubie)で表し，Kociembaの二段階アルゴリズムで解く
+//! 最短手数が必要な時はKorfの方法(IDA*)でも解ける
+//! 2x2x2やNxNxNのキューブはステッカーで表したCubeとCubeSolverで扱う
+//! 操作はシングマスター記法(U, U', U2, ...)で書く
+
+pub mod cube;
+pub mod cubie;
+pub mod optimal;
+pub mod pocket;
+pub mod reduction;
+pub mod two_phase;
+
+use std::fmt::Display;
+use std::sync::Arc;
+
+use super::{PuzzleError, PuzzleResult, Solver};
+use cube::{simplify, Cube, LayerMove};
+use cubie::CubieCube;
+use optimal::{OptimalSearch, OptimalTables};
+use pocket::PocketTable;
+use reduction::{color_scheme, corners_of, to_cubie, Reduction};
+use two_phase::{TwoPhaseSearch, TwoPhaseTables};
+
+/// 操作の種類の数(6面 x 90度, 180度, 270度)
+pub const MOVE_NUM: usize = 18;
+
+/// 面(色も中心の面の名前で表す)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Face {
+    U,
+    R,
+    F,
+    D,
+    L,
+    B,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [Face::U, Face::R, Face::F, Face::D, Face::L, Face::B];
+
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            'U' => Some(Face::U),
+            'R' => Some(Face::R),
+            'F' => Some(Face::F),
+            'D' => Some(Face::D),
+            'L' => Some(Face::L),
+            'B' => Some(Face::B),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Face {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// 一つの面を回す操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move {
+    face: Face,
+    /// 時計回りに90度回す回数(1..=3)
+    turns: u8,
+}
+
+impl Move {
+    /// 面faceを時計回りにturns回(1..=3)回す操作
+    pub fn new(face: Face, turns: u8) -> Self {
+        assert!((1..=3).contains(&turns));
+        Move { face, turns }
+    }
+    pub fn face(&self) -> Face {
+        self.face
+    }
+    pub fn turns(&self) -> u8 {
+        self.turns
+    }
+    /// 0..MOVE_NUMの番号
+    pub fn index(&self) -> usize {
+        self.face as usize * 3 + self.turns as usize - 1
+    }
+    /// 逆操作
+    pub fn inverse(&self) -> Self {
+        Move::new(self.face, 4 - self.turns)
+    }
+    /// 全ての操作
+    pub fn all() -> Vec<Move> {
+        Face::ALL
+            .iter()
+            .flat_map(|&face| (1..=3).map(move |turns| Move::new(face, turns)))
+            .collect()
+    }
+    /// phase2で使う操作 <U, D, R2, L2, F2, B2>
+    pub fn phase2() -> Vec<Move> {
+        Self::all().into_iter().filter(|m| m.is_phase2()).collect()
+    }
+    pub fn is_phase2(&self) -> bool {
+        matches!(self.face, Face::U | Face::D) || self.turns == 2
+    }
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.turns {
+            1 => write!(f, "{}", self.face),
+            2 => write!(f, "{}2", self.face),
+            _ => write!(f, "{}'", self.face),
+        }
+    }
+}
+
+/// "R U R' U2"のようなシングマスター記法の手順を読み込む
+pub fn parse_moves(str: &str) -> PuzzleResult<Vec<Move>> {
+    str.split_whitespace()
+        .map(|token| {
+            let mut chars = token.chars();
+            let face = chars
+                .next()
+                .and_then(Face::from_char)
+                .ok_or_else(|| PuzzleError::new(format!("invalid move: {}", token)))?;
+            let turns = match chars.as_str() {
+                "" => 1,
+                "2" | "2'" => 2,
+                "'" => 3,
+                _ => return Err(PuzzleError::new(format!("invalid move: {}", token))),
+            };
+            Ok(Move::new(face, turns))
+        })
+        .collect()
+}
+
+/// 手順を空白区切りの文字列にする
+pub fn moves_to_string(moves: &[Move]) -> String {
+    moves
+        .iter()
+        .map(|m| m.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 解き方と，それに使う表(大きいので共有する)
+#[derive(Debug, Clone)]
+pub enum Method {
+    /// 二段階アルゴリズム，速いが最短とは限らない
+    TwoPhase(Arc<TwoPhaseTables>),
+    /// Korfの方法，最短手数の解を求める
+    Optimal(Arc<OptimalTables>),
+}
+
+/// ルービックキューブのソルバー
+#[derive(Debug)]
+pub struct RubiksSolver {
+    /// 解く対象のキューブ
+    cube: CubieCube,
+    method: Method,
+    /// 解の手数の上限
+    max_length: usize,
+    /// 次に探索する手数(二段階アルゴリズムではphase1の手数)
+    depth: usize,
+    /// 見つかった手順
+    pub ans: Option<Vec<Move>>,
+}
+
+impl RubiksSolver {
+    /// URFDLBの順に並べた54文字のフェイスレット文字列から作る
+    pub fn new(facelets: &str, tables: Arc<TwoPhaseTables>) -> PuzzleResult<Self> {
+        let cube = CubieCube::from_facelets(facelets)?;
+        Ok(Self::from_cube(cube, tables))
+    }
+    pub fn from_cube(cube: CubieCube, tables: Arc<TwoPhaseTables>) -> Self {
+        RubiksSolver {
+            cube,
+            method: Method::TwoPhase(tables),
+            max_length: 24,
+            depth: 0,
+            ans: None,
+        }
+    }
+    /// 揃った状態にscrambleを適用したキューブから作る
+    pub fn from_scramble(scramble: &str, tables: Arc<TwoPhaseTables>) -> PuzzleResult<Self> {
+        let mut cube = CubieCube::solved();
+        cube.apply_all(&parse_moves(scramble)?);
+        Ok(Self::from_cube(cube, tables))
+    }
+    /// 初期化する
+    pub fn init(&mut self) {
+        self.depth = 0;
+        self.ans = None;
+    }
+    /// 解の手数の上限を変更する(デフォルトは24手)
+    pub fn set_max_length(&mut self, max_length: usize) {
+        self.max_length = max_length;
+        self.init();
+    }
+    /// 解き方を変更する
+    pub fn set_method(&mut self, method: Method) {
+        self.method = method;
+        self.init();
+    }
+    /// 最短手数を求めるモードにする
+    pub fn set_optimal(&mut self, tables: Arc<OptimalTables>) {
+        self.set_method(Method::Optimal(tables));
+    }
+    pub fn cube(&self) -> &CubieCube {
+        &self.cube
+    }
+    /// 見つかった手順をシングマスター記法の文字列にしたもの
+    pub fn ans_string(&self) -> Option<String> {
+        self.ans.as_ref().map(|ans| moves_to_string(ans))
+    }
+    /// 見つかった手順の手数(HTM)
+    pub fn move_count(&self) -> Option<usize> {
+        self.ans.as_ref().map(|ans| ans.len())
+    }
+}
+
+impl Solver for RubiksSolver {
+    fn has_finished(&self) -> PuzzleResult<bool> {
+        Ok(self.ans.is_some())
+    }
+    /// 手数を一つ決めて探索する
+    /// 二段階アルゴリズムではphase1の手数，最短手数のモードでは全体の手数
+    /// 見つからなければ次は手数を一つ増やす
+    fn search(&mut self) -> PuzzleResult<()> {
+        if self.ans.is_some() {
+            return Ok(());
+        }
+        if self.depth > self.max_length {
+            return Err(PuzzleError::new(format!(
+                "not found path within {} moves",
+                self.max_length
+            )));
+        }
+        let ans = match &self.method {
+            Method::TwoPhase(tables) => {
+                TwoPhaseSearch::new(tables, self.cube, self.max_length).search(self.depth)
+            }
+            Method::Optimal(tables) => {
+                let mut search = OptimalSearch::new(tables, self.cube);
+                // 下界より短い手数は探索しない
+                self.depth = self.depth.max(search.estimate());
+                search.search(self.depth)
+            }
+        };
+        match ans {
+            Some(ans) => self.ans = Some(ans),
+            None => self.depth += 1,
+        }
+        Ok(())
+    }
+}
+
+impl Display for RubiksSolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.cube)
+    }
+}
+
+/// 任意の大きさのキューブのソルバー
+/// 2x2x2は全状態の表で最短手数，3x3x3は二段階アルゴリズム，
+/// 4x4x4以上はセンターとエッジを揃えて3x3x3に帰着してから二段階アルゴリズムで解く
+#[derive(Debug)]
+pub struct CubeSolver {
+    cube: Cube,
+    /// 2x2x2で使う表
+    pocket: Option<Arc<PocketTable>>,
+    /// 3x3x3以上で使う表
+    two_phase: Option<Arc<TwoPhaseTables>>,
+    /// 見つかった手順
+    pub ans: Option<Vec<LayerMove>>,
+}
+
+impl CubeSolver {
+    pub fn new(cube: Cube) -> Self {
+        CubeSolver {
+            cube,
+            pocket: None,
+            two_phase: None,
+            ans: None,
+        }
+    }
+    /// 2x2x2の表を設定する
+    pub fn set_pocket_table(&mut self, table: Arc<PocketTable>) {
+        self.pocket = Some(table);
+        self.init();
+    }
+    /// 3x3x3以上で使う二段階アルゴリズムの表を設定する
+    pub fn set_two_phase_tables(&mut self, tables: Arc<TwoPhaseTables>) {
+        self.two_phase = Some(tables);
+        self.init();
+    }
+    /// 初期化する
+    pub fn init(&mut self) {
+        self.ans = None;
+    }
+    pub fn cube(&self) -> &Cube {
+        &self.cube
+    }
+    /// 見つかった手順を文字列にしたもの
+    pub fn ans_string(&self) -> Option<String> {
+        self.ans
+            .as_ref()
+            .map(|ans| cube::layer_moves_to_string(ans))
+    }
+    /// 見つかった手順の手数
+    pub fn move_count(&self) -> Option<usize> {
+        self.ans.as_ref().map(|ans| ans.len())
+    }
+
+    fn solve(&self) -> PuzzleResult<Vec<LayerMove>> {
+        // 色を面の名前に付け替えて，揃った状態の向きを決める
+        let scheme = color_scheme(&self.cube)?;
+        let mut cube = self.cube.clone();
+        cube.recolor(&scheme);
+        if cube.size() == 2 {
+            let table = self
+                .pocket
+                .as_ref()
+                .ok_or_else(|| PuzzleError::new("pocket table is not set"))?;
+            let ans = table.solve(&corners_of(&cube)?)?;
+            return Ok(ans.into_iter().map(LayerMove::from).collect());
+        }
+        let tables = self
+            .two_phase
+            .as_ref()
+            .ok_or_else(|| PuzzleError::new("two phase tables are not set"))?;
+        let mut ans = Vec::new();
+        if cube.size() >= 4 {
+            ans = Reduction::new(cube.size())?.reduce(&mut cube)?;
+        }
+        let mut solver = RubiksSolver::from_cube(to_cubie(&cube)?, tables.clone());
+        solver.run()?;
+        ans.extend(solver.ans.unwrap().into_iter().map(LayerMove::from));
+        Ok(simplify(&ans))
+    }
+}
+
+impl Solver for CubeSolver {
+    fn has_finished(&self) -> PuzzleResult<bool> {
+        Ok(self.ans.is_some())
+    }
+    fn search(&mut self) -> PuzzleResult<()> {
+        if self.ans.is_none() {
+            self.ans = Some(self.solve()?);
+        }
+        Ok(())
+    }
+}
+
+impl Display for CubeSolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.cube)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, OnceLock};
+
+    use super::cube::Cube;
+    use super::optimal::OptimalTables;
+    use super::pocket::PocketTable;
+    use super::two_phase::TwoPhaseTables;
+    use super::{
+        color_scheme, corners_of, moves_to_string, parse_moves, CubeSolver, Face, Move,
+        RubiksSolver,
+    };
+    use crate::solver::random::Random;
+    use crate::solver::Solver;
+
+    /// 表を作るのは重いのでテスト間で共有する
+    pub(super) fn tables() -> Arc<TwoPhaseTables> {
+        static TABLES: OnceLock<Arc<TwoPhaseTables>> = OnceLock::new();
+        TABLES
+            .get_or_init(|| Arc::new(TwoPhaseTables::build()))
+            .clone()
+    }
+
+    #[test]
+    fn test_parse_moves() {
+        let moves = parse_moves("R U2 F' D2'").unwrap();
+        assert_eq!(
+            moves,
+            vec![
+                Move::new(Face::R, 1),
+                Move::new(Face::U, 2),
+                Move::new(Face::F, 3),
+                Move::new(Face::D, 2)
+            ]
+        );
+        assert_eq!(moves_to_string(&moves), "R U2 F' D2");
+        assert!(parse_moves("R X").is_err());
+        assert!(parse_moves("R3").is_err());
+        assert_eq!(Move::all().len(), 18);
+        assert_eq!(Move::phase2().len(), 10);
+    }
+    #[test]
+    fn test_solve() {
+        let mut random = Random::new(0);
+        let moves = Move::all();
+        for _ in 0..5 {
+            let scramble: Vec<Move> = (0..25).map(|_| moves[random.gen_range(18)]).collect();
+            let mut solver =
+                RubiksSolver::from_scramble(&moves_to_string(&scramble), tables()).unwrap();
+            solver.run().unwrap();
+            let ans = solver.ans.clone().unwrap();
+            assert!(ans.len() <= 24);
+            let mut cube = *solver.cube();
+            cube.apply_all(&ans);
+            assert!(cube.is_solved(), "{:?} {:?}", scramble, ans);
+        }
+    }
+    #[test]
+    fn test_solve_facelets() {
+        // superflip
+        let mut solver = RubiksSolver::new(
+            "UBULURUFURURFRBRDRFUFLFRFDFDFDLDRDBDLULBLFLDLBUBRBLBDB",
+            tables(),
+        )
+        .unwrap();
+        solver.run().unwrap();
+        let mut cube = *solver.cube();
+        cube.apply_all(&solver.ans.unwrap());
+        assert!(cube.is_solved());
+
+        let mut solver = RubiksSolver::from_scramble("", tables()).unwrap();
+        solver.run().unwrap();
+        assert_eq!(solver.ans_string(), Some("".to_string()));
+    }
+    #[test]
+    fn test_tables_file() {
+        let path = std::env::temp_dir().join("puzzles_rubiks_tables_test.bin");
+        let tables = tables();
+        tables.save(&path).unwrap();
+        let loaded = TwoPhaseTables::load(&path).unwrap();
+        assert!(*tables == loaded);
+        // 長さは合っていても遷移先が範囲外
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[4] = 0xff;
+        bytes[5] = 0xff;
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(TwoPhaseTables::load(&path).is_err());
+        std::fs::write(&path, b"broken").unwrap();
+        assert!(TwoPhaseTables::load(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn test_optimal() {
+        let optimal = Arc::new(OptimalTables::with_groups(
+            vec![0, 1, 2, 3],
+            vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]],
+        ));
+        for (scramble, count) in [
+            ("", 0),
+            ("R U R'", 3),
+            ("F2 B2", 2),
+            ("R R", 1),
+            ("U D U'", 1),
+        ] {
+            let mut solver = RubiksSolver::from_scramble(scramble, tables()).unwrap();
+            solver.set_optimal(optimal.clone());
+            solver.run().unwrap();
+            assert_eq!(solver.move_count(), Some(count), "{}", scramble);
+            let mut cube = *solver.cube();
+            cube.apply_all(&solver.ans.unwrap());
+            assert!(cube.is_solved());
+        }
+    }
+    #[test]
+    fn test_cube_solver() {
+        let pocket = Arc::new(PocketTable::build());
+        let cases = [
+            (2, "R"),
+            (2, "R U2 F' R' U F2 R U' F R2"),
+            (2, "R U2 F' D L2 B R' U' F2"),
+            (3, "R U2 F' D L2 B R' U' F2 D' L B2"),
+            // 2Rだけだとウィングが奇置換，RだけだとコーナーとエッジのPLLパリティ
+            (4, "2R"),
+            (4, "R"),
+            (4, "Rw U2 2F' L D Bw2 2R U' F 2L' Dw B2 R 2U Lw' F2 2D B'"),
+            (
+                5,
+                "3Rw U2 2F' L D Bw2 2R U' F 2L' Dw B2 R 2U Lw' F2 2D B' 3U",
+            ),
+        ];
+        for (size, scramble) in cases {
+            let mut solver = CubeSolver::new(Cube::from_scramble(size, scramble).unwrap());
+            solver.set_pocket_table(pocket.clone());
+            solver.set_two_phase_tables(tables());
+            solver.run().unwrap();
+            let mut cube = solver.cube().clone();
+            cube.apply_all(&solver.ans.clone().unwrap());
+            assert!(cube.is_solved(), "{}: {}", size, scramble);
+            if size == 2 {
+                // 最短手数は表の距離と一致する
+                let mut recolored = solver.cube().clone();
+                recolored.recolor(&color_scheme(solver.cube()).unwrap());
+                let distance = pocket.distance(&corners_of(&recolored).unwrap()).unwrap();
+                assert_eq!(solver.move_count(), Some(distance), "{}", scramble);
+            }
+        }
+        // 表を設定しなければエラー
+        let mut solver = CubeSolver::new(Cube::from_scramble(2, "R").unwrap());
+        assert!(solver.run().is_err());
+        // 向かい合う色がそろっていない
+        let mut solver =
+            CubeSolver::new(Cube::from_facelets(2, "UUUUFFFFRRRRDDDDLLLLBBBB").unwrap());
+        solver.set_pocket_table(pocket);
+        assert!(solver.run().is_err());
+    }
+}
@@ -0,0 +1,503 @@
+//! Korfの方法による最短手数(HTM: 180度回しも1手)の探索
+//! コーナーとエッジのパターンデータベースの最大値を下界にしてIDA*で探索する
+//! データベースは一部のパーツの位置と向きだけを見た状態から揃うまでの最小手数をBFSで求めたもの
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use super::super::{PuzzleError, PuzzleResult};
+use super::cubie::{CubieCube, CORNER_NUM, EDGE_NUM};
+use super::two_phase::is_redundant;
+use super::{Move, MOVE_NUM};
+
+/// ファイルの先頭に書くマジックナンバー
+const TABLES_MAGIC: &[u8; 4] = b"RBKO";
+
+/// パーツの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PieceKind {
+    Corner,
+    Edge,
+}
+
+impl PieceKind {
+    /// パーツの数
+    fn size(self) -> usize {
+        match self {
+            PieceKind::Corner => CORNER_NUM,
+            PieceKind::Edge => EDGE_NUM,
+        }
+    }
+    /// 向きの数
+    fn ori_num(self) -> usize {
+        match self {
+            PieceKind::Corner => 3,
+            PieceKind::Edge => 2,
+        }
+    }
+}
+
+/// 一部のパーツの位置と向きだけを見たパターンデータベース
+/// 状態の番号はパーツの位置の並べ方の番号 * 向きの数^k + 向きの番号
+#[derive(Clone, PartialEq, Eq)]
+pub struct PieceDatabase {
+    kind: PieceKind,
+    /// 見るパーツの番号
+    pieces: Vec<u8>,
+    /// 各操作で位置posのパーツが移る位置 dest[m][pos]
+    dest: Vec<[u8; EDGE_NUM]>,
+    /// 各操作で位置posのパーツの向きが変わる量 twist[m][pos]
+    twist: Vec<[u8; EDGE_NUM]>,
+    table: Vec<u8>,
+}
+
+impl std::fmt::Debug for PieceDatabase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // 表は大きいので表示しない
+        f.debug_struct("PieceDatabase")
+            .field("kind", &self.kind)
+            .field("pieces", &self.pieces)
+            .finish()
+    }
+}
+
+impl PieceDatabase {
+    /// 揃った状態からのBFSで作る
+    pub fn build(kind: PieceKind, pieces: Vec<u8>) -> Self {
+        let mut db = Self::empty(kind, pieces);
+        db.build_table();
+        db
+    }
+    /// 表が空のデータベース
+    fn empty(kind: PieceKind, pieces: Vec<u8>) -> Self {
+        let n = kind.size();
+        assert!(!pieces.is_empty() && pieces.len() <= n);
+        assert!(pieces.iter().all(|&p| (p as usize) < n));
+        let mut dest = Vec::with_capacity(MOVE_NUM);
+        let mut twist = Vec::with_capacity(MOVE_NUM);
+        for m in Move::all() {
+            let mut cube = CubieCube::solved();
+            cube.apply(m);
+            let mut d = [0; EDGE_NUM];
+            let mut t = [0; EDGE_NUM];
+            for i in 0..n {
+                let (from, ori) = match kind {
+                    PieceKind::Corner => (cube.cp[i], cube.co[i]),
+                    PieceKind::Edge => (cube.ep[i], cube.eo[i]),
+                };
+                d[from as usize] = i as u8;
+                t[from as usize] = ori;
+            }
+            dest.push(d);
+            twist.push(t);
+        }
+        let mut db = PieceDatabase {
+            kind,
+            pieces,
+            dest,
+            twist,
+            table: Vec::new(),
+        };
+        db.table = vec![u8::MAX; db.arrange_num() * db.ori_count()];
+        db
+    }
+    /// 位置の並べ方の数 n! / (n-k)!
+    fn arrange_num(&self) -> usize {
+        let n = self.kind.size();
+        (n - self.pieces.len() + 1..=n).product()
+    }
+    /// 向きを数える個数，全てのパーツを見る時は最後の向きは他から決まる
+    fn ori_digits(&self) -> usize {
+        if self.pieces.len() == self.kind.size() {
+            self.pieces.len() - 1
+        } else {
+            self.pieces.len()
+        }
+    }
+    fn ori_count(&self) -> usize {
+        self.kind.ori_num().pow(self.ori_digits() as u32)
+    }
+
+    /// 見ているパーツの位置と向きから番号を求める
+    fn rank(&self, pos: &[u8], ori: &[u8]) -> usize {
+        let n = self.kind.size();
+        let mut used = 0u32;
+        let mut arrange = 0;
+        for (i, &p) in pos.iter().enumerate() {
+            let smaller = (used & ((1 << p) - 1)).count_ones() as usize;
+            arrange = arrange * (n - i) + p as usize - smaller;
+            used |= 1 << p;
+        }
+        let o = self.kind.ori_num();
+        let ori = ori[..self.ori_digits()]
+            .iter()
+            .fold(0, |acc, &x| acc * o + x as usize);
+        arrange * self.ori_count() + ori
+    }
+    /// 番号から位置と向きを復元する
+    fn unrank(&self, index: usize, pos: &mut [u8], ori: &mut [u8]) {
+        let n = self.kind.size();
+        let k = self.pieces.len();
+        let o = self.kind.ori_num();
+        let (mut arrange, mut rest) = (index / self.ori_count(), index % self.ori_count());
+        let digits = self.ori_digits();
+        let mut sum = 0;
+        for i in (0..digits).rev() {
+            ori[i] = (rest % o) as u8;
+            sum += ori[i] as usize;
+            rest /= o;
+        }
+        if digits < k {
+            ori[k - 1] = ((o - sum % o) % o) as u8;
+        }
+        let mut digit = [0; EDGE_NUM];
+        for i in (0..k).rev() {
+            digit[i] = arrange % (n - i);
+            arrange /= n - i;
+        }
+        // 空いている位置をビットで持ち，digit[i]番目に小さいものを選ぶ
+        let mut free = (1u32 << n) - 1;
+        for i in 0..k {
+            let mut rest = free;
+            for _ in 0..digit[i] {
+                rest &= rest - 1;
+            }
+            let p = rest.trailing_zeros();
+            pos[i] = p as u8;
+            free &= !(1 << p);
+        }
+    }
+
+    /// 前半は手数depthの状態から広げ，後半はまだ手数の決まっていない状態から
+    /// 手数depthの状態に一手で行けるかを調べる(後半は未訪問の状態の方が少ないので速い)
+    fn build_table(&mut self) {
+        let k = self.pieces.len();
+        let solved = self.rank(&self.pieces.clone(), &vec![0; k]);
+        let mut table = std::mem::take(&mut self.table);
+        table[solved] = 0;
+        let mut depth = 0;
+        let mut done = 1;
+        let (mut pos, mut ori) = ([0; EDGE_NUM], [0; EDGE_NUM]);
+        while done < table.len() {
+            let backward = done > table.len() / 2;
+            let mut changed = false;
+            for index in 0..table.len() {
+                if backward {
+                    if table[index] != u8::MAX {
+                        continue;
+                    }
+                    self.unrank(index, &mut pos, &mut ori);
+                    if (0..MOVE_NUM).any(|m| table[self.neighbor(&pos, &ori, m)] == depth) {
+                        table[index] = depth + 1;
+                        done += 1;
+                        changed = true;
+                    }
+                } else {
+                    if table[index] != depth {
+                        continue;
+                    }
+                    self.unrank(index, &mut pos, &mut ori);
+                    for m in 0..MOVE_NUM {
+                        let next = self.neighbor(&pos, &ori, m);
+                        if table[next] == u8::MAX {
+                            table[next] = depth + 1;
+                            done += 1;
+                            changed = true;
+                        }
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+            depth += 1;
+        }
+        self.table = table;
+    }
+    /// 操作mをした後の状態の番号
+    fn neighbor(&self, pos: &[u8], ori: &[u8], m: usize) -> usize {
+        let k = self.pieces.len();
+        let o = self.kind.ori_num() as u8;
+        let (mut next_pos, mut next_ori) = ([0; EDGE_NUM], [0; EDGE_NUM]);
+        for i in 0..k {
+            let p = pos[i] as usize;
+            next_pos[i] = self.dest[m][p];
+            next_ori[i] = (ori[i] + self.twist[m][p]) % o;
+        }
+        self.rank(&next_pos[..k], &next_ori[..k])
+    }
+
+    /// キューブの状態から揃うまでの手数の下界
+    pub fn lookup(&self, cube: &CubieCube) -> u8 {
+        let k = self.pieces.len();
+        let (mut pos, mut ori) = ([0; EDGE_NUM], [0; EDGE_NUM]);
+        let (perm, orient): (&[u8], &[u8]) = match self.kind {
+            PieceKind::Corner => (&cube.cp, &cube.co),
+            PieceKind::Edge => (&cube.ep, &cube.eo),
+        };
+        for (p, (&piece, &o)) in perm.iter().zip(orient).enumerate() {
+            if let Some(i) = self.pieces.iter().position(|&x| x == piece) {
+                pos[i] = p as u8;
+                ori[i] = o;
+            }
+        }
+        self.table[self.rank(&pos[..k], &ori[..k])]
+    }
+}
+
+/// 最短手数の探索に使うデータベースの組
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptimalTables {
+    databases: Vec<PieceDatabase>,
+}
+
+impl OptimalTables {
+    /// 全てのコーナーと，6個ずつ2つに分けたエッジのデータベースを作る
+    pub fn build() -> Self {
+        Self::with_groups(
+            (0..CORNER_NUM as u8).collect(),
+            vec![(0..6).collect(), (6..EDGE_NUM as u8).collect()],
+        )
+    }
+    /// 見るコーナーと，エッジのグループを指定して作る
+    pub fn with_groups(corners: Vec<u8>, edge_groups: Vec<Vec<u8>>) -> Self {
+        let mut databases = vec![PieceDatabase::build(PieceKind::Corner, corners)];
+        for group in edge_groups {
+            databases.push(PieceDatabase::build(PieceKind::Edge, group));
+        }
+        OptimalTables { databases }
+    }
+
+    /// 揃うまでの手数の下界
+    pub fn estimate(&self, cube: &CubieCube) -> usize {
+        self.databases
+            .iter()
+            .map(|db| db.lookup(cube))
+            .max()
+            .unwrap_or(0) as usize
+    }
+
+    /// pathにあればそれを読み込み，なければ作ってpathに保存する
+    pub fn load_or_build(path: impl AsRef<Path>) -> PuzzleResult<Self> {
+        let path = path.as_ref();
+        if path.exists() {
+            if let Ok(tables) = Self::load(path) {
+                return Ok(tables);
+            }
+        }
+        let tables = Self::build();
+        tables.save(path)?;
+        Ok(tables)
+    }
+    /// バイナリ形式でファイルに保存する
+    /// データベースの数，各データベースの種類，パーツの数，パーツの番号，表の順に書く
+    pub fn save(&self, path: impl AsRef<Path>) -> PuzzleResult<()> {
+        let file = File::create(path.as_ref())
+            .map_err(|e| PuzzleError::new(format!("cannot create tables file: {}", e)))?;
+        let mut writer = BufWriter::new(file);
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(TABLES_MAGIC);
+        bytes.push(self.databases.len() as u8);
+        for db in &self.databases {
+            bytes.push(match db.kind {
+                PieceKind::Corner => 0,
+                PieceKind::Edge => 1,
+            });
+            bytes.push(db.pieces.len() as u8);
+            bytes.extend_from_slice(&db.pieces);
+            bytes.extend_from_slice(&db.table);
+        }
+        writer
+            .write_all(&bytes)
+            .and_then(|_| writer.flush())
+            .map_err(|e| PuzzleError::new(format!("cannot write tables file: {}", e)))
+    }
+    /// saveで保存したファイルを読み込む
+    pub fn load(path: impl AsRef<Path>) -> PuzzleResult<Self> {
+        let file = File::open(path.as_ref())
+            .map_err(|e| PuzzleError::new(format!("cannot open tables file: {}", e)))?;
+        let mut bytes = Vec::new();
+        BufReader::new(file)
+            .read_to_end(&mut bytes)
+            .map_err(|e| PuzzleError::new(format!("cannot read tables file: {}", e)))?;
+        let invalid = || PuzzleError::new("invalid tables file");
+        if bytes.len() < 5 || &bytes[..4] != TABLES_MAGIC {
+            return Err(invalid());
+        }
+        let mut pos = 5;
+        let mut databases = Vec::new();
+        for _ in 0..bytes[4] {
+            let kind = match bytes.get(pos) {
+                Some(0) => PieceKind::Corner,
+                Some(1) => PieceKind::Edge,
+                _ => return Err(invalid()),
+            };
+            let k = *bytes.get(pos + 1).ok_or_else(invalid)? as usize;
+            let pieces = bytes
+                .get(pos + 2..pos + 2 + k)
+                .ok_or_else(invalid)?
+                .to_vec();
+            if k == 0 || k > kind.size() || pieces.iter().any(|&p| p as usize >= kind.size()) {
+                return Err(invalid());
+            }
+            pos += 2 + k;
+            let mut db = PieceDatabase::empty(kind, pieces);
+            let len = db.table.len();
+            db.table
+                .copy_from_slice(bytes.get(pos..pos + len).ok_or_else(invalid)?);
+            pos += len;
+            databases.push(db);
+        }
+        if pos != bytes.len() || databases.is_empty() {
+            return Err(invalid());
+        }
+        Ok(OptimalTables { databases })
+    }
+}
+
+/// 最短手数の探索(IDA*)
+pub struct OptimalSearch<'a> {
+    tables: &'a OptimalTables,
+    cube: CubieCube,
+    path: Vec<Move>,
+    /// 各操作のキューブ
+    moves: Vec<CubieCube>,
+}
+
+impl<'a> OptimalSearch<'a> {
+    pub fn new(tables: &'a OptimalTables, cube: CubieCube) -> Self {
+        let moves = Move::all()
+            .into_iter()
+            .map(|m| {
+                let mut cube = CubieCube::solved();
+                cube.apply(m);
+                cube
+            })
+            .collect();
+        OptimalSearch {
+            tables,
+            cube,
+            path: Vec::new(),
+            moves,
+        }
+    }
+    /// 下界
+    pub fn estimate(&self) -> usize {
+        self.tables.estimate(&self.cube)
+    }
+    /// ちょうどdepth手で揃う手順を探す
+    /// depthを0から順に増やして呼べば最初に見つかったものが最短になる
+    pub fn search(&mut self, depth: usize) -> Option<Vec<Move>> {
+        self.path.clear();
+        let cube = self.cube;
+        if self.dfs(&cube, depth) {
+            Some(self.path.clone())
+        } else {
+            None
+        }
+    }
+
+    fn dfs(&mut self, cube: &CubieCube, depth: usize) -> bool {
+        let h = self.tables.estimate(cube);
+        if h > depth {
+            return false;
+        }
+        if depth == 0 {
+            return cube.is_solved();
+        }
+        for m in Move::all() {
+            if is_redundant(self.path.last(), m) {
+                continue;
+            }
+            let next = cube.multiply(&self.moves[m.index()]);
+            self.path.push(m);
+            if self.dfs(&next, depth - 1) {
+                return true;
+            }
+            self.path.pop();
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::super::cubie::CubieCube;
+    use super::super::{parse_moves, Move};
+    use super::{OptimalSearch, OptimalTables, PieceDatabase, PieceKind};
+
+    /// テスト用の小さいデータベース
+    fn small_tables() -> OptimalTables {
+        OptimalTables::with_groups(vec![0, 1, 2, 3], vec![vec![0, 1, 2, 3], vec![8, 9, 10, 11]])
+    }
+
+    /// 揃った状態から4手以内の全ての状態の最短手数
+    fn distances() -> HashMap<CubieCube, usize> {
+        let mut dist = HashMap::new();
+        dist.insert(CubieCube::solved(), 0);
+        let mut layer = vec![CubieCube::solved()];
+        for depth in 1..=4 {
+            let mut next = Vec::new();
+            for cube in layer {
+                for m in Move::all() {
+                    let mut cube = cube;
+                    cube.apply(m);
+                    dist.entry(cube).or_insert_with(|| {
+                        next.push(cube);
+                        depth
+                    });
+                }
+            }
+            layer = next;
+        }
+        dist
+    }
+
+    #[test]
+    fn test_database() {
+        let db = PieceDatabase::build(PieceKind::Edge, vec![0, 5]);
+        assert_eq!(db.table.len(), 12 * 11 * 4);
+        assert!(db.table.iter().all(|&x| x != u8::MAX));
+        let (mut pos, mut ori) = ([0; 12], [0; 12]);
+        for index in 0..db.table.len() {
+            db.unrank(index, &mut pos, &mut ori);
+            assert_eq!(db.rank(&pos[..2], &ori[..2]), index);
+        }
+        // 全てのパーツを見る時は最後の向きは数えない
+        let db = PieceDatabase::empty(PieceKind::Corner, (0..8).collect());
+        assert_eq!(db.table.len(), 40320 * 2187);
+
+        let tables = small_tables();
+        for (cube, dist) in distances() {
+            assert!(tables.estimate(&cube) <= dist);
+        }
+        let mut cube = CubieCube::solved();
+        cube.apply_all(&parse_moves("R U").unwrap());
+        assert!(tables.estimate(&cube) > 0);
+    }
+    #[test]
+    fn test_optimal() {
+        let tables = small_tables();
+        for (cube, dist) in distances().into_iter().step_by(97) {
+            let mut search = OptimalSearch::new(&tables, cube);
+            let ans = (0..=dist).find_map(|depth| search.search(depth)).unwrap();
+            assert_eq!(ans.len(), dist);
+            let mut cube = cube;
+            cube.apply_all(&ans);
+            assert!(cube.is_solved());
+        }
+    }
+    #[test]
+    fn test_tables_file() {
+        let path = std::env::temp_dir().join("puzzles_rubiks_optimal_test.bin");
+        let tables = OptimalTables::with_groups(vec![0, 1], vec![vec![0, 1, 2]]);
+        tables.save(&path).unwrap();
+        assert_eq!(OptimalTables::load(&path).unwrap(), tables);
+        std::fs::write(&path, b"RBKO\x01\x00\x09").unwrap();
+        assert!(OptimalTables::load(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,205 @@
+//! 2x2x2(ポケットキューブ)の最短手数の表
+//! DBLのコーナーを固定してU, R, Fだけで回すと状態は7! * 3^6 = 3674160通りしかないので，
+//! 全ての状態の最短手数(HTM)を揃った状態からのBFSで求めておく
+
+use super::super::{PuzzleError, PuzzleResult};
+use super::cubie::CubieCube;
+use super::{Face, Move};
+
+/// 固定するコーナー(DBL)の位置
+const FIXED: usize = 6;
+/// 固定しないコーナーの位置
+const FREE: [usize; 7] = [0, 1, 2, 3, 4, 5, 7];
+/// コーナーの順列の座標の数 7!
+const PERM_NUM: usize = 5040;
+/// コーナーのねじれの座標の数 3^6
+const TWIST_NUM: usize = 729;
+
+/// 全ての状態の最短手数の表
+#[derive(Clone, PartialEq, Eq)]
+pub struct PocketTable {
+    moves: Vec<Move>,
+    perm_move: Vec<u16>,
+    twist_move: Vec<u16>,
+    /// perm * TWIST_NUM + twist
+    table: Vec<u8>,
+}
+
+impl std::fmt::Debug for PocketTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // 表は大きいので表示しない
+        f.debug_struct("PocketTable").finish()
+    }
+}
+
+/// 固定しないコーナーの順列
+fn perm(cube: &CubieCube) -> usize {
+    let values = FREE.map(|i| cube.cp[i]);
+    let mut rank = 0;
+    for i in 0..FREE.len() {
+        let smaller = values[i + 1..].iter().filter(|&&x| x < values[i]).count();
+        rank = rank * (FREE.len() - i) + smaller;
+    }
+    rank
+}
+fn set_perm(cube: &mut CubieCube, mut rank: usize) {
+    let mut digits = [0; 7];
+    for i in (0..FREE.len()).rev() {
+        digits[i] = rank % (FREE.len() - i);
+        rank /= FREE.len() - i;
+    }
+    let mut rest = FREE.to_vec();
+    for (i, &pos) in FREE.iter().enumerate() {
+        cube.cp[pos] = rest.remove(digits[i]) as u8;
+    }
+    cube.cp[FIXED] = FIXED as u8;
+}
+/// 最初の6個のコーナーのねじれ(DBLは0で，DRBは和から決まる)
+fn twist(cube: &CubieCube) -> usize {
+    cube.co[..6].iter().fold(0, |acc, &x| acc * 3 + x as usize)
+}
+fn set_twist(cube: &mut CubieCube, mut twist: usize) {
+    let mut sum = 0;
+    for i in (0..6).rev() {
+        cube.co[i] = (twist % 3) as u8;
+        sum += cube.co[i];
+        twist /= 3;
+    }
+    cube.co[FIXED] = 0;
+    cube.co[7] = (3 - sum % 3) % 3;
+}
+
+impl PocketTable {
+    /// 揃った状態からのBFSで作る
+    pub fn build() -> Self {
+        let moves: Vec<Move> = [Face::U, Face::R, Face::F]
+            .iter()
+            .flat_map(|&face| (1..=3).map(move |turns| Move::new(face, turns)))
+            .collect();
+        let build_move =
+            |size: usize, set: fn(&mut CubieCube, usize), get: fn(&CubieCube) -> usize| {
+                let mut table = vec![0; size * moves.len()];
+                for coord in 0..size {
+                    let mut cube = CubieCube::solved();
+                    set(&mut cube, coord);
+                    for (i, &m) in moves.iter().enumerate() {
+                        let mut moved = cube;
+                        moved.apply(m);
+                        table[coord * moves.len() + i] = get(&moved) as u16;
+                    }
+                }
+                table
+            };
+        let perm_move = build_move(PERM_NUM, set_perm, perm);
+        let twist_move = build_move(TWIST_NUM, set_twist, twist);
+
+        let mut table = vec![u8::MAX; PERM_NUM * TWIST_NUM];
+        table[0] = 0;
+        let mut depth = 0;
+        loop {
+            let mut changed = false;
+            for index in 0..table.len() {
+                if table[index] != depth {
+                    continue;
+                }
+                let (p, t) = (index / TWIST_NUM, index % TWIST_NUM);
+                for m in 0..moves.len() {
+                    let next = perm_move[p * moves.len() + m] as usize * TWIST_NUM
+                        + twist_move[t * moves.len() + m] as usize;
+                    if table[next] == u8::MAX {
+                        table[next] = depth + 1;
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+            depth += 1;
+        }
+        PocketTable {
+            moves,
+            perm_move,
+            twist_move,
+            table,
+        }
+    }
+
+    /// コーナーの状態の番号
+    /// DBLのコーナーが揃っていなければPuzzleErrorを返す
+    fn index(cube: &CubieCube) -> PuzzleResult<usize> {
+        if cube.cp[FIXED] as usize != FIXED || cube.co[FIXED] != 0 {
+            return Err(PuzzleError::new("DBL corner must be fixed"));
+        }
+        Ok(perm(cube) * TWIST_NUM + twist(cube))
+    }
+    /// 揃うまでの最短手数
+    pub fn distance(&self, cube: &CubieCube) -> PuzzleResult<usize> {
+        Ok(self.table[Self::index(cube)?] as usize)
+    }
+    /// 最大の最短手数とその状態の数
+    pub fn max_distance(&self) -> (usize, usize) {
+        let max = *self.table.iter().max().unwrap();
+        let count = self.table.iter().filter(|&&x| x == max).count();
+        (max as usize, count)
+    }
+    /// コーナーを揃える最短手順(U, R, Fのみ)
+    /// 表を一手ずつ最短手数が減る方向にたどる
+    pub fn solve(&self, cube: &CubieCube) -> PuzzleResult<Vec<Move>> {
+        let index = Self::index(cube)?;
+        let (mut p, mut t) = (index / TWIST_NUM, index % TWIST_NUM);
+        let mut ans = Vec::new();
+        let n = self.moves.len();
+        while self.table[p * TWIST_NUM + t] > 0 {
+            let depth = self.table[p * TWIST_NUM + t];
+            let m = (0..n)
+                .find(|&m| {
+                    let next = self.perm_move[p * n + m] as usize * TWIST_NUM
+                        + self.twist_move[t * n + m] as usize;
+                    self.table[next] + 1 == depth
+                })
+                .unwrap();
+            p = self.perm_move[p * n + m] as usize;
+            t = self.twist_move[t * n + m] as usize;
+            ans.push(self.moves[m]);
+        }
+        Ok(ans)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::cubie::CubieCube;
+    use super::super::parse_moves;
+    use super::{perm, set_perm, set_twist, twist, PocketTable, PERM_NUM, TWIST_NUM};
+
+    #[test]
+    fn test_pocket_table() {
+        let mut cube = CubieCube::solved();
+        for x in 0..PERM_NUM {
+            set_perm(&mut cube, x);
+            assert_eq!(perm(&cube), x);
+        }
+        for x in 0..TWIST_NUM {
+            set_twist(&mut cube, x);
+            assert_eq!(twist(&cube), x);
+        }
+
+        let table = PocketTable::build();
+        // 2x2x2はHTMで最大11手，11手の状態は2644通り
+        assert_eq!(table.max_distance(), (11, 2644));
+
+        let mut cube = CubieCube::solved();
+        cube.apply_all(&parse_moves("R U2 F' R' U F2 R U' F R2").unwrap());
+        let ans = table.solve(&cube).unwrap();
+        assert_eq!(ans.len(), table.distance(&cube).unwrap());
+        assert!(ans.len() <= 10);
+        cube.apply_all(&ans);
+        assert_eq!(cube.cp, CubieCube::solved().cp);
+        assert_eq!(cube.co, CubieCube::solved().co);
+
+        let mut cube = CubieCube::solved();
+        cube.apply_all(&parse_moves("D").unwrap());
+        assert!(table.solve(&cube).is_err());
+    }
+}
@@ -0,0 +1,586 @@
+//! NxNxN(4<=N<=6)のキューブを3x3x3に帰着する(reduction)
+//! 7x7x7以上は交換子の見つからない軌道があるのでPuzzleErrorになる
+//! センターを揃え，エッジのウィングを組にしてから外側の層だけで3x3x3として解く
+//! センターとウィングは一つの軌道の3個だけを巡回させる交換子を探索で見つけ，
+//! セットアップの操作で挟んで動かしたい3か所に当てる
+//! ウィングの順列が奇置換の時は内側の層を一回回して直す(パリティ)
+//! 交換子の探索範囲は狭いので，6x6x6までしか解けない(7x7x7以上はPuzzleErrorを返す)
+
+use std::collections::{HashMap, VecDeque};
+
+use super::super::{PuzzleError, PuzzleResult};
+use super::cube::{Cube, LayerMove};
+use super::cubie::{parity, CubieCube};
+use super::Face;
+
+/// 向かい合う面
+fn opposite(face: Face) -> Face {
+    Face::ALL[(face as usize + 3) % 6]
+}
+
+/// 色から面への対応
+/// 奇数の時は中心のステッカー，偶数の時はDBLのコーナーの色で決める
+pub(super) fn color_scheme(cube: &Cube) -> PuzzleResult<[Face; 6]> {
+    let n = cube.size();
+    let pairs = if n % 2 == 1 {
+        Face::ALL
+            .iter()
+            .map(|&face| (cube.facelets()[cube.index(face, n / 2, n / 2)], face))
+            .collect()
+    } else {
+        let d = cube.facelets()[cube.index(Face::D, n - 1, 0)];
+        let b = cube.facelets()[cube.index(Face::B, n - 1, n - 1)];
+        let l = cube.facelets()[cube.index(Face::L, n - 1, 0)];
+        vec![
+            (d, Face::D),
+            (b, Face::B),
+            (l, Face::L),
+            (opposite(d), Face::U),
+            (opposite(b), Face::F),
+            (opposite(l), Face::R),
+        ]
+    };
+    let mut scheme = [None; 6];
+    for (color, face) in pairs {
+        if scheme[color as usize].replace(face).is_some() {
+            return Err(PuzzleError::new("invalid color scheme"));
+        }
+    }
+    Ok(scheme.map(|face| face.unwrap()))
+}
+
+/// 3x3x3のステッカーの番号に対応するNxNxNのステッカーの色で3x3x3を作る
+/// 中心は面の色とし，辺はmidの列(行)のステッカーを使う
+fn to_facelets(cube: &Cube, mid: usize) -> Vec<Face> {
+    let n = cube.size();
+    let map = |x: usize| [0, mid, n - 1][x];
+    let mut facelets = Vec::with_capacity(54);
+    for face in Face::ALL {
+        for r in 0..3 {
+            for c in 0..3 {
+                if r == 1 && c == 1 {
+                    facelets.push(face);
+                } else {
+                    facelets.push(cube.facelets()[cube.index(face, map(r), map(c))]);
+                }
+            }
+        }
+    }
+    facelets
+}
+
+/// コーナーだけを読み取る(エッジは揃っているものとする)
+pub(super) fn corners_of(cube: &Cube) -> PuzzleResult<CubieCube> {
+    let mut facelets = to_facelets(cube, 0);
+    for face in 0..6 {
+        for i in [1, 3, 5, 7] {
+            facelets[face * 9 + i] = Face::ALL[face];
+        }
+    }
+    let cube = CubieCube::read_pieces(&facelets)?;
+    let mut used = [false; 8];
+    for &c in &cube.cp {
+        if std::mem::replace(&mut used[c as usize], true) {
+            return Err(PuzzleError::new("invalid corner permutation"));
+        }
+    }
+    if cube.co.iter().map(|&x| x as usize).sum::<usize>() % 3 != 0 {
+        return Err(PuzzleError::new("a corner is twisted"));
+    }
+    Ok(cube)
+}
+
+/// 3x3x3に帰着したキューブを読み取る
+pub(super) fn to_cubie(cube: &Cube) -> PuzzleResult<CubieCube> {
+    let n = cube.size();
+    let mid = if n % 2 == 1 { n / 2 } else { 1 };
+    let facelets: String = to_facelets(cube, mid)
+        .iter()
+        .map(|face| face.to_string())
+        .collect();
+    CubieCube::from_facelets(&facelets)
+}
+
+/// 軌道の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OrbitKind {
+    Center,
+    Wing,
+}
+
+/// 操作で互いに移り合うステッカーの集まり
+#[derive(Debug)]
+struct Orbit {
+    kind: OrbitKind,
+    stickers: Vec<usize>,
+    /// この軌道の3個だけを巡回させる交換子(操作の番号)
+    commutator: Vec<usize>,
+    /// 巡回させたい3か所(a→b→c→a)ごとのセットアップ
+    setups: HashMap<[usize; 3], Vec<usize>>,
+}
+
+/// 帰着に使う表
+#[derive(Debug)]
+pub struct Reduction {
+    size: usize,
+    /// 使う操作(奇数の時は中心が動かないように真ん中の層は除く)
+    moves: Vec<LayerMove>,
+    perms: Vec<Vec<usize>>,
+    inverse_perms: Vec<Vec<usize>>,
+    /// エッジのステッカーの相方
+    partner: Vec<Option<usize>>,
+    orbits: Vec<Orbit>,
+}
+
+/// 順列の逆
+fn inverse(perm: &[usize]) -> Vec<usize> {
+    let mut inv = vec![0; perm.len()];
+    for (i, &j) in perm.iter().enumerate() {
+        inv[j] = i;
+    }
+    inv
+}
+
+impl Reduction {
+    /// 大きさsizeのキューブの交換子とセットアップを探索して作る
+    pub fn new(size: usize) -> PuzzleResult<Self> {
+        if size < 4 {
+            return Err(PuzzleError::new(format!(
+                "reduction needs size 4 or more: {}",
+                size
+            )));
+        }
+        let solved = Cube::solved(size);
+        let moves: Vec<LayerMove> = Face::ALL
+            .iter()
+            .flat_map(|&face| {
+                (0..size / 2).flat_map(move |layer| {
+                    (1..=3).map(move |turns| LayerMove::new(face, layer, turns))
+                })
+            })
+            .collect();
+        let perms: Vec<Vec<usize>> = moves.iter().map(|&m| solved.move_permutation(m)).collect();
+        let inverse_perms = perms.iter().map(|perm| inverse(perm)).collect();
+
+        let len = solved.facelets().len();
+        let extreme = size as i32 - 1;
+        let positions: Vec<[i32; 3]> = (0..len).map(|i| solved.position(i)).collect();
+        let mut partner = vec![None; len];
+        for i in 0..len {
+            let count = positions[i].iter().filter(|x| x.abs() == extreme).count();
+            if count == 2 {
+                partner[i] = (0..len).find(|&j| j != i && positions[j] == positions[i]);
+            }
+        }
+
+        // 操作で移り合うステッカーをまとめる
+        let mut root: Vec<usize> = (0..len).collect();
+        fn find(root: &mut [usize], x: usize) -> usize {
+            if root[x] != x {
+                root[x] = find(root, root[x]);
+            }
+            root[x]
+        }
+        for perm in &perms {
+            for (i, &j) in perm.iter().enumerate() {
+                let (a, b) = (find(&mut root, i), find(&mut root, j));
+                root[a] = b;
+            }
+        }
+        let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+        for i in 0..len {
+            let r = find(&mut root, i);
+            groups.entry(r).or_default().push(i);
+        }
+        let mut orbits: Vec<Orbit> = groups
+            .into_values()
+            .filter_map(|stickers| {
+                let pos = positions[stickers[0]];
+                let count = pos.iter().filter(|x| x.abs() == extreme).count();
+                // 奇数の時の真ん中のエッジ(3x3x3のエッジとして扱う)
+                let midge = pos.contains(&0);
+                let kind = match count {
+                    1 if stickers.len() > 1 => OrbitKind::Center,
+                    2 if !midge => OrbitKind::Wing,
+                    _ => return None,
+                };
+                Some(Orbit {
+                    kind,
+                    stickers,
+                    commutator: Vec::new(),
+                    setups: HashMap::new(),
+                })
+            })
+            .collect();
+        orbits.sort_by_key(|orbit| orbit.stickers[0]);
+
+        let mut reduction = Reduction {
+            size,
+            moves,
+            perms,
+            inverse_perms,
+            partner,
+            orbits,
+        };
+        reduction.find_commutators()?;
+        for i in 0..reduction.orbits.len() {
+            reduction.build_setups(i);
+        }
+        Ok(reduction)
+    }
+
+    /// 手順で各ステッカーが移る先
+    fn compose(&self, seq: &[usize]) -> Vec<usize> {
+        let mut result: Vec<usize> = (0..self.perms[0].len()).collect();
+        for &m in seq {
+            for x in result.iter_mut() {
+                *x = self.perms[m][*x];
+            }
+        }
+        result
+    }
+    /// 操作の番号の逆操作
+    fn inverse_move(&self, m: usize) -> usize {
+        let inv = self.moves[m].inverse();
+        self.moves.iter().position(|&x| x == inv).unwrap()
+    }
+    /// 手順の逆
+    fn inverse_seq(&self, seq: &[usize]) -> Vec<usize> {
+        seq.iter().rev().map(|&m| self.inverse_move(m)).collect()
+    }
+
+    /// [A, B] = A B A' B' の形で，Aがm1 m2 m1'か一手，Bが一手のものから
+    /// 各軌道の3個だけを巡回させるものを探す
+    /// 見つからない軌道はBもm1 m2 m1'の形にして探す(5x5x5の十字のセンターなど)
+    fn find_commutators(&mut self) -> PuzzleResult<()> {
+        let num = self.moves.len();
+        let singles: Vec<Vec<usize>> = (0..num).map(|m| vec![m]).collect();
+        let mut conjugates = Vec::new();
+        for m1 in 0..num {
+            for m2 in 0..num {
+                if self.moves[m1].face() != self.moves[m2].face() {
+                    conjugates.push(vec![m1, m2, self.inverse_move(m1)]);
+                }
+            }
+        }
+        let mut a_candidates = singles.clone();
+        a_candidates.extend_from_slice(&conjugates);
+        let orbit_of: HashMap<usize, usize> = self
+            .orbits
+            .iter()
+            .enumerate()
+            .flat_map(|(k, orbit)| orbit.stickers.iter().map(move |&i| (i, k)))
+            .collect();
+        let mut rest = self.orbits.len();
+        for b_candidates in [&singles, &conjugates] {
+            let b_perms: Vec<(Vec<usize>, Vec<usize>)> = b_candidates
+                .iter()
+                .map(|b| {
+                    let perm = self.compose(b);
+                    let inv = inverse(&perm);
+                    (perm, inv)
+                })
+                .collect();
+            for a in &a_candidates {
+                if rest == 0 {
+                    return Ok(());
+                }
+                let perm_a = self.compose(a);
+                let inv_a = inverse(&perm_a);
+                for (b, (perm_b, inv_b)) in b_candidates.iter().zip(&b_perms) {
+                    let mut moved = Vec::new();
+                    let mut perm = vec![0; perm_a.len()];
+                    for i in 0..perm_a.len() {
+                        perm[i] = inv_b[inv_a[perm_b[perm_a[i]]]];
+                        if perm[i] != i {
+                            moved.push(i);
+                            if moved.len() > 6 {
+                                break;
+                            }
+                        }
+                    }
+                    if moved.len() != 3 && moved.len() != 6 {
+                        continue;
+                    }
+                    for &i in &moved {
+                        let k = match orbit_of.get(&i) {
+                            Some(&k) => k,
+                            None => continue,
+                        };
+                        if !self.orbits[k].commutator.is_empty() {
+                            continue;
+                        }
+                        let in_orbit: Vec<usize> = moved
+                            .iter()
+                            .copied()
+                            .filter(|x| orbit_of.get(x) == Some(&k))
+                            .collect();
+                        // センターは3個だけ，ウィングは3個とその相方だけが動くものを使う
+                        let pure = in_orbit.len() == 3
+                            && match self.orbits[k].kind {
+                                OrbitKind::Center => moved.len() == 3,
+                                OrbitKind::Wing => {
+                                    moved.len() == 6
+                                        && in_orbit
+                                            .iter()
+                                            .all(|&x| moved.contains(&self.partner[x].unwrap()))
+                                }
+                            };
+                        if pure {
+                            let mut seq = a.clone();
+                            seq.extend_from_slice(b);
+                            seq.extend(self.inverse_seq(a));
+                            seq.extend(self.inverse_seq(b));
+                            let p = in_orbit[0];
+                            let cycle = [p, perm[p], perm[perm[p]]];
+                            self.orbits[k].commutator = seq;
+                            self.orbits[k].setups.insert(cycle, Vec::new());
+                            rest -= 1;
+                        }
+                    }
+                }
+            }
+        }
+        if rest > 0 {
+            return Err(PuzzleError::new("commutator not found"));
+        }
+        Ok(())
+    }
+
+    /// 交換子の3か所から，セットアップSで挟んだS C S'が巡回させる3か所へのBFS
+    fn build_setups(&mut self, k: usize) {
+        let start = *self.orbits[k].setups.keys().next().unwrap();
+        let mut setups = HashMap::new();
+        setups.insert(start, Vec::new());
+        let mut queue = VecDeque::from(vec![start]);
+        while let Some(cycle) = queue.pop_front() {
+            let setup: Vec<usize> = setups[&cycle].clone();
+            for m in 0..self.moves.len() {
+                if setup
+                    .first()
+                    .map(|&x| self.moves[x].face() == self.moves[m].face())
+                    == Some(true)
+                {
+                    continue;
+                }
+                let next = cycle.map(|x| self.inverse_perms[m][x]);
+                if let std::collections::hash_map::Entry::Vacant(e) = setups.entry(next) {
+                    let mut s = vec![m];
+                    s.extend_from_slice(&setup);
+                    e.insert(s);
+                    queue.push_back(next);
+                }
+            }
+        }
+        self.orbits[k].setups = setups;
+    }
+
+    /// a→b→c→aと巡回させる手順
+    fn cycle(&self, k: usize, [a, b, c]: [usize; 3]) -> Option<Vec<usize>> {
+        let orbit = &self.orbits[k];
+        let setup = [[a, b, c], [b, c, a], [c, a, b]]
+            .iter()
+            .filter_map(|cycle| orbit.setups.get(cycle))
+            .min_by_key(|setup| setup.len())?;
+        let mut seq = setup.clone();
+        seq.extend_from_slice(&orbit.commutator);
+        seq.extend(self.inverse_seq(setup));
+        Some(seq)
+    }
+
+    /// 揃った時の各ステッカーの色
+    /// 偶数の時はコーナーの順列が奇置換ならUFとUBのエッジを入れ替えた状態を目指す(PLLパリティ)
+    /// 奇数の時はウィングを真ん中のエッジと同じ色にする
+    fn goal(&self, cube: &Cube) -> PuzzleResult<Vec<Face>> {
+        let n = self.size;
+        let mut goal: Vec<Face> = (0..cube.facelets().len())
+            .map(|i| cube.face_of(i))
+            .collect();
+        if n.is_multiple_of(2) {
+            if parity(&corners_of(cube)?.cp) == 1 {
+                for c in 1..n - 1 {
+                    goal[cube.index(Face::F, 0, c)] = Face::B;
+                    goal[cube.index(Face::B, 0, c)] = Face::F;
+                }
+            }
+        } else {
+            for orbit in self.orbits.iter().filter(|o| o.kind == OrbitKind::Wing) {
+                for &i in &orbit.stickers {
+                    let mut pos = cube.position(i);
+                    for x in pos.iter_mut() {
+                        if x.abs() != n as i32 - 1 {
+                            *x = 0;
+                        }
+                    }
+                    goal[i] = cube.facelets()[cube.index_at(cube.face_of(i), pos)];
+                }
+            }
+        }
+        Ok(goal)
+    }
+
+    /// 3x3x3に帰着させる手順を求め，cubeに適用する
+    /// cubeは色を面に合わせてあるものとする
+    pub fn reduce(&self, cube: &mut Cube) -> PuzzleResult<Vec<LayerMove>> {
+        let goal = self.goal(cube)?;
+        let mut ans = Vec::new();
+        // パリティ: ウィングの順列が奇置換なら内側の層を一回回す
+        for orbit in self.orbits.iter().filter(|o| o.kind == OrbitKind::Wing) {
+            if self.parity(cube, &goal, orbit)? == 1 {
+                let m = self.parity_move(cube, orbit.stickers[0]);
+                cube.apply(m);
+                ans.push(m);
+            }
+        }
+        // センター，ウィングの順に揃える
+        for kind in [OrbitKind::Center, OrbitKind::Wing] {
+            for k in 0..self.orbits.len() {
+                if self.orbits[k].kind == kind {
+                    self.solve_orbit(cube, &goal, k, &mut ans)?;
+                }
+            }
+        }
+        Ok(ans)
+    }
+
+    /// ステッカーの色(エッジは相方の色と組にする)
+    fn label(&self, facelets: &[Face], i: usize) -> (Face, Option<Face>) {
+        (facelets[i], self.partner[i].map(|j| facelets[j]))
+    }
+
+    /// ウィングの順列の偶奇
+    fn parity(&self, cube: &Cube, goal: &[Face], orbit: &Orbit) -> PuzzleResult<u8> {
+        let mut perm = Vec::with_capacity(orbit.stickers.len());
+        for &i in &orbit.stickers {
+            let label = self.label(cube.facelets(), i);
+            let j = orbit
+                .stickers
+                .iter()
+                .position(|&j| self.label(goal, j) == label)
+                .ok_or_else(|| PuzzleError::new("invalid edge"))?;
+            perm.push(j as u8);
+        }
+        Ok(parity(&perm))
+    }
+    /// ステッカーiを含む内側の層を回す操作
+    fn parity_move(&self, cube: &Cube, i: usize) -> LayerMove {
+        let n = self.size as i32;
+        let pos = cube.position(i);
+        let axis = (0..3).find(|&k| pos[k].abs() != n - 1).unwrap();
+        let t = pos[axis];
+        let face = match (axis, t > 0) {
+            (0, true) => Face::R,
+            (0, false) => Face::L,
+            (1, true) => Face::U,
+            (1, false) => Face::D,
+            (_, true) => Face::F,
+            (_, false) => Face::B,
+        };
+        LayerMove::new(face, ((n - 1 - t.abs()) / 2) as usize, 1)
+    }
+
+    /// 一つの軌道を順に揃える
+    /// 位置pが違えば，後ろからpに入るべきものを持つqと別の位置rを選んでq→p→r→qと巡回させる
+    fn solve_orbit(
+        &self,
+        cube: &mut Cube,
+        goal: &[Face],
+        k: usize,
+        ans: &mut Vec<LayerMove>,
+    ) -> PuzzleResult<()> {
+        let stickers = &self.orbits[k].stickers;
+        let len = stickers.len();
+        for x in 0..len {
+            let p = stickers[x];
+            let target = self.label(goal, p);
+            if self.label(cube.facelets(), p) == target {
+                continue;
+            }
+            let mut best: Option<Vec<usize>> = None;
+            for &q in &stickers[x + 1..] {
+                if self.label(cube.facelets(), q) != target {
+                    continue;
+                }
+                let rs: Vec<usize> = if len - x >= 3 {
+                    stickers[x + 1..]
+                        .iter()
+                        .copied()
+                        .filter(|&r| r != q)
+                        .collect()
+                } else {
+                    // 最後の2か所が入れ替わっている時は，揃えた所から同じ色のものを借りる
+                    let label = self.label(cube.facelets(), p);
+                    stickers[..x]
+                        .iter()
+                        .copied()
+                        .filter(|&r| self.label(cube.facelets(), r) == label)
+                        .collect()
+                };
+                for r in rs {
+                    if let Some(seq) = self.cycle(k, [q, p, r]) {
+                        if best.as_ref().is_none_or(|b| seq.len() < b.len()) {
+                            best = Some(seq);
+                        }
+                    }
+                }
+            }
+            let seq = best.ok_or_else(|| PuzzleError::new("cannot solve centers and edges"))?;
+            for m in seq {
+                cube.permute(&self.perms[m]);
+                ans.push(self.moves[m]);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::cube::Cube;
+    use super::{color_scheme, to_cubie, OrbitKind, Reduction};
+
+    #[test]
+    fn test_reduction() {
+        for size in [4, 5] {
+            let reduction = Reduction::new(size).unwrap();
+            // 4x4x4はセンター1つとウィング2つ，5x5x5はセンター2つとウィング2つ
+            let count = |kind| reduction.orbits.iter().filter(|o| o.kind == kind).count();
+            assert_eq!(
+                (count(OrbitKind::Center), count(OrbitKind::Wing)),
+                (size - 3, 2)
+            );
+            // 軌道のどの3か所も巡回させられる
+            for (k, orbit) in reduction.orbits.iter().enumerate() {
+                assert_eq!(orbit.stickers.len(), 24);
+                for &a in &orbit.stickers {
+                    for &b in &orbit.stickers {
+                        for &c in &orbit.stickers {
+                            if a != b && b != c && c != a {
+                                assert!(reduction.cycle(k, [a, b, c]).is_some());
+                            }
+                        }
+                    }
+                }
+            }
+
+            let mut cube = Cube::from_scramble(
+                size,
+                "Rw U2 2F' L D Bw2 2R U' F 2L' Dw B2 R 2U Lw' F2 2D B'",
+            )
+            .unwrap();
+            let scheme = color_scheme(&cube).unwrap();
+            cube.recolor(&scheme);
+            let ans = reduction.reduce(&mut cube).unwrap();
+            let mut replay = Cube::from_scramble(
+                size,
+                "Rw U2 2F' L D Bw2 2R U' F 2L' Dw B2 R 2U Lw' F2 2D B'",
+            )
+            .unwrap();
+            replay.recolor(&scheme);
+            replay.apply_all(&ans);
+            assert_eq!(replay, cube);
+            // 3x3x3として読み取れる
+            to_cubie(&cube).unwrap();
+        }
+    }
+}
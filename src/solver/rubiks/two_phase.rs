@@ -0,0 +1,631 @@
+//! Kociembaの二段階アルゴリズム
+//! phase1で<U,D,R,L,F,B>を使ってコーナーのねじれ，エッジの反転，Eスライスのエッジの位置を揃え，
+//! phase2で<U,D,R2,L2,F2,B2>だけを使って残りの順列を揃える
+//! 各座標の遷移表と枝刈り表はローカルで作り，ファイルにキャッシュできる
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use super::super::{PuzzleError, PuzzleResult};
+use super::cubie::{CubieCube, CORNER_NUM, EDGE_NUM};
+use super::{Face, Move, MOVE_NUM};
+
+/// コーナーのねじれの座標の数 3^7
+pub const TWIST_NUM: usize = 2187;
+/// エッジの反転の座標の数 2^11
+pub const FLIP_NUM: usize = 2048;
+/// Eスライスのエッジの位置の座標の数 12C4
+pub const SLICE_NUM: usize = 495;
+/// コーナーの順列の座標の数 8!
+pub const CORNER_PERM_NUM: usize = 40320;
+/// U面とD面のエッジの順列の座標の数 8!
+pub const EDGE_PERM_NUM: usize = 40320;
+/// Eスライスのエッジの順列の座標の数 4!
+pub const SLICE_PERM_NUM: usize = 24;
+
+/// ファイルの先頭に書くマジックナンバー
+const TABLES_MAGIC: &[u8; 4] = b"RBK2";
+
+/// nCk
+fn combination(n: usize, k: usize) -> usize {
+    if k > n {
+        return 0;
+    }
+    let mut c = 1;
+    for i in 0..k {
+        c = c * (n - i) / (i + 1);
+    }
+    c
+}
+
+/// 順列のランク(辞書順)
+fn perm_rank(perm: &[u8]) -> usize {
+    let mut rank = 0;
+    for i in 0..perm.len() {
+        let smaller = perm[i + 1..].iter().filter(|&&x| x < perm[i]).count();
+        rank = rank * (perm.len() - i) + smaller;
+    }
+    rank
+}
+/// ランクから順列を復元する
+fn perm_unrank(mut rank: usize, perm: &mut [u8]) {
+    let n = perm.len();
+    let mut digits = vec![0; n];
+    for i in (0..n).rev() {
+        digits[i] = rank % (n - i);
+        rank /= n - i;
+    }
+    let mut rest: Vec<u8> = (0..n as u8).collect();
+    for i in 0..n {
+        perm[i] = rest.remove(digits[i]);
+    }
+}
+
+/// キューブの座標
+/// 遷移表を作る時はsetで作ったキューブに操作を掛けてgetで読み取る
+impl CubieCube {
+    /// コーナーのねじれ
+    pub fn twist(&self) -> usize {
+        self.co[..CORNER_NUM - 1]
+            .iter()
+            .fold(0, |acc, &x| acc * 3 + x as usize)
+    }
+    pub fn set_twist(&mut self, mut twist: usize) {
+        let mut sum = 0;
+        for i in (0..CORNER_NUM - 1).rev() {
+            self.co[i] = (twist % 3) as u8;
+            sum += self.co[i];
+            twist /= 3;
+        }
+        self.co[CORNER_NUM - 1] = (3 - sum % 3) % 3;
+    }
+    /// エッジの反転
+    pub fn flip(&self) -> usize {
+        self.eo[..EDGE_NUM - 1]
+            .iter()
+            .fold(0, |acc, &x| acc * 2 + x as usize)
+    }
+    pub fn set_flip(&mut self, mut flip: usize) {
+        let mut sum = 0;
+        for i in (0..EDGE_NUM - 1).rev() {
+            self.eo[i] = (flip % 2) as u8;
+            sum += self.eo[i];
+            flip /= 2;
+        }
+        self.eo[EDGE_NUM - 1] = sum % 2;
+    }
+    /// Eスライスのエッジ(FR, FL, BL, BR)がどの位置にあるか，揃っていれば0
+    pub fn slice(&self) -> usize {
+        let mut slice = 0;
+        let mut x = 0;
+        for j in (0..EDGE_NUM).rev() {
+            if self.ep[j] >= 8 {
+                slice += combination(EDGE_NUM - 1 - j, x + 1);
+                x += 1;
+            }
+        }
+        slice
+    }
+    pub fn set_slice(&mut self, mut slice: usize) {
+        // スライスのエッジとそれ以外を順に置く
+        let mut is_slice = [false; EDGE_NUM];
+        let mut x = 4;
+        for (j, flag) in is_slice.iter_mut().enumerate() {
+            if x > 0 && slice >= combination(EDGE_NUM - 1 - j, x) {
+                *flag = true;
+                slice -= combination(EDGE_NUM - 1 - j, x);
+                x -= 1;
+            }
+        }
+        let mut slice_edge = 8;
+        let mut other_edge = 0;
+        for (e, &flag) in self.ep.iter_mut().zip(is_slice.iter()) {
+            if flag {
+                *e = slice_edge;
+                slice_edge += 1;
+            } else {
+                *e = other_edge;
+                other_edge += 1;
+            }
+        }
+    }
+    /// コーナーの順列
+    pub fn corner_perm(&self) -> usize {
+        perm_rank(&self.cp)
+    }
+    pub fn set_corner_perm(&mut self, perm: usize) {
+        perm_unrank(perm, &mut self.cp);
+    }
+    /// U面とD面のエッジの順列(phase2でのみ意味がある)
+    pub fn edge_perm(&self) -> usize {
+        perm_rank(&self.ep[..8])
+    }
+    pub fn set_edge_perm(&mut self, perm: usize) {
+        perm_unrank(perm, &mut self.ep[..8]);
+    }
+    /// Eスライスのエッジの順列(phase2でのみ意味がある)
+    pub fn slice_perm(&self) -> usize {
+        let mut perm = [0; 4];
+        for (i, p) in perm.iter_mut().enumerate() {
+            *p = self.ep[8 + i] - 8;
+        }
+        perm_rank(&perm)
+    }
+    pub fn set_slice_perm(&mut self, slice_perm: usize) {
+        let mut perm = [0; 4];
+        perm_unrank(slice_perm, &mut perm);
+        for (i, p) in perm.iter().enumerate() {
+            self.ep[8 + i] = p + 8;
+        }
+    }
+}
+
+/// 二段階アルゴリズムで使う表
+/// 遷移表はmove[coord * MOVE_NUM + m]，枝刈り表は揃うまでの最小手数
+#[derive(Clone, PartialEq, Eq)]
+pub struct TwoPhaseTables {
+    twist_move: Vec<u16>,
+    flip_move: Vec<u16>,
+    slice_move: Vec<u16>,
+    corner_perm_move: Vec<u16>,
+    edge_perm_move: Vec<u16>,
+    slice_perm_move: Vec<u16>,
+    /// slice * TWIST_NUM + twist
+    slice_twist_prun: Vec<u8>,
+    /// slice * FLIP_NUM + flip
+    slice_flip_prun: Vec<u8>,
+    /// slice_perm * CORNER_PERM_NUM + corner_perm
+    slice_corner_prun: Vec<u8>,
+    /// slice_perm * EDGE_PERM_NUM + edge_perm
+    slice_edge_prun: Vec<u8>,
+}
+
+impl std::fmt::Debug for TwoPhaseTables {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // 表は大きいので表示しない
+        f.debug_struct("TwoPhaseTables").finish()
+    }
+}
+
+impl TwoPhaseTables {
+    /// 全ての表を作る
+    pub fn build() -> Self {
+        let twist_move = Self::build_move(
+            TWIST_NUM,
+            false,
+            |cube, x| cube.set_twist(x),
+            |cube| cube.twist(),
+        );
+        let flip_move = Self::build_move(
+            FLIP_NUM,
+            false,
+            |cube, x| cube.set_flip(x),
+            |cube| cube.flip(),
+        );
+        let slice_move = Self::build_move(
+            SLICE_NUM,
+            false,
+            |cube, x| cube.set_slice(x),
+            |cube| cube.slice(),
+        );
+        let corner_perm_move = Self::build_move(
+            CORNER_PERM_NUM,
+            true,
+            |cube, x| cube.set_corner_perm(x),
+            |cube| cube.corner_perm(),
+        );
+        let edge_perm_move = Self::build_move(
+            EDGE_PERM_NUM,
+            true,
+            |cube, x| cube.set_edge_perm(x),
+            |cube| cube.edge_perm(),
+        );
+        let slice_perm_move = Self::build_move(
+            SLICE_PERM_NUM,
+            true,
+            |cube, x| cube.set_slice_perm(x),
+            |cube| cube.slice_perm(),
+        );
+        let phase1 = Move::all();
+        let phase2 = Move::phase2();
+        let slice_twist_prun =
+            Self::build_prun(&slice_move, &twist_move, SLICE_NUM, TWIST_NUM, &phase1);
+        let slice_flip_prun =
+            Self::build_prun(&slice_move, &flip_move, SLICE_NUM, FLIP_NUM, &phase1);
+        let slice_corner_prun = Self::build_prun(
+            &slice_perm_move,
+            &corner_perm_move,
+            SLICE_PERM_NUM,
+            CORNER_PERM_NUM,
+            &phase2,
+        );
+        let slice_edge_prun = Self::build_prun(
+            &slice_perm_move,
+            &edge_perm_move,
+            SLICE_PERM_NUM,
+            EDGE_PERM_NUM,
+            &phase2,
+        );
+        TwoPhaseTables {
+            twist_move,
+            flip_move,
+            slice_move,
+            corner_perm_move,
+            edge_perm_move,
+            slice_perm_move,
+            slice_twist_prun,
+            slice_flip_prun,
+            slice_corner_prun,
+            slice_edge_prun,
+        }
+    }
+    /// 座標の遷移表を作る
+    /// phase2がtrueの時はphase2で使う操作だけを計算する
+    fn build_move(
+        size: usize,
+        phase2: bool,
+        set: impl Fn(&mut CubieCube, usize),
+        get: impl Fn(&CubieCube) -> usize,
+    ) -> Vec<u16> {
+        let mut table = vec![0; size * MOVE_NUM];
+        for coord in 0..size {
+            let mut cube = CubieCube::solved();
+            set(&mut cube, coord);
+            for face in Face::ALL {
+                let basic = CubieCube::basic_move(face);
+                let mut moved = cube;
+                for turns in 1..=3 {
+                    moved = moved.multiply(basic);
+                    let m = Move::new(face, turns);
+                    if !phase2 || m.is_phase2() {
+                        table[coord * MOVE_NUM + m.index()] = get(&moved) as u16;
+                    }
+                }
+            }
+        }
+        table
+    }
+    /// 二つの座標を組にした枝刈り表を揃った状態からのBFSで作る
+    fn build_prun(
+        move1: &[u16],
+        move2: &[u16],
+        size1: usize,
+        size2: usize,
+        moves: &[Move],
+    ) -> Vec<u8> {
+        let mut table = vec![u8::MAX; size1 * size2];
+        table[0] = 0;
+        let mut depth = 0;
+        let mut done = 1;
+        while done < size1 * size2 {
+            let mut changed = false;
+            for index in 0..size1 * size2 {
+                if table[index] != depth {
+                    continue;
+                }
+                let (c1, c2) = (index / size2, index % size2);
+                for m in moves {
+                    let n1 = move1[c1 * MOVE_NUM + m.index()] as usize;
+                    let n2 = move2[c2 * MOVE_NUM + m.index()] as usize;
+                    let next = n1 * size2 + n2;
+                    if table[next] == u8::MAX {
+                        table[next] = depth + 1;
+                        done += 1;
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+            depth += 1;
+        }
+        table
+    }
+
+    /// pathにあればそれを読み込み，なければ作ってpathに保存する
+    pub fn load_or_build(path: impl AsRef<Path>) -> PuzzleResult<Self> {
+        let path = path.as_ref();
+        if path.exists() {
+            if let Ok(tables) = Self::load(path) {
+                return Ok(tables);
+            }
+        }
+        let tables = Self::build();
+        tables.save(path)?;
+        Ok(tables)
+    }
+    /// バイナリ形式でファイルに保存する
+    pub fn save(&self, path: impl AsRef<Path>) -> PuzzleResult<()> {
+        let file = File::create(path.as_ref())
+            .map_err(|e| PuzzleError::new(format!("cannot create tables file: {}", e)))?;
+        let mut writer = BufWriter::new(file);
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(TABLES_MAGIC);
+        for table in self.move_tables() {
+            bytes.extend(table.iter().flat_map(|x| x.to_le_bytes()));
+        }
+        for table in self.prun_tables() {
+            bytes.extend_from_slice(table);
+        }
+        writer
+            .write_all(&bytes)
+            .and_then(|_| writer.flush())
+            .map_err(|e| PuzzleError::new(format!("cannot write tables file: {}", e)))
+    }
+    /// saveで保存したファイルを読み込む
+    pub fn load(path: impl AsRef<Path>) -> PuzzleResult<Self> {
+        let file = File::open(path.as_ref())
+            .map_err(|e| PuzzleError::new(format!("cannot open tables file: {}", e)))?;
+        let mut bytes = Vec::new();
+        BufReader::new(file)
+            .read_to_end(&mut bytes)
+            .map_err(|e| PuzzleError::new(format!("cannot read tables file: {}", e)))?;
+        if bytes.len() < 4 || &bytes[..4] != TABLES_MAGIC {
+            return Err(PuzzleError::new("invalid tables file"));
+        }
+        // 空の表を作って大きさを合わせて埋める
+        let mut tables = TwoPhaseTables {
+            twist_move: vec![0; TWIST_NUM * MOVE_NUM],
+            flip_move: vec![0; FLIP_NUM * MOVE_NUM],
+            slice_move: vec![0; SLICE_NUM * MOVE_NUM],
+            corner_perm_move: vec![0; CORNER_PERM_NUM * MOVE_NUM],
+            edge_perm_move: vec![0; EDGE_PERM_NUM * MOVE_NUM],
+            slice_perm_move: vec![0; SLICE_PERM_NUM * MOVE_NUM],
+            slice_twist_prun: vec![0; SLICE_NUM * TWIST_NUM],
+            slice_flip_prun: vec![0; SLICE_NUM * FLIP_NUM],
+            slice_corner_prun: vec![0; SLICE_PERM_NUM * CORNER_PERM_NUM],
+            slice_edge_prun: vec![0; SLICE_PERM_NUM * EDGE_PERM_NUM],
+        };
+        let len = 4
+            + tables
+                .move_tables()
+                .iter()
+                .map(|x| x.len() * 2)
+                .sum::<usize>()
+            + tables.prun_tables().iter().map(|x| x.len()).sum::<usize>();
+        if bytes.len() != len {
+            return Err(PuzzleError::new("invalid tables file"));
+        }
+        // move_tablesと同じ順の座標の数，遷移先がこれ以上なら壊れている
+        let sizes = [
+            TWIST_NUM,
+            FLIP_NUM,
+            SLICE_NUM,
+            CORNER_PERM_NUM,
+            EDGE_PERM_NUM,
+            SLICE_PERM_NUM,
+        ];
+        let mut pos = 4;
+        for (table, &size) in tables.move_tables_mut().iter_mut().zip(&sizes) {
+            for (x, b) in table.iter_mut().zip(bytes[pos..].chunks(2)) {
+                *x = u16::from_le_bytes([b[0], b[1]]);
+                if *x as usize >= size {
+                    return Err(PuzzleError::new(format!(
+                        "invalid tables file: coordinate {} is out of range {}",
+                        x, size
+                    )));
+                }
+            }
+            pos += table.len() * 2;
+        }
+        for table in tables.prun_tables_mut() {
+            let len = table.len();
+            table.copy_from_slice(&bytes[pos..pos + len]);
+            pos += len;
+        }
+        Ok(tables)
+    }
+    fn move_tables(&self) -> [&Vec<u16>; 6] {
+        [
+            &self.twist_move,
+            &self.flip_move,
+            &self.slice_move,
+            &self.corner_perm_move,
+            &self.edge_perm_move,
+            &self.slice_perm_move,
+        ]
+    }
+    fn move_tables_mut(&mut self) -> [&mut Vec<u16>; 6] {
+        [
+            &mut self.twist_move,
+            &mut self.flip_move,
+            &mut self.slice_move,
+            &mut self.corner_perm_move,
+            &mut self.edge_perm_move,
+            &mut self.slice_perm_move,
+        ]
+    }
+    fn prun_tables(&self) -> [&Vec<u8>; 4] {
+        [
+            &self.slice_twist_prun,
+            &self.slice_flip_prun,
+            &self.slice_corner_prun,
+            &self.slice_edge_prun,
+        ]
+    }
+    fn prun_tables_mut(&mut self) -> [&mut Vec<u8>; 4] {
+        [
+            &mut self.slice_twist_prun,
+            &mut self.slice_flip_prun,
+            &mut self.slice_corner_prun,
+            &mut self.slice_edge_prun,
+        ]
+    }
+
+    /// phase1の下界
+    fn phase1_prun(&self, twist: usize, flip: usize, slice: usize) -> usize {
+        let a = self.slice_twist_prun[slice * TWIST_NUM + twist];
+        let b = self.slice_flip_prun[slice * FLIP_NUM + flip];
+        a.max(b) as usize
+    }
+    /// phase2の下界
+    fn phase2_prun(&self, corner: usize, edge: usize, slice: usize) -> usize {
+        let a = self.slice_corner_prun[slice * CORNER_PERM_NUM + corner];
+        let b = self.slice_edge_prun[slice * EDGE_PERM_NUM + edge];
+        a.max(b) as usize
+    }
+}
+
+/// 直前の操作prevの後にmを探索する必要があるか
+/// 同じ面を続けて回すのと，向かい合う面をD→Uのように逆順で回すのは重複するので省く
+pub fn is_redundant(prev: Option<&Move>, m: Move) -> bool {
+    match prev {
+        Some(prev) => {
+            let (p, f) = (prev.face() as usize, m.face() as usize);
+            p == f || p == f + 3
+        }
+        None => false,
+    }
+}
+
+/// 二段階アルゴリズムの探索
+pub struct TwoPhaseSearch<'a> {
+    tables: &'a TwoPhaseTables,
+    cube: CubieCube,
+    /// 全体の手数の上限
+    max_length: usize,
+    path: Vec<Move>,
+}
+
+impl<'a> TwoPhaseSearch<'a> {
+    pub fn new(tables: &'a TwoPhaseTables, cube: CubieCube, max_length: usize) -> Self {
+        TwoPhaseSearch {
+            tables,
+            cube,
+            max_length,
+            path: Vec::new(),
+        }
+    }
+    /// phase1の手数がちょうどdepthの解を全て試し，
+    /// 続くphase2と合わせてmax_length以内で揃うものがあれば返す
+    pub fn search(&mut self, depth: usize) -> Option<Vec<Move>> {
+        let twist = self.cube.twist();
+        let flip = self.cube.flip();
+        let slice = self.cube.slice();
+        if self.tables.phase1_prun(twist, flip, slice) > depth {
+            return None;
+        }
+        self.path.clear();
+        if self.phase1(twist, flip, slice, depth) {
+            Some(self.path.clone())
+        } else {
+            None
+        }
+    }
+
+    fn phase1(&mut self, twist: usize, flip: usize, slice: usize, depth: usize) -> bool {
+        if depth == 0 {
+            // phase2で使える操作で終わる解は，より短いphase1の解で既に試している
+            if twist != 0 || flip != 0 || slice != 0 {
+                return false;
+            }
+            if let Some(last) = self.path.last() {
+                if last.is_phase2() {
+                    return false;
+                }
+            }
+            return self.start_phase2();
+        }
+        if self.tables.phase1_prun(twist, flip, slice) > depth {
+            return false;
+        }
+        for m in Move::all() {
+            if is_redundant(self.path.last(), m) {
+                continue;
+            }
+            let t = &self.tables;
+            let i = m.index();
+            let twist = t.twist_move[twist * MOVE_NUM + i] as usize;
+            let flip = t.flip_move[flip * MOVE_NUM + i] as usize;
+            let slice = t.slice_move[slice * MOVE_NUM + i] as usize;
+            self.path.push(m);
+            if self.phase1(twist, flip, slice, depth - 1) {
+                return true;
+            }
+            self.path.pop();
+        }
+        false
+    }
+
+    /// phase1の解の後の状態からphase2を探索する
+    fn start_phase2(&mut self) -> bool {
+        let rest = match self.max_length.checked_sub(self.path.len()) {
+            Some(x) => x,
+            None => return false,
+        };
+        let mut cube = self.cube;
+        cube.apply_all(&self.path);
+        let corner = cube.corner_perm();
+        let edge = cube.edge_perm();
+        let slice = cube.slice_perm();
+        let phase1_len = self.path.len();
+        for depth in self.tables.phase2_prun(corner, edge, slice)..=rest {
+            if self.phase2(corner, edge, slice, depth) {
+                return true;
+            }
+            self.path.truncate(phase1_len);
+        }
+        false
+    }
+
+    fn phase2(&mut self, corner: usize, edge: usize, slice: usize, depth: usize) -> bool {
+        if depth == 0 {
+            return corner == 0 && edge == 0 && slice == 0;
+        }
+        if self.tables.phase2_prun(corner, edge, slice) > depth {
+            return false;
+        }
+        for m in Move::phase2() {
+            if is_redundant(self.path.last(), m) {
+                continue;
+            }
+            let t = &self.tables;
+            let i = m.index();
+            let corner = t.corner_perm_move[corner * MOVE_NUM + i] as usize;
+            let edge = t.edge_perm_move[edge * MOVE_NUM + i] as usize;
+            let slice = t.slice_perm_move[slice * MOVE_NUM + i] as usize;
+            self.path.push(m);
+            if self.phase2(corner, edge, slice, depth - 1) {
+                return true;
+            }
+            self.path.pop();
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::cubie::CubieCube;
+    use super::super::parse_moves;
+    use super::{perm_rank, perm_unrank, SLICE_NUM, TWIST_NUM};
+
+    #[test]
+    fn test_coordinates() {
+        let mut perm = [0; 5];
+        for rank in 0..120 {
+            perm_unrank(rank, &mut perm);
+            assert_eq!(perm_rank(&perm), rank);
+        }
+        let mut cube = CubieCube::solved();
+        assert_eq!(
+            (cube.twist(), cube.flip(), cube.slice(), cube.corner_perm()),
+            (0, 0, 0, 0)
+        );
+        for x in 0..TWIST_NUM {
+            cube.set_twist(x);
+            assert_eq!(cube.twist(), x);
+        }
+        for x in 0..SLICE_NUM {
+            cube.set_slice(x);
+            assert_eq!(cube.slice(), x);
+        }
+        // phase2の操作ではphase1の座標は変わらない
+        let mut cube = CubieCube::solved();
+        cube.apply_all(&parse_moves("U R2 F2 D' L2 B2 U2").unwrap());
+        assert_eq!((cube.twist(), cube.flip(), cube.slice()), (0, 0, 0));
+        assert_ne!(cube.corner_perm(), 0);
+    }
+}
@@ -0,0 +1,517 @@
+//! num_searchで使わない上級のテクニック
+//! 隠れたn組，フィッシュ(X-Wing, Swordfish, Jellyfish)，XY-Wing, XYZ-Wing, シンプルカラーリング
+
+use std::collections::{BTreeSet, VecDeque};
+
+use super::technique::Technique;
+use super::{Data, FieldElement, SudokuSolver};
+
+/// 0..nからk個選ぶ組合せを全て返す
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    fn rec(start: usize, n: usize, k: usize, cur: &mut Vec<usize>, ret: &mut Vec<Vec<usize>>) {
+        if cur.len() == k {
+            ret.push(cur.clone());
+            return;
+        }
+        for i in start..n {
+            cur.push(i);
+            rec(i + 1, n, k, cur, ret);
+            cur.pop();
+        }
+    }
+    let mut ret = Vec::new();
+    rec(0, n, k, &mut Vec::new(), &mut ret);
+    ret
+}
+
+impl SudokuSolver {
+    /// マスの候補，確定済みならNone
+    fn candidates(&self, x: usize, y: usize) -> Option<BTreeSet<Data>> {
+        match &*self.field[x][y].lock().unwrap() {
+            FieldElement::SUG(set) => Some(set.clone()),
+            FieldElement::NUM(_) => None,
+        }
+    }
+    /// 候補を一つ消す，消せたらtrueを返す
+    fn remove_candidate(&self, x: usize, y: usize, num: Data) -> bool {
+        match &mut *self.field[x][y].lock().unwrap() {
+            FieldElement::SUG(set) => set.remove(&num),
+            FieldElement::NUM(_) => false,
+        }
+    }
+    /// 2つのマスに同じ数字が入れられないかどうか(同じ行，列，ブロックか追加ルールで関係する)
+    fn sees(&self, a: (usize, usize), b: (usize, usize)) -> bool {
+        a != b
+            && (a.0 == b.0
+                || a.1 == b.1
+                || self.xy_to_block_ind(a.0, a.1) == self.xy_to_block_ind(b.0, b.1)
+                || self.constraint_peers(a.0, a.1).contains(&b))
+    }
+    /// 候補がまだ残っているマスとその候補
+    fn open_cells(&self) -> Vec<((usize, usize), BTreeSet<Data>)> {
+        let mut cells = Vec::new();
+        for x in 0..self.width() {
+            for y in 0..self.width() {
+                if let Some(set) = self.candidates(x, y) {
+                    cells.push(((x, y), set));
+                }
+            }
+        }
+        cells
+    }
+
+    /// 隠れたn組(n = 2, 3, 4)
+    /// 行，列，ブロックの中でn種類の数字がn個のマスにしか入らなければ，そのマスの他の候補を消す
+    pub(super) fn hidden_subset(&self) -> bool {
+        let mut flag = false;
+        for house in self.houses() {
+            // 確定していない数字ごとの入り得るマス
+            let mut positions: Vec<(Data, BTreeSet<usize>)> = Vec::new();
+            for num in 1..=self.width() {
+                let mut placed = false;
+                let mut pos = BTreeSet::new();
+                for (i, &(x, y)) in house.iter().enumerate() {
+                    match &*self.field[x][y].lock().unwrap() {
+                        FieldElement::NUM(n) if *n == num => placed = true,
+                        FieldElement::SUG(set) if set.contains(&num) => {
+                            pos.insert(i);
+                        }
+                        _ => (),
+                    }
+                }
+                if !placed && !pos.is_empty() {
+                    positions.push((num, pos));
+                }
+            }
+            for k in 2..=4 {
+                if positions.len() <= k {
+                    break;
+                }
+                for comb in combinations(positions.len(), k) {
+                    let cells = comb
+                        .iter()
+                        .fold(BTreeSet::new(), |acc, &i| &acc | &positions[i].1);
+                    if cells.len() != k {
+                        continue;
+                    }
+                    let digits: BTreeSet<Data> = comb.iter().map(|&i| positions[i].0).collect();
+                    let mut eliminations = Vec::new();
+                    for &i in &cells {
+                        let (x, y) = house[i];
+                        for num in self.candidates(x, y).unwrap_or_default() {
+                            if !digits.contains(&num) && self.remove_candidate(x, y, num) {
+                                eliminations.push((x, y, num));
+                            }
+                        }
+                    }
+                    flag |= !eliminations.is_empty();
+                    self.record(
+                        Technique::HiddenSubset,
+                        cells.iter().map(|&i| house[i]).collect(),
+                        digits.into_iter().collect(),
+                        eliminations,
+                    );
+                }
+            }
+        }
+        flag
+    }
+
+    /// フィッシュ(n = 2ならX-Wing, 3ならSwordfish, 4ならJellyfish)
+    /// n本の行で数字の入るマスが合わせてn本の列にしかなければ，その列の他の行から消す(行と列を入れ替えても同じ)
+    pub(super) fn fish(&self, n: usize) -> bool {
+        let technique = match n {
+            2 => Technique::XWing,
+            3 => Technique::Swordfish,
+            _ => Technique::Jellyfish,
+        };
+        let width = self.width();
+        let mut flag = false;
+        for num in 1..=width {
+            for by_row in [true, false] {
+                // (line, i)のマス
+                let cell = |line: usize, i: usize| if by_row { (line, i) } else { (i, line) };
+                // 数字の入り得る位置が2からn個の行(列)
+                let mut lines: Vec<(usize, BTreeSet<usize>)> = Vec::new();
+                'LINE: for line in 0..width {
+                    let mut pos = BTreeSet::new();
+                    for i in 0..width {
+                        let (x, y) = cell(line, i);
+                        match &*self.field[x][y].lock().unwrap() {
+                            FieldElement::NUM(m) if *m == num => continue 'LINE,
+                            FieldElement::SUG(set) if set.contains(&num) => {
+                                pos.insert(i);
+                            }
+                            _ => (),
+                        }
+                    }
+                    if 2 <= pos.len() && pos.len() <= n {
+                        lines.push((line, pos));
+                    }
+                }
+                for comb in combinations(lines.len(), n) {
+                    let cover = comb
+                        .iter()
+                        .fold(BTreeSet::new(), |acc, &i| &acc | &lines[i].1);
+                    if cover.len() != n {
+                        continue;
+                    }
+                    let base: Vec<usize> = comb.iter().map(|&i| lines[i].0).collect();
+                    let mut eliminations = Vec::new();
+                    for &i in &cover {
+                        for line in (0..width).filter(|line| !base.contains(line)) {
+                            let (x, y) = cell(line, i);
+                            if self.remove_candidate(x, y, num) {
+                                eliminations.push((x, y, num));
+                            }
+                        }
+                    }
+                    flag |= !eliminations.is_empty();
+                    let cells = comb
+                        .iter()
+                        .flat_map(|&i| {
+                            let (line, pos) = &lines[i];
+                            let line = *line;
+                            pos.iter().map(move |&p| cell(line, p))
+                        })
+                        .collect();
+                    self.record(technique, cells, vec![num], eliminations);
+                }
+            }
+        }
+        flag
+    }
+
+    /// XY-Wing
+    /// 候補が{a, b}のマス(pivot)から見える{a, c}と{b, c}のマスがあれば，その2つから見えるマスのcを消す
+    pub(super) fn xy_wing(&self) -> bool {
+        let cells = self.open_cells();
+        let bivalue: Vec<_> = cells.iter().filter(|(_, set)| set.len() == 2).collect();
+        let mut flag = false;
+        for (pivot, pivot_set) in &bivalue {
+            let wings: Vec<_> = bivalue
+                .iter()
+                .filter(|(cell, set)| self.sees(*pivot, *cell) && (set & pivot_set).len() == 1)
+                .collect();
+            for (i, (wing1, set1)) in wings.iter().enumerate() {
+                for (wing2, set2) in &wings[i + 1..] {
+                    // 2つの羽はpivotの別の数字を持ち，残りの数字cが共通
+                    let common: Vec<Data> = (set1 & set2).into_iter().collect();
+                    if common.len() != 1 || pivot_set.contains(&common[0]) {
+                        continue;
+                    }
+                    let c = common[0];
+                    let eliminations = self.eliminate_seen(c, &[*wing1, *wing2]);
+                    flag |= !eliminations.is_empty();
+                    self.record(
+                        Technique::XYWing,
+                        vec![*pivot, *wing1, *wing2],
+                        vec![c],
+                        eliminations,
+                    );
+                }
+            }
+        }
+        flag
+    }
+
+    /// XYZ-Wing
+    /// 候補が{a, b, c}のマス(pivot)から見える{a, c}と{b, c}のマスがあれば，3つ全てから見えるマスのcを消す
+    pub(super) fn xyz_wing(&self) -> bool {
+        let cells = self.open_cells();
+        let mut flag = false;
+        for (pivot, pivot_set) in cells.iter().filter(|(_, set)| set.len() == 3) {
+            let wings: Vec<_> = cells
+                .iter()
+                .filter(|(cell, set)| {
+                    set.len() == 2 && set.is_subset(pivot_set) && self.sees(*pivot, *cell)
+                })
+                .collect();
+            for (i, (wing1, set1)) in wings.iter().enumerate() {
+                for (wing2, set2) in &wings[i + 1..] {
+                    let common: Vec<Data> = (set1 & set2).into_iter().collect();
+                    if common.len() != 1 {
+                        continue;
+                    }
+                    let c = common[0];
+                    let eliminations = self.eliminate_seen(c, &[*pivot, *wing1, *wing2]);
+                    flag |= !eliminations.is_empty();
+                    self.record(
+                        Technique::XYZWing,
+                        vec![*pivot, *wing1, *wing2],
+                        vec![c],
+                        eliminations,
+                    );
+                }
+            }
+        }
+        flag
+    }
+
+    /// cellsの全てから見えるマスの候補numを消す
+    fn eliminate_seen(&self, num: Data, cells: &[(usize, usize)]) -> Vec<(usize, usize, Data)> {
+        let mut eliminations = Vec::new();
+        for x in 0..self.width() {
+            for y in 0..self.width() {
+                if cells.iter().all(|&cell| self.sees(cell, (x, y)))
+                    && self.remove_candidate(x, y, num)
+                {
+                    eliminations.push((x, y, num));
+                }
+            }
+        }
+        eliminations
+    }
+
+    /// シンプルカラーリング
+    /// 数字の入るマスが2つしかない領域で結んだマスを交互に2色で塗ると，どちらかの色が全て正解になる
+    /// 同じ色どうしが見えていればその色は誤りで，両方の色から見えるマスには入らない
+    pub(super) fn simple_coloring(&self) -> bool {
+        let houses = self.houses();
+        let mut flag = false;
+        for num in 1..=self.width() {
+            let cells: Vec<(usize, usize)> = self
+                .open_cells()
+                .into_iter()
+                .filter(|(_, set)| set.contains(&num))
+                .map(|(cell, _)| cell)
+                .collect();
+            // 強いリンク
+            let mut links: Vec<Vec<usize>> = vec![Vec::new(); cells.len()];
+            for house in &houses {
+                if house
+                    .iter()
+                    .any(|&(x, y)| *self.field[x][y].lock().unwrap() == FieldElement::NUM(num))
+                {
+                    continue;
+                }
+                let ends: Vec<usize> = house
+                    .iter()
+                    .filter_map(|cell| cells.iter().position(|c| c == cell))
+                    .collect();
+                if ends.len() == 2 {
+                    links[ends[0]].push(ends[1]);
+                    links[ends[1]].push(ends[0]);
+                }
+            }
+            let mut color: Vec<Option<usize>> = vec![None; cells.len()];
+            for start in 0..cells.len() {
+                if color[start].is_some() || links[start].is_empty() {
+                    continue;
+                }
+                // 連結成分を2色で塗る
+                let mut component = vec![start];
+                color[start] = Some(0);
+                let mut queue = VecDeque::from([start]);
+                while let Some(i) = queue.pop_front() {
+                    for &j in &links[i] {
+                        if color[j].is_none() {
+                            color[j] = Some(1 - color[i].unwrap());
+                            component.push(j);
+                            queue.push_back(j);
+                        }
+                    }
+                }
+                let group = |c: usize| -> Vec<(usize, usize)> {
+                    component
+                        .iter()
+                        .filter(|&&i| color[i] == Some(c))
+                        .map(|&i| cells[i])
+                        .collect()
+                };
+                let groups = [group(0), group(1)];
+                let mut eliminations = Vec::new();
+                for (c, same) in groups.iter().enumerate() {
+                    // 同じ色どうしが見えていればその色は全て誤り
+                    let wrap = same
+                        .iter()
+                        .enumerate()
+                        .any(|(i, &a)| same[i + 1..].iter().any(|&b| self.sees(a, b)));
+                    if wrap {
+                        for &(x, y) in &groups[c] {
+                            if self.remove_candidate(x, y, num) {
+                                eliminations.push((x, y, num));
+                            }
+                        }
+                    }
+                }
+                if eliminations.is_empty() {
+                    // 両方の色から見える色のないマス
+                    for (i, &(x, y)) in cells.iter().enumerate() {
+                        if component.contains(&i) {
+                            continue;
+                        }
+                        let seen = |c: usize| groups[c].iter().any(|&cell| self.sees(cell, (x, y)));
+                        if seen(0) && seen(1) && self.remove_candidate(x, y, num) {
+                            eliminations.push((x, y, num));
+                        }
+                    }
+                }
+                flag |= !eliminations.is_empty();
+                let members = component.iter().map(|&i| cells[i]).collect();
+                self.record(Technique::SimpleColoring, members, vec![num], eliminations);
+            }
+        }
+        flag
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::technique::Technique;
+    use super::super::{FieldElement, SudokuSolver};
+    use std::collections::BTreeSet;
+
+    /// 候補を直接書き換える
+    fn set(sudoku: &SudokuSolver, x: usize, y: usize, nums: &[usize]) {
+        *sudoku.field[x][y].lock().unwrap() = FieldElement::SUG(nums.iter().copied().collect());
+    }
+    fn has(sudoku: &SudokuSolver, x: usize, y: usize, num: usize) -> bool {
+        match &*sudoku.field[x][y].lock().unwrap() {
+            FieldElement::SUG(set) => set.contains(&num),
+            _ => false,
+        }
+    }
+    fn remove(sudoku: &SudokuSolver, x: usize, y: usize, num: usize) {
+        if let FieldElement::SUG(set) = &mut *sudoku.field[x][y].lock().unwrap() {
+            set.remove(&num);
+        }
+    }
+
+    #[test]
+    fn test_fish() {
+        // 行0と行4で1が入るのは列2と列6だけ
+        let sudoku = SudokuSolver::empty(3);
+        for y in 0..9 {
+            if y != 2 && y != 6 {
+                remove(&sudoku, 0, y, 1);
+                remove(&sudoku, 4, y, 1);
+            }
+        }
+        assert!(sudoku.fish(2));
+        assert!(!has(&sudoku, 1, 2, 1));
+        assert!(!has(&sudoku, 8, 6, 1));
+        assert!(has(&sudoku, 0, 2, 1));
+        assert!(has(&sudoku, 4, 6, 1));
+        assert!(has(&sudoku, 1, 3, 1));
+        let step = sudoku.steps().pop().unwrap();
+        assert_eq!(step.technique, Technique::XWing);
+        assert_eq!(step.eliminations.len(), 14);
+        assert!(!sudoku.fish(2));
+
+        // 列1, 4, 7で5が入るのは行0, 3, 6だけ(Swordfish)
+        let sudoku = SudokuSolver::empty(3);
+        for x in 0..9 {
+            if x % 3 != 0 {
+                for y in [1, 4, 7] {
+                    remove(&sudoku, x, y, 5);
+                }
+            }
+        }
+        remove(&sudoku, 0, 1, 5);
+        remove(&sudoku, 3, 4, 5);
+        remove(&sudoku, 6, 7, 5);
+        assert!(!sudoku.fish(2));
+        assert!(sudoku.fish(3));
+        assert!(!has(&sudoku, 0, 0, 5));
+        assert!(!has(&sudoku, 6, 8, 5));
+        assert!(has(&sudoku, 1, 0, 5));
+    }
+
+    #[test]
+    fn test_wings() {
+        // pivot (0,0) {1,2}, 羽 (0,4) {1,3}, (4,0) {2,3} -> (4,4)から3を消す
+        let sudoku = SudokuSolver::empty(3);
+        set(&sudoku, 0, 0, &[1, 2]);
+        set(&sudoku, 0, 4, &[1, 3]);
+        set(&sudoku, 4, 0, &[2, 3]);
+        assert!(sudoku.xy_wing());
+        assert!(!has(&sudoku, 4, 4, 3));
+        assert!(has(&sudoku, 4, 5, 3));
+        assert!(has(&sudoku, 0, 4, 3));
+        assert_eq!(sudoku.steps()[0].technique, Technique::XYWing);
+
+        // pivot (0,0) {1,2,3}, 羽 (0,1) {1,3}, (1,0) {2,3} -> ブロック0の他のマスから3を消す
+        let sudoku = SudokuSolver::empty(3);
+        set(&sudoku, 0, 0, &[1, 2, 3]);
+        set(&sudoku, 0, 1, &[1, 3]);
+        set(&sudoku, 1, 0, &[2, 3]);
+        assert!(!sudoku.xy_wing());
+        assert!(sudoku.xyz_wing());
+        assert!(!has(&sudoku, 1, 1, 3));
+        assert!(!has(&sudoku, 2, 2, 3));
+        assert!(has(&sudoku, 0, 5, 3));
+        assert!(has(&sudoku, 0, 0, 3));
+    }
+
+    #[test]
+    fn test_hidden_subset() {
+        // 行0で1と2が入るのは(0,3),(0,7)だけ
+        let sudoku = SudokuSolver::empty(3);
+        for y in 0..9 {
+            if y != 3 && y != 7 {
+                remove(&sudoku, 0, y, 1);
+                remove(&sudoku, 0, y, 2);
+            }
+        }
+        assert!(sudoku.hidden_subset());
+        match &*sudoku.field[0][3].lock().unwrap() {
+            FieldElement::SUG(set) => assert_eq!(*set, BTreeSet::from([1, 2])),
+            _ => unreachable!(),
+        }
+        assert!(has(&sudoku, 1, 3, 5));
+        let step = sudoku.steps().pop().unwrap();
+        assert_eq!(step.technique, Technique::HiddenSubset);
+        assert_eq!(step.cells, vec![(0, 3), (0, 7)]);
+        assert_eq!(step.digits, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_simple_coloring() {
+        // 7の強いリンク (0,0)-(0,4) 行0, (0,4)-(4,4) 列4, (4,4)-(4,0) 行4
+        // (0,0)と(4,4)が同じ色，(0,4)と(4,0)が同じ色
+        // 列0の(0,0)と(4,0)は違う色なので，列0の他のマスは両方の色から見えて7が入らない
+        let sudoku = SudokuSolver::empty(3);
+        for y in 0..9 {
+            if y != 0 && y != 4 {
+                remove(&sudoku, 0, y, 7);
+                remove(&sudoku, 4, y, 7);
+            }
+        }
+        for x in 0..9 {
+            if x != 0 && x != 4 {
+                remove(&sudoku, x, 4, 7);
+            }
+        }
+        assert!(sudoku.simple_coloring());
+        assert!(!has(&sudoku, 8, 0, 7));
+        assert!(has(&sudoku, 0, 0, 7));
+        assert!(has(&sudoku, 8, 8, 7));
+
+        // 7の強いリンク (0,0)-(0,8) 行0, (0,8)-(5,8) 列8, (5,8)-(5,1) 行5, (5,1)-(1,1) 列1
+        // (0,0)と(5,8)と(1,1)が同じ色で，(0,0)と(1,1)は同じブロックなのでこの色は誤り
+        let sudoku = SudokuSolver::empty(3);
+        for y in 0..9 {
+            if y != 0 && y != 8 {
+                remove(&sudoku, 0, y, 7);
+            }
+            if y != 8 && y != 1 {
+                remove(&sudoku, 5, y, 7);
+            }
+        }
+        for x in 0..9 {
+            if x != 0 && x != 5 {
+                remove(&sudoku, x, 8, 7);
+            }
+            if x != 5 && x != 1 {
+                remove(&sudoku, x, 1, 7);
+            }
+        }
+        assert!(sudoku.simple_coloring());
+        assert!(!has(&sudoku, 0, 0, 7));
+        assert!(!has(&sudoku, 1, 1, 7));
+        assert!(!has(&sudoku, 5, 8, 7));
+        assert!(has(&sudoku, 0, 8, 7));
+        assert!(has(&sudoku, 5, 1, 7));
+    }
+}
@@ -0,0 +1,195 @@
+//! ファイルにまとめられた9x9の数独をrayonで並列に解く
+//! 一行に81文字で一問，空きマスは'.'か'0'で書く
+//! 出力も一行に一問で，入力と同じ順番に解(解けなければ入力のまま)と結果を書く
+
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+
+use super::super::{PuzzleError, PuzzleResult};
+use super::bitboard::BitboardSolver;
+
+/// 一度に読み込んで並列に解く問題の数
+const CHUNK: usize = 4096;
+
+/// 一問ごとの結果
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum Status {
+    /// 唯一解が見つかった
+    Solved,
+    /// 解がない
+    Unsolvable,
+    /// 解が複数ある(出力は見つかった最初の解)
+    MultipleSolutions,
+    /// 81文字でないか，使えない文字がある
+    InvalidInput,
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Status::Solved => "solved",
+            Status::Unsolvable => "unsolvable",
+            Status::MultipleSolutions => "multiple",
+            Status::InvalidInput => "invalid",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+/// 全体の結果
+#[derive(Clone, Debug)]
+pub struct BatchReport {
+    /// 入力の順番での一問ごとの結果
+    pub statuses: Vec<Status>,
+    /// 読み込みから書き出しまでにかかった時間
+    pub elapsed: Duration,
+}
+
+impl BatchReport {
+    /// 結果がstatusだった問題の数
+    pub fn count(&self, status: Status) -> usize {
+        self.statuses.iter().filter(|&&s| s == status).count()
+    }
+    /// 一秒あたりに処理した問題の数
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs == 0.0 {
+            return 0.0;
+        }
+        self.statuses.len() as f64 / secs
+    }
+}
+
+impl Display for BatchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} puzzles: {} solved, {} unsolvable, {} multiple, {} invalid in {:.3}s ({:.1} puzzles/s)",
+            self.statuses.len(),
+            self.count(Status::Solved),
+            self.count(Status::Unsolvable),
+            self.count(Status::MultipleSolutions),
+            self.count(Status::InvalidInput),
+            self.elapsed.as_secs_f64(),
+            self.throughput()
+        )
+    }
+}
+
+/// 一行の問題を解いて，結果と書き出す81文字を返す
+pub fn solve_line(line: &str) -> (Status, String) {
+    if line.len() != 81 || !line.bytes().all(|b| b == b'.' || b.is_ascii_digit()) {
+        return (Status::InvalidInput, line.to_string());
+    }
+    let rows: Vec<&str> = (0..9).map(|i| &line[i * 9..(i + 1) * 9]).collect();
+    let sudoku = BitboardSolver::new(rows);
+    let mut solutions = Vec::new();
+    let mut count = 0;
+    sudoku.solutions_dfs(2, &mut count, &mut |solver| solutions.push(*solver));
+    let status = match count {
+        0 => return (Status::Unsolvable, line.to_string()),
+        1 => Status::Solved,
+        _ => Status::MultipleSolutions,
+    };
+    let solution = &solutions[0];
+    let str = (0..81)
+        .map(|i| solution.symbol(solution.get(i / 9, i % 9).unwrap()))
+        .collect();
+    (status, str)
+}
+
+/// readerから一行ずつ問題を読み，CHUNK問ずつ並列に解いてwriterに同じ順番で書く
+/// 出力の各行は「解(解けなければ入力) 結果」で，入力の行と一対一に対応する
+/// 行は前後の空白も含めてそのまま読むので，空行や空白だけの行はInvalidInputになる
+pub fn solve_lines(reader: impl BufRead, mut writer: impl Write) -> PuzzleResult<BatchReport> {
+    let start = Instant::now();
+    let mut statuses = Vec::new();
+    let mut lines = reader.lines();
+    loop {
+        let mut chunk = Vec::with_capacity(CHUNK);
+        for line in lines.by_ref() {
+            chunk.push(line.map_err(|e| PuzzleError::new(e.to_string()))?);
+            if chunk.len() == CHUNK {
+                break;
+            }
+        }
+        if chunk.is_empty() {
+            break;
+        }
+        let results: Vec<(Status, String)> =
+            chunk.par_iter().map(|line| solve_line(line)).collect();
+        for (status, str) in results {
+            writeln!(writer, "{} {}", str, status).map_err(|e| PuzzleError::new(e.to_string()))?;
+            statuses.push(status);
+        }
+    }
+    writer
+        .flush()
+        .map_err(|e| PuzzleError::new(e.to_string()))?;
+    Ok(BatchReport {
+        statuses,
+        elapsed: start.elapsed(),
+    })
+}
+
+/// inputのファイルの問題を解いてoutputのファイルに書く
+pub fn solve_file(input: impl AsRef<Path>, output: impl AsRef<Path>) -> PuzzleResult<BatchReport> {
+    let reader = File::open(input).map_err(|e| PuzzleError::new(e.to_string()))?;
+    let writer = File::create(output).map_err(|e| PuzzleError::new(e.to_string()))?;
+    solve_lines(BufReader::new(reader), BufWriter::new(writer))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{solve_lines, Status};
+
+    #[test]
+    fn test_solve_lines() {
+        let input = "\
+902304501000208000758109423604005792000407000217900845106703904000501000509602317
+800000000003600000070090200050007000000045700000100030001000068008500010090000400
+
+\x20\x20\x20
+..3456789456789123789123456..4365897365897214897214365531642978642978531978531642
+11...............................................................................
+12345
+1234567890abcdefghijklmnopqrstuvwxyz123456789012345678901234567890123456789012345
+";
+        let mut output = Vec::new();
+        let report = solve_lines(input.as_bytes(), &mut output).unwrap();
+        assert_eq!(
+            report.statuses,
+            vec![
+                Status::Solved,
+                Status::Solved,
+                Status::InvalidInput,
+                Status::InvalidInput,
+                Status::MultipleSolutions,
+                Status::Unsolvable,
+                Status::InvalidInput,
+                Status::InvalidInput,
+            ]
+        );
+        assert_eq!(report.count(Status::InvalidInput), 4);
+        assert!(report.throughput() >= 0.0);
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        // 入力の行と一対一に対応する
+        assert_eq!(lines.len(), 8);
+        assert_eq!(
+            lines[0],
+            "962374581341258679758169423634815792895427136217936845126783954473591268589642317 solved"
+        );
+        assert!(lines[1].starts_with("812753649"));
+        assert_eq!(lines[2], " invalid");
+        assert_eq!(lines[3], "    invalid");
+        assert!(lines[5].ends_with("unsolvable"));
+        assert_eq!(lines[6], "12345 invalid");
+        assert!(!format!("{}", report).is_empty());
+    }
+}
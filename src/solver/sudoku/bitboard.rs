@@ -0,0 +1,578 @@
+//! ビットボードによる高速な数独ソルバー
+//! マスごとの候補と行，列，ブロックごとの確定済みの数字をビットマスクで持ち，盤面全体をコピーして分岐する
+//! 正方形のブロックだけの数独(16x16まで)に対応し，追加ルールやキラー数独には対応しない
+
+use std::fmt::Display;
+
+use super::super::{PuzzleError, PuzzleResult, Solver};
+use super::{symbols, Data, FieldElement, SudokuSolver};
+
+/// 盤面の一辺の最大の長さ
+const MAX_WIDTH: usize = 16;
+const MAX_CELLS: usize = MAX_WIDTH * MAX_WIDTH;
+
+/// 数字dを(d - 1)ビット目で表す
+type Mask = u16;
+
+/// n^2 x n^2の数独(n <= 4)
+/// 状態はCopyなので，仮置きの時は盤面ごとコピーする
+///
+/// 解くことと解を数えることに絞っているので，SudokuSolverの次のものは持たない
+/// - 推論の記録と難易度(steps, difficulty, hint, logical_solve, set_technique)
+/// - 追加ルール，キラー数独，ジグソー数独(add_constraint, add_cage, jigsaw)，from_solverはこれらをPuzzleErrorにする
+/// - 候補(鉛筆書き)とその読み書き(from_json, pencil_marks)
+///
+/// これらが必要な時はto_solverでSudokuSolverに変換する
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct BitboardSolver {
+    /// ブロックの一辺の長さ(n)
+    size: usize,
+    /// 確定した数字，空きマスは0
+    values: [u8; MAX_CELLS],
+    /// 空きマスの候補，確定したマスは0
+    cands: [Mask; MAX_CELLS],
+    /// 行，列，ブロックごとの確定済みの数字
+    rows: [Mask; MAX_WIDTH],
+    cols: [Mask; MAX_WIDTH],
+    blocks: [Mask; MAX_WIDTH],
+    /// 空きマスの数
+    vacant: usize,
+    /// 同じ数字が重なる問題を読み込んだ
+    broken: bool,
+}
+
+impl BitboardSolver {
+    /// 一行ずつの文字列から作る(SudokuSolver::newと同じ形式)
+    /// 読めない文字列ではpanicするので，入力が正しいかわからなければparseを使う
+    /// 同じ数字が重なっていてもpanicせず，解のない盤面として扱う
+    pub fn new(string: Vec<&str>) -> Self {
+        Self::read(string).expect("invalid data")
+    }
+    /// newと同じ形式の文字列から作る
+    /// 行の長さが違う，使えない文字がある，同じ数字が重なっていればPuzzleErrorを返す
+    pub fn parse(string: Vec<&str>) -> PuzzleResult<Self> {
+        let solver = Self::read(string)?;
+        if solver.broken {
+            return Err(PuzzleError::new(
+                "bitboard: the same number appears twice in a row, column or block",
+            ));
+        }
+        Ok(solver)
+    }
+    /// 文字列を読む，同じ数字の重なりはbrokenにする
+    fn read(string: Vec<&str>) -> PuzzleResult<Self> {
+        let len = string.len();
+        let size = (1..=len).find(|x| x * x >= len).unwrap_or(0);
+        if size * size != len || len == 0 || len > MAX_WIDTH {
+            return Err(PuzzleError::new(format!(
+                "bitboard: wrong length: the number of rows must be a square up to {}, but is {}",
+                MAX_WIDTH, len
+            )));
+        }
+        let symbols = symbols(len).unwrap();
+        let mut solver = BitboardSolver {
+            size,
+            values: [0; MAX_CELLS],
+            cands: [0; MAX_CELLS],
+            rows: [0; MAX_WIDTH],
+            cols: [0; MAX_WIDTH],
+            blocks: [0; MAX_WIDTH],
+            vacant: len * len,
+            broken: false,
+        };
+        let full = solver.full();
+        for i in 0..len * len {
+            solver.cands[i] = full;
+        }
+        for (x, row) in string.iter().enumerate() {
+            if row.chars().count() != len {
+                return Err(PuzzleError::new(format!(
+                    "bitboard: row {}: wrong length, expected {} but found {}",
+                    x,
+                    len,
+                    row.chars().count()
+                )));
+            }
+            for (y, c) in row.chars().enumerate() {
+                let c = c.to_ascii_uppercase();
+                match symbols.iter().position(|&s| s == c) {
+                    Some(pos) => {
+                        if !solver.place(x * len + y, pos + 1) {
+                            solver.broken = true;
+                        }
+                    }
+                    None if c == '*' || c == '.' || c == '0' => (),
+                    None => {
+                        return Err(PuzzleError::new(format!(
+                            "bitboard: row {}, column {}: bad character '{}'",
+                            x, y, c
+                        )))
+                    }
+                }
+            }
+        }
+        Ok(solver)
+    }
+    /// SudokuSolverの確定した数字から作る(候補は引き継がない)
+    /// 追加ルールやケージがある時，ブロックが正方形でない時，16x16より大きい時はPuzzleErrorを返す
+    pub fn from_solver(solver: &SudokuSolver) -> PuzzleResult<Self> {
+        let (size, width) = (solver.size(), solver.width());
+        if !solver.constraints().is_empty() {
+            return Err(PuzzleError::new("bitboard: constraints are not supported"));
+        }
+        if !solver.cages().is_empty() {
+            return Err(PuzzleError::new("bitboard: killer cages are not supported"));
+        }
+        if width > MAX_WIDTH {
+            return Err(PuzzleError::new(format!(
+                "bitboard: width {} is larger than {}",
+                width, MAX_WIDTH
+            )));
+        }
+        let square = (0..width).all(|x| {
+            (0..width).all(|y| solver.xy_to_block_ind(x, y) == (x / size) * size + y / size)
+        });
+        if !square {
+            return Err(PuzzleError::new(
+                "bitboard: irregular regions are not supported",
+            ));
+        }
+        let rows: Vec<String> = (0..width)
+            .map(|x| {
+                (0..width)
+                    .map(|y| match &*solver.field[x][y].lock().unwrap() {
+                        FieldElement::NUM(num) => solver.symbol(*num),
+                        FieldElement::SUG(_) => '.',
+                    })
+                    .collect()
+            })
+            .collect();
+        Self::read(rows.iter().map(|row| row.as_str()).collect())
+    }
+    /// 同じ盤面のSudokuSolverに変換する
+    pub fn to_solver(&self) -> SudokuSolver {
+        let width = self.width();
+        let rows: Vec<String> = (0..width)
+            .map(|x| {
+                (0..width)
+                    .map(|y| match self.values[x * width + y] {
+                        0 => '.',
+                        val => self.symbol(val as Data),
+                    })
+                    .collect()
+            })
+            .collect();
+        SudokuSolver::new(rows.iter().map(|row| row.as_str()).collect())
+    }
+
+    /// ブロックの一辺の長さ(9x9なら3)
+    pub fn size(&self) -> usize {
+        self.size
+    }
+    /// 盤面の一辺の長さ，数字の種類の数でもある(9x9なら9)
+    pub fn width(&self) -> usize {
+        self.size * self.size
+    }
+    /// 数字valを表す記号
+    pub fn symbol(&self, val: Data) -> char {
+        symbols(self.width()).unwrap()[val - 1]
+    }
+    /// (x, y)の確定した数字
+    pub fn get(&self, x: usize, y: usize) -> Option<Data> {
+        match self.values[x * self.width() + y] {
+            0 => None,
+            val => Some(val as Data),
+        }
+    }
+
+    /// 解の数をlimit個まで数える
+    /// 0なら解なし，1なら唯一解，limitならlimit個以上の解がある
+    pub fn count_solutions(&self, limit: usize) -> usize {
+        let mut count = 0;
+        if limit > 0 {
+            self.solutions_dfs(limit, &mut count, &mut |_| {});
+        }
+        count
+    }
+    /// 唯一解を持つかどうか
+    pub fn has_unique_solution(&self) -> bool {
+        self.count_solutions(2) == 1
+    }
+    /// 全ての解を列挙する
+    pub fn all_solutions(&self) -> Vec<BitboardSolver> {
+        let mut solutions = Vec::new();
+        let mut count = 0;
+        self.solutions_dfs(usize::MAX, &mut count, &mut |solver| {
+            solutions.push(*solver)
+        });
+        solutions
+    }
+
+    fn full(&self) -> Mask {
+        ((1u32 << self.width()) - 1) as Mask
+    }
+    #[inline]
+    fn block_of(&self, x: usize, y: usize) -> usize {
+        (x / self.size) * self.size + y / self.size
+    }
+    /// i番目のマスに数字を置き，同じ行，列，ブロックの候補から消す
+    /// 既に同じ数字が入っていて置けなければfalseを返す
+    fn place(&mut self, i: usize, val: Data) -> bool {
+        let width = self.width();
+        let (x, y) = (i / width, i % width);
+        let block = self.block_of(x, y);
+        let bit = 1 << (val - 1);
+        if self.values[i] != 0 || (self.rows[x] | self.cols[y] | self.blocks[block]) & bit != 0 {
+            return false;
+        }
+        self.values[i] = val as u8;
+        self.cands[i] = 0;
+        self.rows[x] |= bit;
+        self.cols[y] |= bit;
+        self.blocks[block] |= bit;
+        self.vacant -= 1;
+        let (bx, by) = ((x / self.size) * self.size, (y / self.size) * self.size);
+        for k in 0..width {
+            self.cands[x * width + k] &= !bit;
+            self.cands[k * width + y] &= !bit;
+            self.cands[(bx + k / self.size) * width + by + k % self.size] &= !bit;
+        }
+        true
+    }
+    /// 候補が一つのマスと，行，列，ブロックで一か所にしか入らない数字を確定する
+    /// 進めばSome(true)，矛盾があればNoneを返す
+    fn singles(&mut self) -> Option<bool> {
+        let (size, width) = (self.size, self.width());
+        let mut progress = false;
+        for i in 0..width * width {
+            if self.values[i] != 0 {
+                continue;
+            }
+            let cand = self.cands[i];
+            if cand == 0 {
+                return None;
+            }
+            if cand.count_ones() == 1 {
+                if !self.place(i, cand.trailing_zeros() as Data + 1) {
+                    return None;
+                }
+                progress = true;
+            }
+        }
+        for unit in 0..3 * width {
+            let cell = |k: usize| match unit / width {
+                0 => (unit % width) * width + k,
+                1 => k * width + unit % width,
+                _ => {
+                    let (bx, by) = ((unit % width / size) * size, (unit % width % size) * size);
+                    (bx + k / size) * width + by + k % size
+                }
+            };
+            // 一か所以上に入る数字と二か所以上に入る数字
+            let (mut once, mut twice, mut placed) = (0, 0, 0);
+            for k in 0..width {
+                let i = cell(k);
+                twice |= once & self.cands[i];
+                once |= self.cands[i];
+                if self.values[i] != 0 {
+                    placed |= 1 << (self.values[i] - 1);
+                }
+            }
+            if once | placed != self.full() {
+                return None;
+            }
+            let mut hidden = once & !twice & !placed;
+            while hidden != 0 {
+                let bit = hidden & hidden.wrapping_neg();
+                hidden &= !bit;
+                let i = (0..width).map(cell).find(|&i| self.cands[i] & bit != 0)?;
+                if !self.place(i, bit.trailing_zeros() as Data + 1) {
+                    return None;
+                }
+                progress = true;
+            }
+        }
+        Some(progress)
+    }
+    /// 進まなくなるまでsinglesを繰り返す，矛盾があればfalseを返す
+    fn propagate(&mut self) -> bool {
+        loop {
+            match self.singles() {
+                Some(true) => (),
+                Some(false) => return true,
+                None => return false,
+            }
+        }
+    }
+    /// 候補が最も少ない空きマス
+    fn pickup_cell(&self) -> Option<usize> {
+        (0..self.width() * self.width())
+            .filter(|&i| self.values[i] == 0)
+            .min_by_key(|&i| self.cands[i].count_ones())
+    }
+    /// 埋められるだけ埋めてから，候補が最も少ないマスで盤面をコピーして分岐する
+    /// 解が見つかるたびにfoundを呼び，countがlimitに達したら打ち切る
+    pub(super) fn solutions_dfs(
+        &self,
+        limit: usize,
+        count: &mut usize,
+        found: &mut impl FnMut(&BitboardSolver),
+    ) {
+        let mut solver = *self;
+        if solver.broken || !solver.propagate() {
+            return;
+        }
+        let i = match solver.pickup_cell() {
+            Some(i) => i,
+            None => {
+                *count += 1;
+                found(&solver);
+                return;
+            }
+        };
+        let mut cand = solver.cands[i];
+        while cand != 0 {
+            if *count >= limit {
+                return;
+            }
+            let bit = cand & cand.wrapping_neg();
+            cand &= !bit;
+            let mut next = solver;
+            if next.place(i, bit.trailing_zeros() as Data + 1) {
+                next.solutions_dfs(limit, count, found);
+            }
+        }
+    }
+}
+
+impl Solver for BitboardSolver {
+    fn has_finished(&self) -> PuzzleResult<bool> {
+        if self.broken {
+            return Err(PuzzleError::new("duplicated number"));
+        }
+        Ok(self.vacant == 0)
+    }
+    /// 確定できるマスがあれば埋め，なければ仮置きで解を探してそれに置き換える
+    fn search(&mut self) -> PuzzleResult<()> {
+        if self.broken {
+            return Err(PuzzleError::new("duplicated number"));
+        }
+        match self.singles() {
+            Some(true) => Ok(()),
+            Some(false) => {
+                let mut solution = None;
+                self.solutions_dfs(1, &mut 0, &mut |solver| solution = Some(*solver));
+                *self = solution.ok_or_else(|| PuzzleError::new("not found path"))?;
+                Ok(())
+            }
+            None => Err(PuzzleError::new("contradiction")),
+        }
+    }
+}
+
+impl Display for BitboardSolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = self.width();
+        for x in 0..width {
+            for y in 0..width {
+                match self.get(x, y) {
+                    Some(val) => write!(f, "{}", self.symbol(val))?,
+                    None => write!(f, ".")?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use super::super::super::Solver;
+    use super::super::constraint::Constraint;
+    use super::super::killer::Cage;
+    use super::super::SudokuSolver;
+    use super::BitboardSolver;
+
+    /// 仮置きの多い難しい問題と易しい問題
+    const PUZZLES: [&str; 6] = [
+        "9.23.45.1...2.8...7581.94236.4..5792...4.7...2179..8451.67.39.4...5.1...5.96.2317",
+        "8..........36......7..9.2...5...7.......457.....1...3...1....68..85...1..9....4..",
+        "1....7.9..3..2...8..96..5....53..9...1..8...26....4...3......1..4......7..7...3..",
+        "1.......2.9.4...5...6...7...5.9.3.......7.......85..4.7.....6...3...9.8...2.....1",
+        "..............3.85..1.2.......5.7.....4...1...9.......5......73..2.1........4...9",
+        "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+    ];
+
+    #[test]
+    fn test_bitboard() {
+        let mut sudoku = BitboardSolver::new(vec![
+            "902304501",
+            "000208000",
+            "758109423",
+            "604005792",
+            "000407000",
+            "217900845",
+            "106703904",
+            "000501000",
+            "509602317",
+        ]);
+        sudoku.run().unwrap();
+        let ans = BitboardSolver::new(vec![
+            "962374581",
+            "341258679",
+            "758169423",
+            "634815792",
+            "895427136",
+            "217936845",
+            "126783954",
+            "473591268",
+            "589642317",
+        ]);
+        assert_eq!(sudoku, ans);
+        assert!(sudoku.to_solver() == ans.to_solver());
+
+        // 仮置きが必要
+        let hard = BitboardSolver::new(vec![
+            "800000000",
+            "003600000",
+            "070090200",
+            "050007000",
+            "000045700",
+            "000100030",
+            "001000068",
+            "008500010",
+            "090000400",
+        ]);
+        assert!(hard.has_unique_solution());
+        let mut solver = hard;
+        solver.run().unwrap();
+        assert_eq!(format!("{}", solver).lines().next(), Some("812753649"));
+        // コピーなので元の盤面は変わらない
+        assert_eq!(hard.get(0, 1), None);
+        // SudokuSolverと同じ解
+        let mut slow = hard.to_solver();
+        slow.run().unwrap();
+        assert!(slow == solver.to_solver());
+
+        // 解が2つ
+        let two = BitboardSolver::new(vec![
+            "..3456789",
+            "456789123",
+            "789123456",
+            "..4365897",
+            "365897214",
+            "897214365",
+            "531642978",
+            "642978531",
+            "978531642",
+        ]);
+        assert_eq!(two.count_solutions(10), 2);
+        assert_eq!(two.all_solutions().len(), 2);
+        assert!(!two.has_unique_solution());
+
+        // 解がない
+        let mut wrong = BitboardSolver::new(vec![
+            "11.......",
+            ".........",
+            ".........",
+            ".........",
+            ".........",
+            ".........",
+            ".........",
+            ".........",
+            ".........",
+        ]);
+        assert_eq!(wrong.count_solutions(2), 0);
+        assert!(wrong.run().is_err());
+
+        // 16x16
+        let mut big = BitboardSolver::new(vec!["................"; 16]);
+        big.run().unwrap();
+        assert_eq!(big.width(), 16);
+        assert!(big.to_solver().has_finished().unwrap());
+    }
+    #[test]
+    fn test_parse() {
+        let rows: Vec<&str> = (0..9).map(|i| &PUZZLES[0][i * 9..i * 9 + 9]).collect();
+        let sudoku = BitboardSolver::parse(rows.clone()).unwrap();
+        assert_eq!(sudoku, BitboardSolver::new(rows.clone()));
+        let mut short = rows.clone();
+        short[3] = "6.4..579";
+        assert!(BitboardSolver::parse(short).is_err());
+        let mut bad = rows.clone();
+        bad[3] = "6.4..579x";
+        assert!(BitboardSolver::parse(bad).is_err());
+        let mut conflict = rows.clone();
+        conflict[1] = "9..2.8...";
+        assert!(BitboardSolver::parse(conflict.clone()).is_err());
+        // newは重なりを解のない盤面として読む
+        assert_eq!(BitboardSolver::new(conflict).count_solutions(1), 0);
+        assert!(BitboardSolver::parse(vec!["."; 25]).is_err());
+
+        // SudokuSolverからの変換
+        let solver = SudokuSolver::new(rows.clone());
+        assert_eq!(BitboardSolver::from_solver(&solver).unwrap(), sudoku);
+        let mut constraint = solver.clone();
+        constraint.add_constraint(Constraint::Diagonal);
+        assert!(BitboardSolver::from_solver(&constraint).is_err());
+        let mut killer = SudokuSolver::empty(3);
+        killer.add_cage(Cage::new(3, vec![(0, 0), (0, 1)])).unwrap();
+        assert!(BitboardSolver::from_solver(&killer).is_err());
+        let regions = vec![
+            "AAABBBCCC",
+            "AAABBBCCC",
+            "ADBABBCCC",
+            "ADDEEEFFF",
+            "DDEDEEFFF",
+            "DDDEEHFIF",
+            "GGGHHEIIF",
+            "GGGHHHIII",
+            "GGGHHHIII",
+        ];
+        let jigsaw = SudokuSolver::jigsaw(vec!["........."; 9], regions).unwrap();
+        assert!(BitboardSolver::from_solver(&jigsaw).is_err());
+    }
+    /// PUZZLESを解く速さをSudokuSolverと比べる
+    /// cargo test --release -- --ignored --nocapture bench_bitboard
+    #[test]
+    #[ignore]
+    fn bench_bitboard() {
+        const REPEAT: usize = 100;
+        let rows: Vec<Vec<&str>> = PUZZLES
+            .iter()
+            .map(|line| (0..9).map(|i| &line[i * 9..i * 9 + 9]).collect())
+            .collect();
+        let start = Instant::now();
+        let mut solutions = Vec::new();
+        for _ in 0..REPEAT {
+            solutions.clear();
+            for rows in &rows {
+                let mut solver = BitboardSolver::new(rows.clone());
+                solver.run().unwrap();
+                solutions.push(solver);
+            }
+        }
+        let bitboard = start.elapsed().as_secs_f64() / REPEAT as f64;
+
+        let start = Instant::now();
+        for (rows, solution) in rows.iter().zip(&solutions) {
+            let mut solver = SudokuSolver::new(rows.clone());
+            solver.run().unwrap();
+            assert!(solver == solution.to_solver());
+        }
+        let slow = start.elapsed().as_secs_f64();
+        eprintln!(
+            "{} puzzles: BitboardSolver {:.6}s, SudokuSolver {:.6}s ({:.1}x)",
+            PUZZLES.len(),
+            bitboard,
+            slow,
+            slow / bitboard
+        );
+        for rows in &rows {
+            assert!(BitboardSolver::new(rows.clone()).has_unique_solution());
+        }
+    }
+}
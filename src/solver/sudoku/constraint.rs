@@ -0,0 +1,90 @@
+//! 数独の追加ルール(バリアント)
+//! 行，列，ブロックに加えて全て異なる数字が入る領域と，同じ数字を置けない位置関係を定める
+
+/// 追加ルールの種類
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum Constraint {
+    /// 2本の対角線にも同じ数字が入らない(X-sudoku)
+    Diagonal,
+    /// ブロックの間に置いた窓にも同じ数字が入らない(9x9なら4つ)
+    Windoku,
+    /// キングの動きで隣り合うマスに同じ数字が入らない
+    AntiKing,
+    /// ナイトの動きで移れるマスに同じ数字が入らない
+    AntiKnight,
+}
+
+const KING: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+const KNIGHT: [(isize, isize); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
+
+impl Constraint {
+    /// 全て異なる数字が入る追加の領域
+    /// sizeはブロックの一辺の長さ
+    pub(super) fn units(&self, size: usize) -> Vec<Vec<(usize, usize)>> {
+        let width = size * size;
+        match self {
+            Constraint::Diagonal => vec![
+                (0..width).map(|i| (i, i)).collect(),
+                (0..width).map(|i| (i, width - 1 - i)).collect(),
+            ],
+            Constraint::Windoku => {
+                // ブロックの境界から一つずらした位置に一辺sizeの窓を置く
+                let starts: Vec<usize> = (0..size - 1).map(|k| 1 + k * (size + 1)).collect();
+                let mut units = Vec::new();
+                for &lx in &starts {
+                    for &ly in &starts {
+                        units.push((0..width).map(|i| (lx + i / size, ly + i % size)).collect());
+                    }
+                }
+                units
+            }
+            _ => Vec::new(),
+        }
+    }
+    /// 同じ数字を置けないマスへの相対位置
+    pub(super) fn offsets(&self) -> &'static [(isize, isize)] {
+        match self {
+            Constraint::AntiKing => &KING,
+            Constraint::AntiKnight => &KNIGHT,
+            _ => &[],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Constraint;
+
+    #[test]
+    fn test_units() {
+        let units = Constraint::Diagonal.units(3);
+        assert_eq!(units.len(), 2);
+        assert!(units.iter().all(|unit| unit.len() == 9));
+        assert_eq!(units[1][0], (0, 8));
+
+        let units = Constraint::Windoku.units(3);
+        assert_eq!(units.len(), 4);
+        assert_eq!(units[3][0], (5, 5));
+        assert_eq!(units[3][8], (7, 7));
+        assert!(Constraint::AntiKing.units(3).is_empty());
+        assert_eq!(Constraint::AntiKnight.offsets().len(), 8);
+    }
+}
@@ -0,0 +1,235 @@
+//! 数独を完全被覆問題に直してDancing Linksで解く
+//! 行はマスと数字の組，列は「マスに数字が入る」「行，列，ブロック(と追加ルールの領域)に数字が入る」
+//! 追加ルールで同じ数字を置けないマスの組は，数字ごとの副列(高々一度)で表す
+//! キラー数独のケージの和は表せないので扱わない
+
+use std::collections::BTreeSet;
+
+use super::super::dlx::DancingLinks;
+use super::super::{PuzzleError, PuzzleResult};
+use super::{Data, FieldElement, SudokuSolver};
+
+/// 完全被覆問題の行ごとの(数字, x, y)
+type Candidates = Vec<(Data, usize, usize)>;
+
+impl SudokuSolver {
+    /// Dancing Linksで解を見つけた順にfoundに渡す，盤面は変えない
+    /// foundがfalseを返したら打ち切る
+    pub fn dlx_solutions(&self, mut found: impl FnMut(&SudokuSolver) -> bool) -> PuzzleResult<()> {
+        let (mut dlx, candidates) = self.exact_cover()?;
+        dlx.solve(|rows| {
+            let mut solver = self.clone();
+            for &row in rows {
+                solver.set(candidates[row]);
+            }
+            found(&solver)
+        });
+        Ok(())
+    }
+    /// Dancing Linksで解の数をlimit個まで数える
+    pub fn dlx_count_solutions(&self, limit: usize) -> PuzzleResult<usize> {
+        let (mut dlx, _) = self.exact_cover()?;
+        Ok(dlx.count_solutions(limit))
+    }
+    /// Dancing Linksで最初に見つかった解で盤面を埋める
+    /// 解がなければPuzzleErrorを返す
+    pub fn dlx_solve(&mut self) -> PuzzleResult<()> {
+        let (mut dlx, candidates) = self.exact_cover()?;
+        let rows = dlx
+            .first_solution()
+            .ok_or_else(|| PuzzleError::new("not found path"))?;
+        for row in rows {
+            self.set(candidates[row]);
+        }
+        Ok(())
+    }
+
+    /// 完全被覆問題と，その行ごとの(数字, x, y)
+    fn exact_cover(&self) -> PuzzleResult<(DancingLinks, Candidates)> {
+        if !self.cages.is_empty() {
+            return Err(PuzzleError::new("killer cages cannot be solved with dlx"));
+        }
+        let width = self.width();
+        let cells = width * width;
+        // 追加ルールで同じ数字を置けないマスの組
+        let mut pairs = BTreeSet::new();
+        for x in 0..width {
+            for y in 0..width {
+                for (px, py) in self.constraint_peers(x, y) {
+                    // 同じ領域のマスは主列で表せている
+                    let same_unit = self
+                        .units
+                        .iter()
+                        .any(|unit| unit.contains(&(x, y)) && unit.contains(&(px, py)));
+                    if (x, y) < (px, py) && !same_unit {
+                        pairs.insert(((x, y), (px, py)));
+                    }
+                }
+            }
+        }
+        let pairs: Vec<_> = pairs.into_iter().collect();
+        let primary = 4 * cells + self.units.len() * width;
+        let mut dlx = DancingLinks::new(primary, pairs.len() * width);
+        let mut candidates = Vec::new();
+        for x in 0..width {
+            for y in 0..width {
+                let nums: Vec<Data> = match &*self.field[x][y].lock().unwrap() {
+                    FieldElement::NUM(num) => vec![*num],
+                    FieldElement::SUG(set) => set.iter().copied().collect(),
+                };
+                for num in nums {
+                    let n = num - 1;
+                    let mut columns = vec![
+                        x * width + y,
+                        cells + x * width + n,
+                        2 * cells + y * width + n,
+                        3 * cells + self.xy_to_block_ind(x, y) * width + n,
+                    ];
+                    for (i, unit) in self.units.iter().enumerate() {
+                        if unit.contains(&(x, y)) {
+                            columns.push(4 * cells + i * width + n);
+                        }
+                    }
+                    for (i, &(a, b)) in pairs.iter().enumerate() {
+                        if a == (x, y) || b == (x, y) {
+                            columns.push(primary + i * width + n);
+                        }
+                    }
+                    dlx.add_row(&columns)?;
+                    candidates.push((num, x, y));
+                }
+            }
+        }
+        Ok((dlx, candidates))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::constraint::Constraint;
+    use super::super::killer::Cage;
+    use super::super::SudokuSolver;
+    use crate::solver::Solver;
+
+    #[test]
+    fn test_dlx() {
+        let puzzle = vec![
+            "800000000",
+            "003600000",
+            "070090200",
+            "050007000",
+            "000045700",
+            "000100030",
+            "001000068",
+            "008500010",
+            "090000400",
+        ];
+        let mut sudoku = SudokuSolver::new(puzzle);
+        assert_eq!(sudoku.dlx_count_solutions(2).unwrap(), 1);
+        sudoku.dlx_solve().unwrap();
+        assert!(sudoku.has_finished().unwrap());
+        assert_eq!(
+            sudoku.to_line(),
+            "812753649943682175675491283154237896369845721287169534521974368438526917796318452"
+        );
+
+        // 2と8を入れ替えられるので解が2つある
+        let sudoku = SudokuSolver::new(vec![
+            "960374501",
+            "341258679",
+            "750169403",
+            "634815792",
+            "895427136",
+            "217936845",
+            "126783954",
+            "473591268",
+            "589642317",
+        ]);
+        assert_eq!(sudoku.dlx_count_solutions(10).unwrap(), 2);
+        let mut solutions = Vec::new();
+        sudoku
+            .dlx_solutions(|solver| {
+                solutions.push(solver.clone());
+                true
+            })
+            .unwrap();
+        assert_eq!(solutions.len(), 2);
+        assert!(solutions[0] != solutions[1]);
+        for solution in &solutions {
+            assert!(solution.has_finished().unwrap());
+        }
+        // 元の盤面は変わらない
+        assert!(sudoku.is_vacant());
+
+        // 追加ルールとジグソー
+        for constraint in [
+            Constraint::Diagonal,
+            Constraint::Windoku,
+            Constraint::AntiKing,
+        ] {
+            let mut sudoku = SudokuSolver::new(vec!["000000000"; 9]);
+            sudoku.add_constraint(constraint);
+            sudoku.dlx_solve().unwrap();
+            assert!(sudoku.has_finished().unwrap());
+        }
+        // (0,2)と(1,4)の1がナイトの動きで移れる
+        let mut knight = SudokuSolver::new(vec![
+            "001000000",
+            "000010000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+        ]);
+        assert_eq!(knight.dlx_count_solutions(1).unwrap(), 1);
+        knight.add_constraint(Constraint::AntiKnight);
+        assert_eq!(knight.dlx_count_solutions(1).unwrap(), 0);
+        let jigsaw = SudokuSolver::jigsaw(
+            vec![
+                "020000780",
+                "000000005",
+                "400089200",
+                "050900001",
+                "000002000",
+                "096000002",
+                "000020000",
+                "000000470",
+                "000710000",
+            ],
+            vec![
+                "AAABBBCCC",
+                "AAABBBCCC",
+                "ADBABBCCC",
+                "ADDEEEFFF",
+                "DDEDEEFFF",
+                "DDDEEHFIF",
+                "GGGHHEIIF",
+                "GGGHHHIII",
+                "GGGHHHIII",
+            ],
+        )
+        .unwrap();
+        assert_eq!(jigsaw.dlx_count_solutions(2).unwrap(), 1);
+
+        // 解のない盤面とケージ
+        let sudoku = SudokuSolver::new(vec![
+            "110000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+        ]);
+        assert_eq!(sudoku.dlx_count_solutions(2).unwrap(), 0);
+        assert!(sudoku.clone().dlx_solve().is_err());
+        let mut killer = SudokuSolver::new(vec!["000000000"; 9]);
+        killer.add_cage(Cage::new(3, vec![(0, 0), (0, 1)])).unwrap();
+        assert!(killer.dlx_count_solutions(1).is_err());
+    }
+}
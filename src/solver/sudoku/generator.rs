@@ -0,0 +1,205 @@
+//! ランダムな唯一解の問題の生成
+//! ランダムに埋めた盤面から，解が一つのままである限り数字を一つずつ消していく
+//! 同じシードからは同じ問題ができる
+
+use super::super::random::Random;
+use super::super::{PuzzleError, PuzzleResult};
+use super::bitboard::BitboardSolver;
+use super::technique::Difficulty;
+use super::{symbols, Data, SudokuSolver};
+
+/// 目標の難易度にならなかった時に作り直す回数
+const ATTEMPTS: usize = 50;
+
+/// 残す数字の配置の対称性
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum Symmetry {
+    None,
+    /// 中心について180度回転
+    Rotational,
+    /// 左右反転
+    Mirror,
+    /// 左上から右下への対角線で反転
+    Diagonal,
+}
+
+impl Symmetry {
+    /// (x, y)と一緒に消すマス((x, y)自身を含む)
+    fn orbit(&self, x: usize, y: usize, width: usize) -> Vec<(usize, usize)> {
+        let other = match self {
+            Symmetry::None => (x, y),
+            Symmetry::Rotational => (width - 1 - x, width - 1 - y),
+            Symmetry::Mirror => (x, width - 1 - y),
+            Symmetry::Diagonal => (y, x),
+        };
+        if other == (x, y) {
+            vec![(x, y)]
+        } else {
+            vec![(x, y), other]
+        }
+    }
+}
+
+/// 0を空きマスとした盤面を一行ずつの文字列にする
+fn to_rows(grid: &[Vec<Data>]) -> Vec<String> {
+    let symbols = symbols(grid.len()).unwrap();
+    grid.iter()
+        .map(|row| {
+            row.iter()
+                .map(|&val| if val == 0 { '.' } else { symbols[val - 1] })
+                .collect()
+        })
+        .collect()
+}
+
+impl SudokuSolver {
+    /// 唯一解の問題をランダムに作る
+    /// sizeはブロックの一辺の長さ(4まで)，symmetryの対称性を保ったまま数字を消す
+    /// targetを指定すると，消した後の難易度がtargetを超える数字は消さず，ちょうどtargetになるまで作り直す
+    /// 何度作り直してもtargetにならなければPuzzleErrorを返す
+    pub fn generate(
+        size: usize,
+        symmetry: Symmetry,
+        target: Option<Difficulty>,
+        seed: u64,
+    ) -> PuzzleResult<Self> {
+        if size == 0 || size > 4 {
+            return Err(PuzzleError::new(format!("cannot generate size {}", size)));
+        }
+        let mut random = Random::new(seed);
+        for _ in 0..ATTEMPTS {
+            let grid = Self::random_grid(size, &mut random);
+            let puzzle = Self::remove_clues(grid, symmetry, target, &mut random);
+            let rows = to_rows(&puzzle);
+            let solver = SudokuSolver::new(rows.iter().map(|row| row.as_str()).collect());
+            match target {
+                Some(target) if solver.difficulty()? != target => continue,
+                _ => return Ok(solver),
+            }
+        }
+        Err(PuzzleError::new(format!(
+            "no puzzle with difficulty {:?} in {} attempts",
+            target, ATTEMPTS
+        )))
+    }
+
+    /// ランダムに埋めた盤面
+    /// 対角線上のブロックは互いに関係しないのでランダムな順列で埋め，残りを解いてから数字を入れ替える
+    /// (4x4では残りを埋められないことがあるので，その時は埋め直す)
+    fn random_grid(size: usize, random: &mut Random) -> Vec<Vec<Data>> {
+        let width = size * size;
+        let solution = loop {
+            let mut grid = vec![vec![0; width]; width];
+            for block in 0..size {
+                let mut nums: Vec<Data> = (1..=width).collect();
+                random.shuffle(&mut nums);
+                for (i, num) in nums.into_iter().enumerate() {
+                    grid[block * size + i / size][block * size + i % size] = num;
+                }
+            }
+            let rows = to_rows(&grid);
+            let mut solution = None;
+            BitboardSolver::new(rows.iter().map(|row| row.as_str()).collect()).solutions_dfs(
+                1,
+                &mut 0,
+                &mut |solver| solution = Some(*solver),
+            );
+            if let Some(solution) = solution {
+                break solution;
+            }
+        };
+        let mut relabel: Vec<Data> = (1..=width).collect();
+        random.shuffle(&mut relabel);
+        (0..width)
+            .map(|x| {
+                (0..width)
+                    .map(|y| relabel[solution.get(x, y).unwrap() - 1])
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// ランダムな順番で，解が一つのままで難易度がtargetを超えない限り数字を消す
+    fn remove_clues(
+        mut grid: Vec<Vec<Data>>,
+        symmetry: Symmetry,
+        target: Option<Difficulty>,
+        random: &mut Random,
+    ) -> Vec<Vec<Data>> {
+        let width = grid.len();
+        let mut cells: Vec<(usize, usize)> =
+            (0..width * width).map(|i| (i / width, i % width)).collect();
+        random.shuffle(&mut cells);
+        for (x, y) in cells {
+            if grid[x][y] == 0 {
+                continue;
+            }
+            let orbit = symmetry.orbit(x, y, width);
+            let saved: Vec<Data> = orbit.iter().map(|&(x, y)| grid[x][y]).collect();
+            for &(x, y) in &orbit {
+                grid[x][y] = 0;
+            }
+            let rows = to_rows(&grid);
+            let rows: Vec<&str> = rows.iter().map(|row| row.as_str()).collect();
+            let keep = BitboardSolver::new(rows.clone()).has_unique_solution()
+                && match target {
+                    Some(target) if target < Difficulty::Extreme => SudokuSolver::new(rows)
+                        .difficulty()
+                        .is_ok_and(|difficulty| difficulty <= target),
+                    _ => true,
+                };
+            if !keep {
+                for (&(x, y), &val) in orbit.iter().zip(&saved) {
+                    grid[x][y] = val;
+                }
+            }
+        }
+        grid
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::technique::Difficulty;
+    use super::super::{FieldElement, SudokuSolver};
+    use super::Symmetry;
+    use crate::solver::Solver;
+
+    #[test]
+    fn test_generate() {
+        let sudoku = SudokuSolver::generate(3, Symmetry::None, None, 0).unwrap();
+        assert!(sudoku.has_unique_solution());
+        // 同じシードなら同じ問題
+        let again = SudokuSolver::generate(3, Symmetry::None, None, 0).unwrap();
+        assert_eq!(format!("{}", sudoku), format!("{}", again));
+        let other = SudokuSolver::generate(3, Symmetry::None, None, 1).unwrap();
+        assert_ne!(format!("{}", sudoku), format!("{}", other));
+        let mut solver = sudoku.clone();
+        solver.run().unwrap();
+
+        // 180度回転で対称
+        let sudoku = SudokuSolver::generate(3, Symmetry::Rotational, None, 2).unwrap();
+        assert!(sudoku.has_unique_solution());
+        let is_blank = |x: usize, y: usize| {
+            matches!(*sudoku.field[x][y].lock().unwrap(), FieldElement::SUG(_))
+        };
+        for x in 0..9 {
+            for y in 0..9 {
+                assert_eq!(is_blank(x, y), is_blank(8 - x, 8 - y));
+            }
+        }
+
+        // 目標の難易度
+        let sudoku = SudokuSolver::generate(3, Symmetry::None, Some(Difficulty::Easy), 3).unwrap();
+        assert_eq!(sudoku.difficulty().unwrap(), Difficulty::Easy);
+        assert!(sudoku.has_unique_solution());
+        let sudoku =
+            SudokuSolver::generate(3, Symmetry::Rotational, Some(Difficulty::Hard), 5).unwrap();
+        assert_eq!(sudoku.difficulty().unwrap(), Difficulty::Hard);
+
+        let sudoku = SudokuSolver::generate(2, Symmetry::Mirror, None, 4).unwrap();
+        assert_eq!(sudoku.width(), 4);
+        assert!(sudoku.has_unique_solution());
+        assert!(SudokuSolver::generate(5, Symmetry::None, None, 0).is_err());
+    }
+}
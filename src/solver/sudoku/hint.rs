@@ -0,0 +1,288 @@
+//! 途中まで埋めた盤面に対するヒント
+//! 盤面は変えずに，次に使える最も簡単な推論を一つだけ説明付きで返す
+
+use std::fmt::Display;
+
+use super::super::{PuzzleError, PuzzleResult};
+use super::technique::{Step, Technique};
+use super::{FieldElement, SudokuSolver};
+
+/// ヒントの結果
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+pub enum Hint {
+    /// 次に使える推論とその説明
+    Step { step: Step, description: String },
+    /// 今の盤面は正しい解と矛盾している(わかる場合は間違っているマス)
+    Mistake(Vec<(usize, usize)>),
+    /// 全て埋まっている
+    Solved,
+}
+
+impl Display for Hint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Hint::Step { description, .. } => write!(f, "{}", description),
+            Hint::Mistake(cells) if cells.is_empty() => {
+                write!(f, "the grid has no solution")
+            }
+            Hint::Mistake(cells) => {
+                let cells: Vec<String> = cells.iter().map(|&(x, y)| cell_name(x, y)).collect();
+                write!(f, "wrong entries at {}", cells.join(", "))
+            }
+            Hint::Solved => write!(f, "the grid is already solved"),
+        }
+    }
+}
+
+/// (x, y)を1から数えてr1c1のように表す
+fn cell_name(x: usize, y: usize) -> String {
+    format!("r{}c{}", x + 1, y + 1)
+}
+
+impl SudokuSolver {
+    /// 次に使える最も簡単な推論を返す，盤面は変えない
+    /// 確定した数字から候補を消すだけの手順は飛ばし，論理で進めなければ正しい数字を一つ仮置きとして教える
+    /// 盤面に解がなければHint::Mistakeを返す
+    pub fn hint(&self) -> PuzzleResult<Hint> {
+        let work = self.clone();
+        work.remove_given_suggest();
+        work.clear_steps();
+        if work.has_contradiction() {
+            return Ok(Hint::Mistake(self.duplicated_cells()));
+        }
+        if !work.is_vacant() {
+            return Ok(Hint::Solved);
+        }
+        let solution = match work.first_solution() {
+            Some(solution) => solution,
+            None => return Ok(Hint::Mistake(self.duplicated_cells())),
+        };
+        for technique in Technique::LOGICAL {
+            if work.apply_technique(technique) {
+                if let Some(step) = work.steps().into_iter().next() {
+                    let description = self.describe(&step);
+                    return Ok(Hint::Step { step, description });
+                }
+            }
+        }
+        // 論理では進めないので，候補の最も少ないマスの正しい数字を教える
+        let (_, x, y) = work
+            .pickup_elem()?
+            .next()
+            .ok_or_else(|| PuzzleError::new("no candidate"))?;
+        let num = match *solution.field[x][y].lock().unwrap() {
+            FieldElement::NUM(num) => num,
+            FieldElement::SUG(_) => return Err(PuzzleError::new("solution is not filled")),
+        };
+        let step = Step {
+            technique: Technique::Guess,
+            cells: vec![(x, y)],
+            digits: vec![num],
+            eliminations: Vec::new(),
+        };
+        let description = self.describe(&step);
+        Ok(Hint::Step { step, description })
+    }
+    /// puzzleを元の問題として，その解と違う数字を入れたマスがあればHint::Mistakeを返す
+    /// そうでなければhintと同じ
+    pub fn hint_from(&self, puzzle: &SudokuSolver) -> PuzzleResult<Hint> {
+        let solution = puzzle
+            .first_solution()
+            .ok_or_else(|| PuzzleError::new("the puzzle has no solution"))?;
+        let mut wrong = Vec::new();
+        for x in 0..self.width() {
+            for y in 0..self.width() {
+                if let FieldElement::NUM(num) = *self.field[x][y].lock().unwrap() {
+                    if *solution.field[x][y].lock().unwrap() != FieldElement::NUM(num) {
+                        wrong.push((x, y));
+                    }
+                }
+            }
+        }
+        if !wrong.is_empty() {
+            return Ok(Hint::Mistake(wrong));
+        }
+        self.hint()
+    }
+
+    /// 最初に見つかった解
+    fn first_solution(&self) -> Option<SudokuSolver> {
+        let mut solution = None;
+        self.clone()
+            .solutions_dfs(1, &mut 0, &mut |solver| solution = Some(solver.clone()));
+        solution
+    }
+    /// 同じ行，列，ブロック(と追加ルールで関係するマス)に同じ数字があるマス
+    fn duplicated_cells(&self) -> Vec<(usize, usize)> {
+        let width = self.width();
+        let mut cells = Vec::new();
+        for x in 0..width {
+            for y in 0..width {
+                let num = match *self.field[x][y].lock().unwrap() {
+                    FieldElement::NUM(num) => num,
+                    FieldElement::SUG(_) => continue,
+                };
+                let block = self.xy_to_block_ind(x, y);
+                let duplicated = (0..width)
+                    .flat_map(|t| [(x, t), (t, y)])
+                    .chain(self.block_cells(block))
+                    .chain(self.constraint_peers(x, y))
+                    .any(|(px, py)| {
+                        (px, py) != (x, y)
+                            && *self.field[px][py].lock().unwrap() == FieldElement::NUM(num)
+                    });
+                if duplicated {
+                    cells.push((x, y));
+                }
+            }
+        }
+        cells
+    }
+    /// 推論の説明
+    fn describe(&self, step: &Step) -> String {
+        let digits: Vec<String> = step
+            .digits
+            .iter()
+            .map(|&n| self.symbol(n).to_string())
+            .collect();
+        let digits = digits.join(", ");
+        let cells: Vec<String> = step.cells.iter().map(|&(x, y)| cell_name(x, y)).collect();
+        let eliminations: Vec<String> = step
+            .eliminations
+            .iter()
+            .map(|&(x, y, n)| format!("{}<>{}", cell_name(x, y), self.symbol(n)))
+            .collect();
+        let eliminations = eliminations.join(", ");
+        match step.technique {
+            Technique::NakedSingle if eliminations.is_empty() => {
+                format!("Naked single: {} can only be {}", cells[0], digits)
+            }
+            Technique::NakedSingle => format!(
+                "Naked single: {} can only be {}, so remove it from {}",
+                cells[0], digits, eliminations
+            ),
+            Technique::HiddenSingle => format!(
+                "Hidden single: {} has only one place in its row, column or box, so {} is {}",
+                digits, cells[0], digits
+            ),
+            Technique::LockedCandidates => format!(
+                "Locked candidates: {} in one box lies on a single line ({}), so remove {}",
+                digits,
+                cells.join(", "),
+                eliminations
+            ),
+            Technique::Guess => format!("No logical step found: try {} at {}", digits, cells[0]),
+            technique => format!(
+                "{:?}: {} at {}, so remove {}",
+                technique,
+                digits,
+                cells.join(", "),
+                eliminations
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::technique::{Step, Technique};
+    use super::super::{FieldElement, SudokuSolver};
+    use super::Hint;
+    use crate::solver::Solver;
+
+    /// ヒントの通りに数字を入れるか候補を消す
+    fn apply(solver: &SudokuSolver, step: &Step) {
+        let place = match step.technique {
+            Technique::NakedSingle | Technique::HiddenSingle | Technique::Guess => {
+                Some(step.cells[0])
+            }
+            _ => None,
+        };
+        match place {
+            Some((x, y)) => *solver.field[x][y].lock().unwrap() = FieldElement::NUM(step.digits[0]),
+            None => {
+                for &(x, y, n) in &step.eliminations {
+                    if let FieldElement::SUG(set) = &mut *solver.field[x][y].lock().unwrap() {
+                        set.remove(&n);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_hint() {
+        let puzzle = SudokuSolver::from_line(
+            "9.23.45.1...2.8...7581.94236.4..5792...4.7...2179..8451.67.39.4...5.1...5.96.2317",
+        )
+        .unwrap();
+        let hint = puzzle.hint().unwrap();
+        // 盤面は変わらない
+        assert!(puzzle.steps().is_empty());
+        assert_eq!(
+            puzzle.to_line(),
+            "9.23.45.1...2.8...7581.94236.4..5792...4.7...2179..8451.67.39.4...5.1...5.96.2317"
+        );
+        match &hint {
+            Hint::Step { step, description } => {
+                assert_eq!(step.technique, Technique::NakedSingle);
+                assert!(description.starts_with("Naked single"));
+            }
+            _ => unreachable!(),
+        }
+
+        // 一か所にしか入らない数字が必要
+        let puzzle = SudokuSolver::from_line(
+            "000007000020008040103000000000150000000300070000000089090000000080002000000600100",
+        )
+        .unwrap();
+        let mut hints = Vec::new();
+        let solver = puzzle.clone();
+        // ヒントに従って最後まで進める
+        loop {
+            match solver.hint_from(&puzzle).unwrap() {
+                Hint::Step { step, .. } => {
+                    hints.push(step.technique);
+                    apply(&solver, &step);
+                }
+                Hint::Solved => break,
+                Hint::Mistake(_) => unreachable!(),
+            }
+        }
+        assert!(hints.contains(&Technique::HiddenSingle));
+        assert!(!hints.contains(&Technique::Guess));
+        assert!(solver.has_finished().unwrap());
+
+        // 規則には反しないが解とは違う数字をr1c1に入れた
+        let answer = puzzle.all_solutions().remove(0).to_line();
+        let wrong = (b'1'..=b'9')
+            .filter(|&c| c != answer.as_bytes()[0])
+            .find_map(|c| {
+                let mut line = puzzle.to_line().into_bytes();
+                line[0] = c;
+                SudokuSolver::from_line(std::str::from_utf8(&line).unwrap()).ok()
+            })
+            .unwrap();
+        assert_eq!(
+            wrong.hint_from(&puzzle).unwrap(),
+            Hint::Mistake(vec![(0, 0)])
+        );
+        // 同じ行に同じ数字
+        let mut duplicated = puzzle.to_line().into_bytes();
+        duplicated[0] = b'7';
+        let duplicated = SudokuSolver::new(
+            (0..9)
+                .map(|i| std::str::from_utf8(&duplicated[i * 9..i * 9 + 9]).unwrap())
+                .collect(),
+        );
+        assert_eq!(
+            duplicated.hint().unwrap(),
+            Hint::Mistake(vec![(0, 0), (0, 5)])
+        );
+        assert!(!format!("{}", duplicated.hint().unwrap()).is_empty());
+
+        let mut solved = puzzle.clone();
+        solved.run().unwrap();
+        assert_eq!(solved.hint().unwrap(), Hint::Solved);
+    }
+}
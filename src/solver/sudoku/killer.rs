@@ -0,0 +1,67 @@
+//! キラー数独のケージ
+//! ケージ内のマスには異なる数字が入り，その和が決められた値になる
+
+use super::Data;
+
+/// 和の決まったマスの集まり
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+pub struct Cage {
+    sum: Data,
+    cells: Vec<(usize, usize)>,
+}
+
+impl Cage {
+    pub fn new(sum: Data, cells: Vec<(usize, usize)>) -> Self {
+        Cage { sum, cells }
+    }
+    pub fn sum(&self) -> Data {
+        self.sum
+    }
+    pub fn cells(&self) -> &[(usize, usize)] {
+        &self.cells
+    }
+
+    /// 1からwidthまでの異なる数字でマスの数だけ選んで和がsumになる組
+    /// 数字dを(d - 1)ビット目で表す
+    pub(super) fn combinations(&self, width: usize) -> Vec<u32> {
+        fn rec(start: Data, width: usize, rest: usize, sum: Data, mask: u32, ret: &mut Vec<u32>) {
+            if rest == 0 {
+                if sum == 0 {
+                    ret.push(mask);
+                }
+                return;
+            }
+            for d in start..=width {
+                // 残りは昇順に選ぶので，dを選ぶと和は最低でもd * rest以上になる
+                if d * rest > sum {
+                    break;
+                }
+                rec(d + 1, width, rest - 1, sum - d, mask | 1 << (d - 1), ret);
+            }
+        }
+        let mut ret = Vec::new();
+        rec(1, width, self.cells.len(), self.sum, 0, &mut ret);
+        ret
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Cage;
+
+    #[test]
+    fn test_combinations() {
+        let cage = Cage::new(3, vec![(0, 0), (0, 1)]);
+        assert_eq!(cage.combinations(9), vec![0b11]);
+        let cage = Cage::new(17, vec![(0, 0), (0, 1)]);
+        assert_eq!(cage.combinations(9), vec![0b1_1000_0000]);
+        // 10 = 1+9 = 2+8 = 3+7 = 4+6
+        let cage = Cage::new(10, vec![(0, 0), (0, 1)]);
+        assert_eq!(cage.combinations(9).len(), 4);
+        // 45 = 1+...+9
+        let cage = Cage::new(45, (0..9).map(|i| (0, i)).collect());
+        assert_eq!(cage.combinations(9), vec![0b1_1111_1111]);
+        let cage = Cage::new(46, (0..9).map(|i| (0, i)).collect());
+        assert!(cage.combinations(9).is_empty());
+    }
+}
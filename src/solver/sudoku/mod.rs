@@ -0,0 +1,2224 @@
+mod advanced;
+pub mod batch;
+pub mod bitboard;
+pub mod constraint;
+mod exact_cover;
+pub mod generator;
+pub mod hint;
+pub mod killer;
+pub mod notation;
+pub mod technique;
+
+use super::PuzzleError;
+use super::PuzzleResult;
+use super::Solver;
+use constraint::Constraint;
+use core::panic;
+use killer::Cage;
+use rayon::prelude::*;
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use technique::{Difficulty, Step, Technique};
+
+type Data = usize;
+
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+pub enum FieldElement {
+    SUG(BTreeSet<Data>),
+    /// 確定したマス
+    NUM(Data),
+}
+
+impl FieldElement {
+    /// suggestでnumに変更する場合のみ変更した値を，それ以外は0を返す
+    pub fn suggest_confirmed(&mut self) -> Data {
+        let val = match self {
+            FieldElement::SUG(x) => {
+                if x.len() == 1 {
+                    let val = *x.iter().next().unwrap();
+                    assert!(val > 0);
+                    *self = FieldElement::NUM(val);
+                    val
+                } else {
+                    0
+                }
+            }
+            _ => 0,
+        };
+        val
+    }
+}
+
+/// 行，列，ブロックごとに確定済みの数字を記録する
+type Cache = Vec<Arc<Mutex<Vec<bool>>>>;
+
+/// n^2 x n^2の数独(9x9ならn=3)
+/// 数字は1からn^2で持ち，表示や読み込みの時だけ記号に変換する
+#[derive(Debug)]
+pub struct SudokuSolver {
+    pub field: Vec<Vec<Arc<Mutex<FieldElement>>>>,
+    /// ブロックの一辺の長さ(n)
+    size: usize,
+    /// ブロックごとのマス(ジグソー数独では不規則な領域)
+    blocks: Vec<Vec<(usize, usize)>>,
+    /// マスごとのブロックの番号
+    block_ind: Vec<Vec<usize>>,
+    row_cache: Cache,
+    col_cache: Cache,
+    block_cache: Cache,
+    /// 有効な追加ルール
+    constraints: Vec<Constraint>,
+    /// 追加ルールで全て異なる数字が入る領域
+    units: Vec<Vec<(usize, usize)>>,
+    /// 追加ルールで同じ数字を置けないマスへの相対位置
+    offsets: Vec<(isize, isize)>,
+    /// キラー数独のケージ
+    cages: Vec<Cage>,
+    /// ケージごとの和を作れる数字の組
+    combinations: Vec<Vec<u32>>,
+    /// 推論の記録
+    log: Mutex<Vec<Step>>,
+    /// 使わないテクニック
+    disabled: Vec<Technique>,
+}
+/// Arcをそのままcloneすると中身を共有してしまい，dfsで元の状態に戻せないので中身ごとcloneする
+impl Clone for SudokuSolver {
+    fn clone(&self) -> Self {
+        let field = self
+            .field
+            .iter()
+            .map(|vec| {
+                vec.iter()
+                    .map(|elem| Arc::new(Mutex::new(elem.lock().unwrap().clone())))
+                    .collect()
+            })
+            .collect();
+        let clone_cache = |cache: &Cache| {
+            cache
+                .iter()
+                .map(|c| Arc::new(Mutex::new(c.lock().unwrap().clone())))
+                .collect()
+        };
+        SudokuSolver {
+            field,
+            size: self.size,
+            blocks: self.blocks.clone(),
+            block_ind: self.block_ind.clone(),
+            row_cache: clone_cache(&self.row_cache),
+            col_cache: clone_cache(&self.col_cache),
+            block_cache: clone_cache(&self.block_cache),
+            constraints: self.constraints.clone(),
+            units: self.units.clone(),
+            offsets: self.offsets.clone(),
+            cages: self.cages.clone(),
+            combinations: self.combinations.clone(),
+            log: Mutex::new(self.log.lock().unwrap().clone()),
+            disabled: self.disabled.clone(),
+        }
+    }
+}
+impl PartialEq for SudokuSolver {
+    fn eq(&self, other: &Self) -> bool {
+        self.field
+            .iter()
+            .zip(&other.field)
+            .map(|(s, o)| {
+                for (s, o) in s.iter().zip(o) {
+                    if *s.lock().unwrap() != *o.lock().unwrap() {
+                        return false;
+                    }
+                }
+                true
+            })
+            .fold(true, |x, y| x & y)
+    }
+}
+impl Eq for SudokuSolver {}
+
+/// 盤面の一辺の長さごとの数字の記号
+/// 9以下は1から，16x16は16進数の0からF，それより大きいものはAからの英字を使う
+fn symbols(len: usize) -> Option<Vec<char>> {
+    let chars: Vec<char> = match len {
+        0..=9 => "123456789".chars().collect(),
+        16 => "0123456789ABCDEF".chars().collect(),
+        _ => ('A'..='Z').collect(),
+    };
+    if len > chars.len() {
+        return None;
+    }
+    Some(chars[..len].to_vec())
+}
+
+impl SudokuSolver {
+    /// 一行ずつの文字列から作る．行の数n^2から盤面の大きさを決める
+    /// 空きマスは'*'か'.'(記号に'0'を使わない大きさなら'0'も)
+    /// 読めない文字列ではpanicするので，入力が正しいかわからなければparseを使う
+    pub fn new(string: Vec<&str>) -> Self {
+        match Self::read(string) {
+            Ok(solver) => solver,
+            Err(e) => panic!("invalid data: {}", e),
+        }
+    }
+    /// newと同じ形式の文字列から作る
+    /// 行の長さが違う，使えない文字がある，同じ行，列，ブロックに同じ数字があればその位置を含むPuzzleErrorを返す
+    pub fn parse(string: Vec<&str>) -> PuzzleResult<Self> {
+        let solver = Self::read(string)?;
+        solver.check_givens()?;
+        Ok(solver)
+    }
+    /// 文字列を読む，数字の重なりは確かめない
+    fn read(string: Vec<&str>) -> PuzzleResult<Self> {
+        let len = string.len();
+        let size = (1..=len).find(|x| x * x >= len).unwrap_or(0);
+        if size * size != len || len == 0 {
+            return Err(PuzzleError::new(format!(
+                "wrong length: the number of rows must be a square, but is {}",
+                len
+            )));
+        }
+        let symbols = symbols(len)
+            .ok_or_else(|| PuzzleError::new(format!("wrong length: {} rows is too large", len)))?;
+        let mut field = Vec::new();
+        let default_tree = FieldElement::SUG((1..=len).collect());
+        let new_cache = || {
+            (0..len)
+                .map(|_| Arc::new(Mutex::new(vec![false; len])))
+                .collect()
+        };
+
+        for (xind, i) in string.iter().enumerate() {
+            field.push(Vec::new());
+            if i.chars().count() != len {
+                return Err(PuzzleError::new(format!(
+                    "row {}: wrong length, expected {} but found {}",
+                    xind,
+                    len,
+                    i.chars().count()
+                )));
+            }
+            for (yind, c) in i.chars().enumerate() {
+                let c = c.to_ascii_uppercase();
+                match symbols.iter().position(|&x| x == c) {
+                    Some(pos) => {
+                        let val = pos + 1;
+                        field[xind].push(Arc::new(Mutex::new(FieldElement::NUM(val))));
+                    }
+                    None if c == '*' || c == '.' || c == '0' => {
+                        field[xind].push(Arc::new(Mutex::new(default_tree.clone())))
+                    }
+                    None => {
+                        return Err(PuzzleError::new(format!(
+                            "row {}, column {}: bad character '{}'",
+                            xind, yind, c
+                        )))
+                    }
+                }
+            }
+        }
+
+        // n x nの正方形のブロック
+        let blocks: Vec<Vec<(usize, usize)>> = (0..len)
+            .map(|block| {
+                let (lx, ly) = ((block / size) * size, (block % size) * size);
+                (0..len).map(|i| (lx + i / size, ly + i % size)).collect()
+            })
+            .collect();
+        let block_ind = (0..len)
+            .map(|x| (0..len).map(|y| (x / size) * size + y / size).collect())
+            .collect();
+
+        let mut solver = SudokuSolver {
+            field,
+            size,
+            blocks,
+            block_ind,
+            row_cache: new_cache(),
+            col_cache: new_cache(),
+            block_cache: new_cache(),
+            constraints: Vec::new(),
+            units: Vec::new(),
+            offsets: Vec::new(),
+            cages: Vec::new(),
+            combinations: Vec::new(),
+            log: Mutex::new(Vec::new()),
+            disabled: Vec::new(),
+        };
+        solver.init();
+        Ok(solver)
+    }
+    fn init(&mut self) {}
+    /// 同じ行，列，ブロック(と追加ルールで関係するマス)に同じ数字が入っていないか確かめる
+    /// 重なっていれば後に現れたマスの位置を含むPuzzleErrorを返す
+    fn check_givens(&self) -> PuzzleResult<()> {
+        let width = self.width();
+        for x in 0..width {
+            for y in 0..width {
+                let val = match *self.field[x][y].lock().unwrap() {
+                    FieldElement::NUM(val) => val,
+                    FieldElement::SUG(_) => continue,
+                };
+                let block = self.xy_to_block_ind(x, y);
+                let peers = (0..width)
+                    .flat_map(|t| [(x, t), (t, y)])
+                    .chain(self.block_cells(block))
+                    .chain(self.constraint_peers(x, y));
+                for (px, py) in peers {
+                    if (px, py) < (x, y)
+                        && *self.field[px][py].lock().unwrap() == FieldElement::NUM(val)
+                    {
+                        return Err(PuzzleError::new(format!(
+                            "row {}, column {}: conflicting given '{}' with row {}, column {}",
+                            x,
+                            y,
+                            self.symbol(val),
+                            px,
+                            py
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    /// ジグソー数独
+    /// regionsは盤面と同じ大きさの文字の地図で，同じ文字のマスがブロックの代わりの領域になる
+    /// 領域の数や大きさが盤面の一辺と違う時や，問題がparseで読めない時はPuzzleErrorを返す
+    pub fn jigsaw(string: Vec<&str>, regions: Vec<&str>) -> PuzzleResult<Self> {
+        let mut solver = SudokuSolver::read(string)?;
+        solver.set_regions(&regions)?;
+        solver.check_givens()?;
+        Ok(solver)
+    }
+    /// 数字が一つも入っていない盤面(キラー数独など)
+    /// sizeはブロックの一辺の長さ
+    pub fn empty(size: usize) -> Self {
+        let row = ".".repeat(size * size);
+        SudokuSolver::new(vec![row.as_str(); size * size])
+    }
+
+    /// 追加ルールを有効にする
+    /// 既に確定しているマスの数字は新しいルールで関係するマスの候補から消す
+    pub fn add_constraint(&mut self, constraint: Constraint) {
+        if self.constraints.contains(&constraint) {
+            return;
+        }
+        self.constraints.push(constraint);
+        self.units.extend(constraint.units(self.size));
+        self.offsets.extend(constraint.offsets());
+        self.remove_given_suggest();
+    }
+    /// 確定しているマスの数字を関係するマスの候補から消す
+    fn remove_given_suggest(&self) {
+        for x in 0..self.width() {
+            for y in 0..self.width() {
+                let val = match *self.field[x][y].lock().unwrap() {
+                    FieldElement::NUM(val) => val,
+                    _ => continue,
+                };
+                self.remove_suggest(x, y, val);
+            }
+        }
+    }
+    /// 有効な追加ルール
+    pub fn constraints(&self) -> &[Constraint] {
+        &self.constraints
+    }
+    /// キラー数独のケージを加える
+    /// ケージ内のマスは全て異なる数字になる
+    /// 盤面の外のマスや他のケージと重なるマスを含む場合，和が作れない場合はPuzzleErrorを返す
+    pub fn add_cage(&mut self, cage: Cage) -> PuzzleResult<()> {
+        let cells = cage.cells();
+        if cells.is_empty() {
+            return Err(PuzzleError::new("empty cage"));
+        }
+        for (i, &(x, y)) in cells.iter().enumerate() {
+            if x >= self.width() || y >= self.width() {
+                return Err(PuzzleError::new(format!(
+                    "cage cell ({}, {}) is out of the field",
+                    x, y
+                )));
+            }
+            if cells[..i].contains(&(x, y))
+                || self
+                    .cages
+                    .iter()
+                    .any(|other| other.cells().contains(&(x, y)))
+            {
+                return Err(PuzzleError::new(format!(
+                    "cage cell ({}, {}) is duplicated",
+                    x, y
+                )));
+            }
+        }
+        let combinations = cage.combinations(self.width());
+        if combinations.is_empty() {
+            return Err(PuzzleError::new(format!(
+                "cage sum {} cannot be made",
+                cage.sum()
+            )));
+        }
+        self.units.push(cells.to_vec());
+        self.cages.push(cage);
+        self.combinations.push(combinations);
+        self.remove_given_suggest();
+        Ok(())
+    }
+    /// キラー数独のケージ
+    pub fn cages(&self) -> &[Cage] {
+        &self.cages
+    }
+    /// ケージの和を作れる数字の組に含まれない候補を消す
+    /// 確定した数字を全て含み，残りのマスにそれぞれ候補があって，残りの数字が全てどこかのマスに入る組だけを使う
+    fn cage_search(&self) -> bool {
+        let mut flag = false;
+        for (cage, combinations) in self.cages.iter().zip(&self.combinations) {
+            let mut placed = 0u32;
+            let mut masks = Vec::new();
+            for &(x, y) in cage.cells() {
+                match &*self.field[x][y].lock().unwrap() {
+                    FieldElement::NUM(n) => placed |= 1 << (n - 1),
+                    FieldElement::SUG(set) => {
+                        masks.push(((x, y), set.iter().fold(0u32, |acc, n| acc | 1 << (n - 1))))
+                    }
+                }
+            }
+            let mut allowed = 0u32;
+            for &comb in combinations {
+                if comb & placed != placed {
+                    continue;
+                }
+                let rest = comb & !placed;
+                let union = masks.iter().fold(0, |acc, &(_, mask)| acc | mask);
+                if masks.iter().all(|&(_, mask)| mask & rest != 0) && union & rest == rest {
+                    allowed |= rest;
+                }
+            }
+            let mut eliminations = Vec::new();
+            for ((x, y), mask) in masks {
+                if mask & !allowed == 0 {
+                    continue;
+                }
+                if let FieldElement::SUG(set) = &mut *self.field[x][y].lock().unwrap() {
+                    eliminations.extend(
+                        set.iter()
+                            .filter(|&&n| allowed >> (n - 1) & 1 == 0)
+                            .map(|&n| (x, y, n)),
+                    );
+                    set.retain(|n| allowed >> (n - 1) & 1 == 1);
+                    flag = true;
+                }
+            }
+            let digits = (1..=self.width())
+                .filter(|n| allowed >> (n - 1) & 1 == 1)
+                .collect();
+            self.record(
+                Technique::CageCombination,
+                cage.cells().to_vec(),
+                digits,
+                eliminations,
+            );
+        }
+        flag
+    }
+    /// ケージの和を確かめる
+    /// 和が大きすぎるか，埋まっていて和が違うケージがあればfalseを返す
+    fn check_cage_sums(&self) -> bool {
+        self.cages.iter().all(|cage| {
+            let mut sum = 0;
+            let mut filled = true;
+            for &(x, y) in cage.cells() {
+                match *self.field[x][y].lock().unwrap() {
+                    FieldElement::NUM(n) => sum += n,
+                    _ => filled = false,
+                }
+            }
+            if filled {
+                sum == cage.sum()
+            } else {
+                sum < cage.sum()
+            }
+        })
+    }
+    /// 追加ルールで(x, y)と同じ数字を置けないマス
+    fn constraint_peers(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        let mut peers = Vec::new();
+        for unit in &self.units {
+            if unit.contains(&(x, y)) {
+                peers.extend(unit.iter().filter(|&&cell| cell != (x, y)));
+            }
+        }
+        let width = self.width() as isize;
+        for &(dx, dy) in &self.offsets {
+            let (px, py) = (x as isize + dx, y as isize + dy);
+            if 0 <= px && px < width && 0 <= py && py < width {
+                peers.push((px as usize, py as usize));
+            }
+        }
+        peers
+    }
+
+    /// ブロックの一辺の長さ(9x9なら3)
+    pub fn size(&self) -> usize {
+        self.size
+    }
+    /// 盤面の一辺の長さ，数字の種類の数でもある(9x9なら9)
+    pub fn width(&self) -> usize {
+        self.size * self.size
+    }
+    /// 数字valを表す記号
+    pub fn symbol(&self, val: Data) -> char {
+        symbols(self.width()).unwrap()[val - 1]
+    }
+
+    // related to block
+    #[inline]
+    fn xy_to_block_ind(&self, x: usize, y: usize) -> usize {
+        self.block_ind[x][y]
+    }
+    /// ブロックに含まれるマス
+    fn block_cells(&self, block: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.blocks[block].iter().copied()
+    }
+    /// ブロックを領域の地図で置き換える
+    /// 同じ文字のマスが一つの領域になり，領域の番号は左上から現れた順につける
+    fn set_regions(&mut self, regions: &[&str]) -> PuzzleResult<()> {
+        let width = self.width();
+        if regions.len() != width {
+            return Err(PuzzleError::new(format!(
+                "region map must have {} rows, but has {}",
+                width,
+                regions.len()
+            )));
+        }
+        let mut letters = Vec::new();
+        let mut blocks: Vec<Vec<(usize, usize)>> = Vec::new();
+        let mut block_ind = vec![vec![0; width]; width];
+        for (x, row) in regions.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err(PuzzleError::new(format!(
+                    "region map row {} must have {} cells",
+                    x, width
+                )));
+            }
+            for (y, c) in row.chars().enumerate() {
+                let ind = match letters.iter().position(|&l| l == c) {
+                    Some(ind) => ind,
+                    None => {
+                        letters.push(c);
+                        blocks.push(Vec::new());
+                        letters.len() - 1
+                    }
+                };
+                blocks[ind].push((x, y));
+                block_ind[x][y] = ind;
+            }
+        }
+        if blocks.len() != width {
+            return Err(PuzzleError::new(format!(
+                "region map must have {} regions, but has {}",
+                width,
+                blocks.len()
+            )));
+        }
+        if let Some(ind) = blocks.iter().position(|block| block.len() != width) {
+            return Err(PuzzleError::new(format!(
+                "region '{}' must have {} cells",
+                letters[ind], width
+            )));
+        }
+        self.blocks = blocks;
+        self.block_ind = block_ind;
+        Ok(())
+    }
+
+    /// cacheの変更があるかどうかを返す
+    fn col_search(&self, col: usize, num: Data) -> bool {
+        let mut confirm_row = None;
+        {
+            // cacheが有効な期間
+            let cache = &mut self.col_cache[col].lock().unwrap()[num - 1];
+            if *cache {
+                // 前までの探索で確定済み
+                return false;
+            }
+            for row in 0..self.width() {
+                let elem = self.field[row][col].lock().unwrap();
+
+                match &*elem {
+                    FieldElement::NUM(n) if n == &num => {
+                        confirm_row = Some(row);
+                        *cache = true;
+                        break;
+                    }
+                    FieldElement::SUG(set) if set.len() == 1 && set.contains(&num) => {
+                        confirm_row = Some(row);
+                        *cache = true;
+                        break;
+                    }
+                    _ => (),
+                };
+            }
+            if !*cache {
+                return false;
+            }
+        }
+        // ここまで来れるのはflagがTrueかつcacheがFalseだった場合のみ
+        let confirm_row = confirm_row.unwrap();
+        let mut eliminations = Vec::new();
+        for row in 0..self.width() {
+            let mut elem = self.field[row][col].lock().unwrap();
+            match &mut *elem {
+                FieldElement::SUG(set) => {
+                    if confirm_row != row && set.remove(&num) {
+                        eliminations.push((row, col, num));
+                    }
+                }
+                _ => (),
+            }
+        }
+        self.record(
+            Technique::NakedSingle,
+            vec![(confirm_row, col)],
+            vec![num],
+            eliminations,
+        );
+        true
+    }
+    /// cacheの変更があるかどうかを返す
+    fn block_search(&self, block: usize, num: Data) -> bool {
+        let mut confirm_col_row = None;
+        {
+            // cacheが有効な期間
+            let cache = &mut self.block_cache[block].lock().unwrap()[num - 1];
+            if *cache {
+                // 前までの探索で確定済み
+                return false;
+            }
+            for (row, col) in self.block_cells(block) {
+                let elem = self.field[row][col].lock().unwrap();
+
+                match &*elem {
+                    FieldElement::NUM(n) if n == &num => {
+                        confirm_col_row = Some((col, row));
+                        *cache = true;
+                        break;
+                    }
+                    FieldElement::SUG(set) if set.len() == 1 && set.contains(&num) => {
+                        confirm_col_row = Some((col, row));
+                        *cache = true;
+                        break;
+                    }
+                    _ => (),
+                };
+            }
+            if !*cache {
+                return false;
+            }
+        }
+        // ここまで来れるのはflagがTrueかつcacheがFalseだった場合のみ
+        let confirm_col_row = confirm_col_row.unwrap();
+        let mut eliminations = Vec::new();
+        for (row, col) in self.block_cells(block) {
+            let mut elem = self.field[row][col].lock().unwrap();
+            match &mut *elem {
+                FieldElement::SUG(set) => {
+                    if confirm_col_row != (col, row) && set.remove(&num) {
+                        eliminations.push((row, col, num));
+                    }
+                }
+                _ => (),
+            }
+        }
+        self.record(
+            Technique::NakedSingle,
+            vec![(confirm_col_row.1, confirm_col_row.0)],
+            vec![num],
+            eliminations,
+        );
+        true
+    }
+    /// cacheの変更があるかどうかを返す
+    fn row_search(&self, row: usize, num: Data) -> bool {
+        let mut confirm_col = None;
+        {
+            // cacheが有効な期間
+            let cache = &mut self.row_cache[row].lock().unwrap()[num - 1];
+            if *cache {
+                // 前までの探索で確定済み
+                // panic!("{:?}", cache);
+                return false;
+            }
+            for col in 0..self.width() {
+                let elem = self.field[row][col].lock().unwrap();
+
+                match &*elem {
+                    FieldElement::NUM(n) if n == &num => {
+                        *cache = true;
+                        confirm_col = Some(col);
+                        break;
+                    }
+                    FieldElement::SUG(set) if set.len() == 1 && set.contains(&num) => {
+                        *cache = true;
+                        confirm_col = Some(col);
+                        break;
+                    }
+                    _ => (),
+                };
+            }
+            if !*cache {
+                return false;
+            }
+        }
+        // ここまで来れるのはflagがTrueかつcacheがFalseだった場合のみ
+        let confirm_col = confirm_col.unwrap();
+        let mut eliminations = Vec::new();
+        for col in 0..self.width() {
+            let mut elem = self.field[row][col].lock().unwrap();
+            match &mut *elem {
+                FieldElement::SUG(set) => {
+                    if confirm_col != col && set.remove(&num) {
+                        eliminations.push((row, col, num));
+                    }
+                }
+                _ => (),
+            }
+        }
+        self.record(
+            Technique::NakedSingle,
+            vec![(row, confirm_col)],
+            vec![num],
+            eliminations,
+        );
+        true
+    }
+
+    #[allow(dead_code)]
+    /// par version of one_line_search
+    /// but I think xxx_search is not heavy task, so I think it don't make the runtime short.
+    fn par_one_line_search(&self, num: Data) -> bool {
+        let mut flag = false;
+        // row
+        flag |= (0..self.width())
+            .into_par_iter()
+            .map(|row| self.row_search(row, num))
+            .reduce(|| false, |a, b| a | b);
+        // col
+        flag |= (0..self.width())
+            .into_par_iter()
+            .map(|col| self.col_search(col, num))
+            .reduce(|| false, |a, b| a | b);
+        //block
+        flag |= (0..self.width())
+            .into_par_iter()
+            .map(|block| self.block_search(block, num))
+            .reduce(|| false, |a, b| a | b);
+        flag
+    }
+    /// normal version of one_line_search
+    fn one_line_search(&self, num: Data) -> bool {
+        let mut flag = false;
+        // row
+        flag |= (0..self.width())
+            .into_iter()
+            .map(|row| self.row_search(row, num))
+            .fold(false, |a, b| a | b);
+        // col
+        flag |= (0..self.width())
+            .into_iter()
+            .map(|col| self.col_search(col, num))
+            .fold(false, |a, b| a | b);
+        //block
+        flag |= (0..self.width())
+            .into_iter()
+            .map(|block| self.block_search(block, num))
+            .fold(false, |a, b| a | b);
+        flag
+    }
+    /// ブロックの中でnumが入る候補が一つの行(列)に並んでいれば，その行(列)の他のブロックから消す
+    fn line_confirmed(&self, num: Data) -> bool {
+        // 変化するものが一つでもあるかどうか
+        let mut flag = false;
+        // blockごとに回す
+        for block in 0..self.width() {
+            let cells: Vec<(usize, usize)> = self
+                .block_cells(block)
+                .filter(|&(x, y)| {
+                    matches!(&*self.field[x][y].lock().unwrap(), FieldElement::SUG(set) if set.contains(&num))
+                })
+                .collect();
+            // 一つしかなければブロックのhidden singleなので，hidden_singleに任せる
+            if cells.len() < 2 {
+                continue;
+            }
+            let (row, col) = cells[0];
+            let mut eliminations = Vec::new();
+            if cells.iter().all(|&(x, _)| x == row) {
+                for col in 0..self.width() {
+                    if self.xy_to_block_ind(row, col) == block {
+                        // 同一ブロックなので消さない
+                        continue;
+                    }
+                    let mut elem = self.field[row][col].lock().unwrap();
+                    if let FieldElement::SUG(set) = &mut *elem {
+                        if set.remove(&num) {
+                            eliminations.push((row, col, num));
+                        }
+                    }
+                }
+            }
+            if cells.iter().all(|&(_, y)| y == col) {
+                for row in 0..self.width() {
+                    if self.xy_to_block_ind(row, col) == block {
+                        // 同一ブロックなので消さない
+                        continue;
+                    }
+                    let mut elem = self.field[row][col].lock().unwrap();
+                    if let FieldElement::SUG(set) = &mut *elem {
+                        if set.remove(&num) {
+                            eliminations.push((row, col, num));
+                        }
+                    }
+                }
+            }
+            flag |= !eliminations.is_empty();
+            self.record(Technique::LockedCandidates, cells, vec![num], eliminations);
+        }
+        flag
+    }
+    #[allow(dead_code)]
+    /// 全ての数字でline_confirmedを行う
+    fn all_line_confirmed(&self) -> bool {
+        (1..=self.width()).fold(false, |flag, num| self.line_confirmed(num) | flag)
+    }
+    fn pseudo_confirmed(&self, num: usize) -> bool {
+        let mut flag = false;
+        for block in 0..self.width() {
+            flag |= self.block_pseudo_confirmed(num, block);
+        }
+        flag
+    }
+    fn block_pseudo_confirmed(&self, num: usize, block: usize) -> bool {
+        // todo(ちょいむずい...?)
+        // blockのx,y座標と，そのマスに含まれる数字を記録する
+        let mut set_in_num_xy = Vec::new();
+
+        for (x, y) in self.block_cells(block) {
+            let elem = self.field[x][y].lock().unwrap();
+            match &*elem {
+                FieldElement::NUM(n) if n == &num => {
+                    // numが確定済みの値なのでスルー
+                    return false;
+                }
+                FieldElement::SUG(set) if set.contains(&num) => {
+                    // blockのx,y座標と，そのマスに含まれる数字を記録する
+                    set_in_num_xy.push((x, y));
+                }
+                _ => (),
+            }
+        }
+
+        // 計算量削減のためset_in_num_xyの上限を設けておく。
+        // 多分人の手でも3くらいがいいとこ？
+        const LIMIT: usize = 4;
+        // bit演算のための補助関数
+        // https://qiita.com/pikohideaki/items/cf0f1d4dd7fb57c7aa5b
+        fn next_combination(bit: i32) -> usize {
+            let x = bit & -bit;
+            let y = x + bit;
+            return (y | (((bit & !y) / x) >> 1)) as usize;
+        }
+        // 変更があるかどうかのフラグ
+        let mut flag = false;
+
+        // n個のマスにn個の種類の数字のみしか入らないので他のマスにある数字を削除
+        // kこの部分集合の中にk個あれば他のマスにこれらの数字はは入れない
+        // kはlimitを上限にする
+        for (k, vec) in (2..LIMIT).map(|k| {
+            let mut ret = Vec::new();
+            let mut bit = (1 << k) - 1;
+            while bit < (1 << set_in_num_xy.len()) {
+                ret.push(bit);
+                bit = next_combination(bit as i32);
+            }
+            (k, ret)
+        }) {
+            // kこの部分集合
+            'BIT: for bit in vec {
+                // println!("k:{}, {:b}, {:?}", k, bit, set_in_num_xy);
+                let mut num_set = BTreeSet::new();
+                // kこの部分集合に含まれるものの数を数える
+                for i in 0..set_in_num_xy.len() {
+                    // set_in_num_xyからkこのマスを選択する
+                    if (bit >> i) & 1 == 1 {
+                        let (x, y) = set_in_num_xy[i];
+                        let elem = self.field[x][y].lock().unwrap();
+                        match &*elem {
+                            FieldElement::SUG(set) => {
+                                num_set = &num_set | set;
+                            }
+                            FieldElement::NUM(_) => {
+                                panic!("unreachable");
+                            }
+                        }
+                    }
+                }
+                if num_set.len() != k {
+                    continue 'BIT;
+                }
+                // ここまできたということはcontinue 'BITにならなかった
+                // 現在の部分集合が条件を満たしている
+                let mut eliminations = Vec::new();
+                for i in 0..set_in_num_xy.len() {
+                    // set_in_num_xyからkこ以外のマスを選択する
+                    if (bit >> i) & 1 == 0 {
+                        let (x, y) = set_in_num_xy[i];
+                        let mut elem = self.field[x][y].lock().unwrap();
+                        match &mut *elem {
+                            FieldElement::SUG(set) => {
+                                let dif = &*set - &num_set;
+                                // println!("dif: {:?},set: {:?}", dif, set);
+                                if dif.len() != set.len() {
+                                    // 変更があったのでflagをtrueにする
+                                    flag = true;
+                                    eliminations
+                                        .extend((&*set & &num_set).iter().map(|&n| (x, y, n)));
+                                    *set = dif;
+                                }
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+                let cells = (0..set_in_num_xy.len())
+                    .filter(|i| (bit >> i) & 1 == 1)
+                    .map(|i| set_in_num_xy[i])
+                    .collect();
+                self.record(
+                    Technique::NakedSubset,
+                    cells,
+                    num_set.into_iter().collect(),
+                    eliminations,
+                );
+            }
+        }
+        // n個のマスにのみn種類の数字があるためその他の種類の数字を削除(todo)
+        flag
+    }
+
+    /// 私が数独を解くのに使うアルゴリズムを実装
+    /// これで解けないものはかなり技巧的な技術が必要になる
+    pub fn num_search(&mut self) -> bool {
+        let flag = AtomicBool::new(false);
+        let suudoku_nums: Vec<Data> = (1..=self.width()).collect();
+        // 1からn^2までの数字をそれぞれ探索する
+        suudoku_nums.iter().for_each(|&x| {
+            let mut search_flag = false;
+            // row, col, blockで一通りにきまる
+            search_flag |= self.one_line_search(x);
+
+            // row, col, blockでxが入るマスが一つしかない
+            if self.is_enabled(Technique::HiddenSingle) {
+                search_flag |= self.hidden_single(x);
+            }
+
+            // 同一ブロックで2つ以上候補があるがそれが1列に並んでいる(その列に入ることが確定するので他のブロックから削除)
+            if self.is_enabled(Technique::LockedCandidates) {
+                search_flag |= self.line_confirmed(x);
+            }
+
+            // 同一ブロックでn個がnマスに入る(1,2が入るマスが2つしかない場合他の数字は入れられない)
+            if self.is_enabled(Technique::NakedSubset) {
+                search_flag |= self.pseudo_confirmed(x);
+            }
+
+            if search_flag {
+                flag.store(true, Ordering::Relaxed);
+            }
+        });
+        // ケージの和を作れない候補を消す
+        if self.is_enabled(Technique::CageCombination) && self.cage_search() {
+            flag.store(true, Ordering::Relaxed);
+        }
+        if self.confirm_singles() {
+            flag.store(true, Ordering::Relaxed);
+        }
+        flag.load(Ordering::Relaxed)
+    }
+    /// SUGの要素数が1つのマスを確定し，関係するマスの候補から消す
+    fn confirm_singles(&self) -> bool {
+        let mut flag = false;
+        for x in 0..self.width() {
+            for y in 0..self.width() {
+                let is_done = self.field[x][y].lock().unwrap().suggest_confirmed();
+                if is_done != 0 {
+                    let eliminations = self.remove_suggest(x, y, is_done);
+                    // 確定したこと自体が進展なので，消す候補がなくても記録する
+                    self.log.lock().unwrap().push(Step {
+                        technique: Technique::NakedSingle,
+                        cells: vec![(x, y)],
+                        digits: vec![is_done],
+                        eliminations,
+                    });
+                    flag = true;
+                }
+            }
+        }
+        flag
+    }
+    /// 行，列，ブロック(と追加ルールの領域)の中でnumの入るマスが一つしかなければ，そのマスをnumにする
+    fn hidden_single(&self, num: Data) -> bool {
+        let mut flag = false;
+        for unit in self.houses() {
+            let mut candidates = Vec::new();
+            for &(x, y) in &unit {
+                match &*self.field[x][y].lock().unwrap() {
+                    FieldElement::NUM(n) if *n == num => {
+                        candidates.clear();
+                        break;
+                    }
+                    FieldElement::SUG(set) if set.contains(&num) => candidates.push((x, y)),
+                    _ => (),
+                }
+            }
+            if candidates.len() != 1 {
+                continue;
+            }
+            let (x, y) = candidates[0];
+            if let FieldElement::SUG(set) = &mut *self.field[x][y].lock().unwrap() {
+                if set.len() == 1 {
+                    continue;
+                }
+                let eliminations = set
+                    .iter()
+                    .filter(|&&n| n != num)
+                    .map(|&n| (x, y, n))
+                    .collect();
+                *set = BTreeSet::from([num]);
+                self.record(
+                    Technique::HiddenSingle,
+                    vec![(x, y)],
+                    vec![num],
+                    eliminations,
+                );
+                flag = true;
+            }
+        }
+        flag
+    }
+    /// 全ての数字が一つずつ入る領域(行，列，ブロックと追加ルールの領域)
+    /// ケージのように全ての数字が入るとは限らない領域は含めない
+    fn houses(&self) -> Vec<Vec<(usize, usize)>> {
+        let width = self.width();
+        let rows = (0..width).map(|x| (0..width).map(|y| (x, y)).collect());
+        let cols = (0..width).map(|y| (0..width).map(|x| (x, y)).collect());
+        let units = self
+            .units
+            .iter()
+            .filter(|unit| unit.len() == width)
+            .cloned();
+        rows.chain(cols)
+            .chain(self.blocks.iter().cloned())
+            .chain(units)
+            .collect()
+    }
+    /// (x, y)の数字valを関係するマスの候補から消し，消した候補を返す
+    fn remove_suggest(&self, x: usize, y: usize, val: usize) -> Vec<(usize, usize, Data)> {
+        let mut eliminations = Vec::new();
+        let blockind = self.xy_to_block_ind(x, y);
+        let peers = (0..self.width())
+            .flat_map(|t| [(x, t), (t, y)])
+            .chain(self.block_cells(blockind))
+            .chain(self.constraint_peers(x, y));
+        for (px, py) in peers {
+            if let FieldElement::SUG(set) = &mut *self.field[px][py].lock().unwrap() {
+                // 空になった場合は矛盾しているが，has_contradictionで検出する
+                if set.remove(&val) {
+                    eliminations.push((px, py, val));
+                }
+            }
+        }
+        eliminations
+    }
+
+    /// suggestが一番小さなマスを用意する
+    /// 空いているマスがなければPuzzleErrorを返す
+    fn pickup_elem(&self) -> PuzzleResult<std::vec::IntoIter<(Data, usize, usize)>> {
+        let mut min = None;
+        for (x, vec) in self.field.iter().enumerate() {
+            for (y, val) in vec.iter().enumerate() {
+                if let FieldElement::SUG(set) = &*val.lock().unwrap() {
+                    if min.is_none_or(|(len, _, _)| set.len() < len) {
+                        min = Some((set.len(), x, y));
+                    }
+                }
+            }
+        }
+        let (_, min_x, min_y) = min.ok_or_else(|| PuzzleError::new("no vacant cell"))?;
+        let min_elem = if let FieldElement::SUG(ref set) = *self.field[min_x][min_y].lock().unwrap()
+        {
+            set.iter()
+                .map(|val| (*val, min_x, min_y))
+                .collect::<Vec<_>>()
+                .into_iter()
+        } else {
+            return Err(PuzzleError::new("software buf: it cannot reach here."));
+        };
+        Ok(min_elem)
+    }
+
+    /// x,y座標で指定された場所に確定の値valを入れる
+    fn set(&mut self, (val, x, y): (Data, usize, usize)) {
+        self.field[x][y] = Arc::new(Mutex::new(FieldElement::NUM(val)));
+        self.remove_suggest(x, y, val);
+    }
+
+    fn is_vacant(&self) -> bool {
+        for vec in &self.field {
+            for val in vec {
+                if let FieldElement::NUM(_) = *val.lock().unwrap() {
+                } else {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// num_searchだけでうまく行かないような難しい数独は仮決めして行うしかないのでdfsを回す
+    /// 仮決めする値は最もsuggestが小さいようなマスを発見してイテレータで回す
+    fn dfs(&mut self) -> PuzzleResult<()> {
+        let mut elems = self.pickup_elem()?.into_iter();
+        loop {
+            // 元の状態を保持，失敗したときに戻れるようにしておく
+            let clone = self.clone();
+
+            // 候補になるelemを選出
+            let elem = match elems.next() {
+                Some(x) => x,
+                _ => break,
+            };
+            self.set(elem);
+            self.record(
+                Technique::Guess,
+                vec![(elem.1, elem.2)],
+                vec![elem.0],
+                Vec::new(),
+            );
+
+            // 探索実行
+            while self.num_search() {}
+            if self.has_contradiction() {
+                // 前提が間違っていた
+                *self = clone;
+            } else if self.is_vacant() {
+                // dfsの中で仮極めが必要
+                match self.dfs() {
+                    Ok(x) => return Ok(x),
+                    Err(_) => {
+                        // 失敗したので前提が間違っていた
+                        *self = clone;
+                    }
+                }
+            } else if self.has_finished()? {
+                return Ok(());
+            } else {
+                *self = clone;
+            }
+        }
+        Err(PuzzleError {
+            error: "not found path".to_string(),
+        })
+    }
+    /// 推論を記録する，仮置き以外で消した候補がなければ記録しない
+    fn record(
+        &self,
+        technique: Technique,
+        cells: Vec<(usize, usize)>,
+        digits: Vec<Data>,
+        eliminations: Vec<(usize, usize, Data)>,
+    ) {
+        if eliminations.is_empty() && technique != Technique::Guess {
+            return;
+        }
+        self.log.lock().unwrap().push(Step {
+            technique,
+            cells,
+            digits,
+            eliminations,
+        });
+    }
+    /// これまでの推論の記録
+    pub fn steps(&self) -> Vec<Step> {
+        self.log.lock().unwrap().clone()
+    }
+    pub fn clear_steps(&self) {
+        self.log.lock().unwrap().clear();
+    }
+    /// テクニックを使うかどうかを切り替える(初期状態では全て使う)
+    /// 候補が一つになったマスの確定と仮置きは常に使う
+    pub fn set_technique(&mut self, technique: Technique, enabled: bool) {
+        self.disabled.retain(|&t| t != technique);
+        if !enabled {
+            self.disabled.push(technique);
+        }
+    }
+    pub fn is_enabled(&self, technique: Technique) -> bool {
+        matches!(technique, Technique::NakedSingle | Technique::Guess)
+            || !self.disabled.contains(&technique)
+    }
+    /// 一つのテクニックを全ての数字で試す，変化があればtrueを返す
+    /// 使わない設定のテクニックは何もしない
+    fn apply_technique(&self, technique: Technique) -> bool {
+        if !self.is_enabled(technique) {
+            return false;
+        }
+        let nums = 1..=self.width();
+        match technique {
+            Technique::NakedSingle => {
+                let flag = nums.fold(false, |flag, num| self.one_line_search(num) | flag);
+                self.confirm_singles() | flag
+            }
+            Technique::HiddenSingle => nums.fold(false, |flag, num| self.hidden_single(num) | flag),
+            Technique::LockedCandidates => {
+                nums.fold(false, |flag, num| self.line_confirmed(num) | flag)
+            }
+            Technique::NakedSubset => {
+                nums.fold(false, |flag, num| self.pseudo_confirmed(num) | flag)
+            }
+            Technique::CageCombination => self.cage_search(),
+            Technique::HiddenSubset => self.hidden_subset(),
+            Technique::XWing => self.fish(2),
+            Technique::Swordfish => self.fish(3),
+            Technique::Jellyfish => self.fish(4),
+            Technique::XYWing => self.xy_wing(),
+            Technique::XYZWing => self.xyz_wing(),
+            Technique::SimpleColoring => self.simple_coloring(),
+            Technique::Guess => false,
+        }
+    }
+    /// num_searchで進まない時に上級のテクニックを簡単なものから試す
+    /// どれかで変化があればtrueを返す
+    pub fn advanced_search(&mut self) -> bool {
+        let flag = Technique::ADVANCED
+            .iter()
+            .any(|&technique| self.apply_technique(technique));
+        if flag {
+            self.confirm_singles();
+        }
+        flag
+    }
+    /// 簡単なテクニックから順に試し，変化があれば最初から繰り返す
+    /// 仮置きをせずに解けたらtrueを返す
+    pub fn logical_solve(&mut self) -> bool {
+        while self.is_vacant() && !self.has_contradiction() {
+            if !Technique::LOGICAL
+                .iter()
+                .any(|&technique| self.apply_technique(technique))
+            {
+                return false;
+            }
+        }
+        !self.is_vacant() && matches!(self.has_finished(), Ok(true))
+    }
+    /// 難易度
+    /// 簡単なテクニックから順に使って解き，必要だった最も難しいテクニックで決める
+    /// 論理で解けなければ仮置きが必要なのでExtremeになる．解がなければPuzzleErrorを返す
+    pub fn difficulty(&self) -> PuzzleResult<Difficulty> {
+        let mut solver = self.clone();
+        solver.clear_steps();
+        if !solver.logical_solve() {
+            solver.run()?;
+        }
+        Ok(technique::grade(&solver.steps()))
+    }
+
+    /// 候補がなくなったマスや，行，列，ブロック，追加ルールで重複した数字，和の合わないケージがあればtrueを返す
+    fn has_contradiction(&self) -> bool {
+        let len = self.width();
+        let mut row_flags = vec![vec![false; len]; len];
+        let mut col_flags = vec![vec![false; len]; len];
+        let mut block_flags = vec![vec![false; len]; len];
+        for x in 0..len {
+            for y in 0..len {
+                match &*self.field[x][y].lock().unwrap() {
+                    FieldElement::SUG(set) if set.is_empty() => return true,
+                    FieldElement::SUG(_) => (),
+                    FieldElement::NUM(n) => {
+                        let n = *n - 1;
+                        for flags in [
+                            &mut row_flags[x],
+                            &mut col_flags[y],
+                            &mut block_flags[self.xy_to_block_ind(x, y)],
+                        ] {
+                            if flags[n] {
+                                return true;
+                            }
+                            flags[n] = true;
+                        }
+                        for (px, py) in self.constraint_peers(x, y) {
+                            if *self.field[px][py].lock().unwrap() == FieldElement::NUM(n + 1) {
+                                return true;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        !self.check_cage_sums()
+    }
+
+    /// 解の数をlimit個まで数える
+    /// 0なら解なし，1なら唯一解，limitならlimit個以上の解がある
+    pub fn count_solutions(&self, limit: usize) -> usize {
+        let mut count = 0;
+        if limit > 0 {
+            self.clone().solutions_dfs(limit, &mut count, &mut |_| {});
+        }
+        count
+    }
+    /// 唯一解を持つかどうか
+    pub fn has_unique_solution(&self) -> bool {
+        self.count_solutions(2) == 1
+    }
+    /// 全ての解を列挙する
+    pub fn all_solutions(&self) -> Vec<SudokuSolver> {
+        let mut solutions = Vec::new();
+        let mut count = 0;
+        self.clone()
+            .solutions_dfs(usize::MAX, &mut count, &mut |solver| {
+                solutions.push(solver.clone())
+            });
+        solutions
+    }
+    /// num_searchで埋められるだけ埋めてから，候補が最も少ないマスで分岐する
+    /// 解が見つかるたびにfoundを呼び，countがlimitに達したら打ち切る
+    fn solutions_dfs(
+        &mut self,
+        limit: usize,
+        count: &mut usize,
+        found: &mut impl FnMut(&SudokuSolver),
+    ) {
+        while self.num_search() {}
+        if self.has_contradiction() {
+            return;
+        }
+        if !self.is_vacant() {
+            if let Ok(true) = self.has_finished() {
+                *count += 1;
+                found(self);
+            }
+            return;
+        }
+        let elems = match self.pickup_elem() {
+            Ok(elems) => elems,
+            Err(_) => return,
+        };
+        for elem in elems {
+            if *count >= limit {
+                return;
+            }
+            let mut next = self.clone();
+            next.set(elem);
+            next.solutions_dfs(limit, count, found);
+        }
+    }
+
+    fn check_block(&self) -> PuzzleResult<bool> {
+        let flag = (0..self.width())
+            .into_par_iter()
+            .map(|block| {
+                let mut flags = vec![false; self.width()];
+                for (x, y) in self.block_cells(block) {
+                    if let FieldElement::NUM(ref n) = *self.field[x][y].lock().unwrap() {
+                        let n = *n - 1;
+                        if flags[n] {
+                            return Err(PuzzleError::new("check_block"));
+                        }
+                        flags[n] = true;
+                    } else {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            })
+            .reduce(|| Ok(true), |a, b| Ok(a? & b?))?;
+        Ok(flag)
+    }
+    fn check_row(&self) -> PuzzleResult<bool> {
+        let flag = (0..self.width())
+            .into_par_iter()
+            .map(|row| {
+                let mut flags = vec![false; self.width()];
+                for col in 0..self.width() {
+                    if let FieldElement::NUM(ref n) = *self.field[row][col].lock().unwrap() {
+                        let n = *n - 1;
+                        if flags[n] {
+                            return Err(PuzzleError::new("check_row"));
+                        }
+                        flags[n] = true;
+                    } else {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            })
+            .reduce(|| Ok(true), |a, b| Ok(a? & b?))?;
+        Ok(flag)
+    }
+    fn check_column(&self) -> PuzzleResult<bool> {
+        let flag = (0..self.width())
+            .into_par_iter()
+            .map(|col| {
+                let mut flags = vec![false; self.width()];
+                for row in 0..self.width() {
+                    if let FieldElement::NUM(ref n) = *self.field[row][col].lock().unwrap() {
+                        let n = *n - 1;
+                        if flags[n] {
+                            return Err(PuzzleError::new("check_row"));
+                        }
+                        flags[n] = true;
+                    } else {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            })
+            .reduce(|| Ok(true), |a, b| Ok(a? & b?))?;
+        Ok(flag)
+    }
+    /// 追加ルールとケージの確認
+    fn check_constraints(&self) -> PuzzleResult<bool> {
+        if !self.check_cage_sums() {
+            return Err(PuzzleError::new("check_cage"));
+        }
+        let mut finished = true;
+        for x in 0..self.width() {
+            for y in 0..self.width() {
+                let val = match *self.field[x][y].lock().unwrap() {
+                    FieldElement::NUM(val) => val,
+                    _ => {
+                        finished = false;
+                        continue;
+                    }
+                };
+                for (px, py) in self.constraint_peers(x, y) {
+                    if *self.field[px][py].lock().unwrap() == FieldElement::NUM(val) {
+                        return Err(PuzzleError::new("check_constraints"));
+                    }
+                }
+            }
+        }
+        Ok(finished)
+    }
+}
+
+impl Solver for SudokuSolver {
+    // fn run(&mut self) -> Result<(), PuzzleError> {
+    //     Ok(())
+    // }
+    // fn run(&mut self) -> PuzzleResult<()>;
+    fn has_finished(&self) -> PuzzleResult<bool> {
+        Ok(self.check_row()?
+            && self.check_column()?
+            && self.check_block()?
+            && self.check_constraints()?)
+    }
+    /// 候補がなくなったり数字が重なったりしていればPuzzleErrorを返す
+    fn search(&mut self) -> PuzzleResult<()> {
+        if self.has_contradiction() {
+            return Err(PuzzleError::new("contradiction"));
+        }
+        if self.num_search() || self.advanced_search() {
+            Ok(())
+        } else {
+            self.dfs()
+            // Ok(())
+        }
+    }
+}
+/// 確定したマスは数字，空きマスは候補を{1,6}のように表示する
+impl Display for FieldElement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldElement::NUM(x) => write!(f, "{}", x),
+            FieldElement::SUG(set) => {
+                let nums: Vec<String> = set.iter().map(|n| n.to_string()).collect();
+                write!(f, "{{{}}}", nums.join(","))
+            }
+        }
+    }
+}
+
+impl SudokuSolver {
+    /// 候補をマスごとに小さなn x nの格子で表示する文字列
+    /// format!("{:#}", solver)と同じ
+    pub fn pencil_marks(&self) -> String {
+        format!("{:#}", self)
+    }
+    /// (x, y)のマスを表示した時のline行目
+    /// 候補表示でなければ一文字で空きマスは'.'，候補表示ならn文字で，入らない候補は'.'，確定した数字は中央に置く
+    fn render_cell(&self, x: usize, y: usize, line: usize, pencil: bool) -> String {
+        let size = self.size();
+        match &*self.field[x][y].lock().unwrap() {
+            FieldElement::NUM(val) if pencil => (0..size)
+                .map(|k| {
+                    if line == size / 2 && k == size / 2 {
+                        self.symbol(*val)
+                    } else {
+                        ' '
+                    }
+                })
+                .collect(),
+            FieldElement::NUM(val) => self.symbol(*val).to_string(),
+            FieldElement::SUG(set) if pencil => (0..size)
+                .map(|k| {
+                    let num = line * size + k + 1;
+                    if set.contains(&num) {
+                        self.symbol(num)
+                    } else {
+                        '.'
+                    }
+                })
+                .collect(),
+            FieldElement::SUG(_) => ".".to_string(),
+        }
+    }
+}
+
+/// ブロックの境界を線で区切った盤面
+/// {:#}で表示すると空きマスの候補も表示する
+impl Display for SudokuSolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (size, width) = (self.size(), self.width());
+        let pencil = f.alternate();
+        // 一マスの幅と高さ
+        let cell = if pencil { size } else { 1 };
+        let inner = size * cell + size + 1;
+        let border = format!("+{}\n", format!("{}+", "-".repeat(inner)).repeat(size));
+        let spacer = format!("|{}\n", format!("{}|", " ".repeat(inner)).repeat(size));
+        for x in 0..width {
+            if x % size == 0 {
+                write!(f, "{}", border)?;
+            } else if pencil {
+                write!(f, "{}", spacer)?;
+            }
+            for line in 0..cell {
+                write!(f, "|")?;
+                for by in 0..size {
+                    let cells: Vec<String> = (0..size)
+                        .map(|k| self.render_cell(x, by * size + k, line, pencil))
+                        .collect();
+                    write!(f, " {} |", cells.join(" "))?;
+                }
+                writeln!(f)?;
+            }
+        }
+        write!(f, "{}", border)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn easy_test() {
+        let mut sudoku = SudokuSolver::new(vec![
+            "902304501",
+            "000208000",
+            "758109423",
+            "604005792",
+            "000407000",
+            "217900845",
+            "106703904",
+            "000501000",
+            "509602317",
+        ]);
+        while !sudoku.has_finished().unwrap() {
+            sudoku.search().unwrap();
+        }
+
+        let ans = SudokuSolver::new(vec![
+            "962374581",
+            "341258679",
+            "758169423",
+            "634815792",
+            "895427136",
+            "217936845",
+            "126783954",
+            "473591268",
+            "589642317",
+        ]);
+        assert!(sudoku == ans);
+    }
+    #[test]
+    fn hard_test() {
+        let mut sudoku = SudokuSolver::new(vec![
+            "000007000",
+            "020008040",
+            "103000000",
+            "000150000",
+            "000300070",
+            "000000089",
+            "090000000",
+            "080002000",
+            "000600100",
+        ]);
+        sudoku.run().unwrap();
+
+        assert!(sudoku.has_finished().unwrap());
+    }
+    #[test]
+    fn size_test() {
+        let mut sudoku = SudokuSolver::new(vec!["...2", "3..1", ".3..", "2.13"]);
+        assert_eq!(sudoku.size(), 2);
+        assert_eq!(sudoku.width(), 4);
+        sudoku.run().unwrap();
+        assert!(sudoku.has_finished().unwrap());
+        assert_eq!(
+            sudoku,
+            SudokuSolver::new(vec!["4132", "3241", "1324", "2413"])
+        );
+
+        // 16x16は16進数の0からFを使う
+        let mut sudoku = SudokuSolver::new(vec![
+            "7.B14C.93.D8.EF0",
+            "4C59..D.A.F.7.B.",
+            "36D.A..0.2..4C59",
+            "....72..4.5936.8",
+            "2B1.C.9..D8.E..7",
+            "C.936D..EF0.2.1.",
+            "6..A....2B1.C...",
+            ".F..2B1.C...6.8.",
+            "B14C.9.6D8.E....",
+            ".9..D.AEF.7.B14C",
+            "...EF0.2.1.C...6",
+            "F0.....C5936D...",
+            ".4..9....AEF.72.",
+            "..6...EF.72B..C.",
+            "8...0..B1.C5..6.",
+            "0.2B14C5....8AE.",
+        ]);
+        assert_eq!(sudoku.size(), 4);
+        assert_eq!(sudoku.symbol(1), '0');
+        assert_eq!(sudoku.symbol(16), 'F');
+        sudoku.run().unwrap();
+        assert!(sudoku.has_finished().unwrap());
+
+        // 25x25はAからYを使う
+        let mut sudoku = SudokuSolver::new(vec![
+            "..A.TBL....QS..GID....Y..",
+            "B.U.F.QS.W.IDO..CY.M.R..T",
+            "HQSJW...OX..YKMPR.....U.F",
+            ".....V..K..RAN.BL.....SJW",
+            ".C..MP.AN.BL.EFHQSJW...OX",
+            ".AN.B.U.FHQ.JW.IDOX.CY.M.",
+            "...FH.S....D....YKM..A.T.",
+            "QSJWG.DOX...K.PRANT.LU.F.",
+            "I.....YK.P....BLUEF.Q.JW.",
+            ".YK.....TBLUE.HQSJ.GIDOXV",
+            "AN..LUE.H...WG..O.VCY.MP.",
+            ".E..QS.WG.DOX.C.K.PR..TBL",
+            ".JW..DO.V.Y.MPR.N..L.E.HQ",
+            ".......MP..NTBL.E.H......",
+            "Y.M.R.NTB.UE..QS.WG.D..V.",
+            "...LU..H.....IDOXV.YKM..A",
+            "E.HQS....D.XVCYK.P.ANT..U",
+            "..G.D...CY.M.R...B..EFH..",
+            ".X..YKM.R.NTBLUEF...J..I.",
+            "KM....TBL.E...S.WGIDOXV.Y",
+            "TBLUE.HQ..WG..OXV..K..R..",
+            ".HQ..WG.D..VC.KMPR.NTBLUE",
+            "W..DOX..YKM.R...B...FHQSJ",
+            "XVCYK.P..NTBL.EFHQ..WGIDO",
+            "M..A.T.........WG.D.XVC.K",
+        ]);
+        assert_eq!(sudoku.width(), 25);
+        sudoku.run().unwrap();
+        assert!(sudoku.has_finished().unwrap());
+    }
+    #[test]
+    fn constraint_test() {
+        let ans = SudokuSolver::new(vec![
+            "962374581",
+            "341258679",
+            "758169423",
+            "634815792",
+            "895427136",
+            "217936845",
+            "126783954",
+            "473591268",
+            "589642317",
+        ]);
+        assert!(ans.has_finished().unwrap());
+        // 対角線に8が2つある
+        let mut diagonal = ans.clone();
+        diagonal.add_constraint(Constraint::Diagonal);
+        assert!(diagonal.has_finished().is_err());
+        // (5,0)と(6,1)の2が斜めに隣り合う
+        let mut king = ans.clone();
+        king.add_constraint(Constraint::AntiKing);
+        assert!(king.has_finished().is_err());
+
+        // 空の盤面から追加ルールを満たす解を作る
+        for constraint in [
+            Constraint::Diagonal,
+            Constraint::Windoku,
+            Constraint::AntiKing,
+            Constraint::AntiKnight,
+        ] {
+            let mut sudoku = SudokuSolver::new(vec!["000000000"; 9]);
+            sudoku.add_constraint(constraint);
+            assert_eq!(sudoku.constraints(), &[constraint]);
+            sudoku.run().unwrap();
+            assert!(sudoku.has_finished().unwrap());
+        }
+
+        // 与えられた数字の候補の削除
+        let mut sudoku = SudokuSolver::new(vec![
+            "100000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+        ]);
+        sudoku.add_constraint(Constraint::Diagonal);
+        sudoku.add_constraint(Constraint::AntiKnight);
+        let has = |x: usize, y: usize| match &*sudoku.field[x][y].lock().unwrap() {
+            FieldElement::SUG(set) => set.contains(&1),
+            _ => unreachable!(),
+        };
+        assert!(!has(4, 4));
+        assert!(!has(1, 2));
+        assert!(!has(2, 1));
+        assert!(has(3, 5));
+    }
+    #[test]
+    fn killer_test() {
+        let ans = [
+            "962374581",
+            "341258679",
+            "758169423",
+            "634815792",
+            "895427136",
+            "217936845",
+            "126783954",
+            "473591268",
+            "589642317",
+        ];
+        let val = |x: usize, y: usize| ans[x].as_bytes()[y] as usize - '0' as usize;
+        // 横に2マスずつのケージと，右端の列は縦に2マスずつのケージ
+        let mut cages = Vec::new();
+        for x in 0..9 {
+            for y in (0..8).step_by(2) {
+                cages.push(Cage::new(
+                    val(x, y) + val(x, y + 1),
+                    vec![(x, y), (x, y + 1)],
+                ));
+            }
+        }
+        for x in (0..8).step_by(2) {
+            cages.push(Cage::new(
+                val(x, 8) + val(x + 1, 8),
+                vec![(x, 8), (x + 1, 8)],
+            ));
+        }
+        cages.push(Cage::new(val(8, 8), vec![(8, 8)]));
+
+        // 数字が一つもない状態から解く
+        let mut sudoku = SudokuSolver::empty(3);
+        for cage in cages.clone() {
+            sudoku.add_cage(cage).unwrap();
+        }
+        assert_eq!(sudoku.cages().len(), 41);
+        sudoku.run().unwrap();
+        assert!(sudoku.has_finished().unwrap());
+
+        // 和の合わない解は認めない
+        let mut wrong = SudokuSolver::new(ans.to_vec());
+        wrong.add_cage(Cage::new(10, vec![(0, 0), (0, 1)])).unwrap();
+        assert!(wrong.has_finished().is_err());
+
+        // 候補の絞り込み
+        let mut sudoku = SudokuSolver::empty(3);
+        sudoku.add_cage(Cage::new(3, vec![(0, 0), (0, 1)])).unwrap();
+        sudoku
+            .add_cage(Cage::new(24, vec![(1, 0), (1, 1), (1, 2)]))
+            .unwrap();
+        sudoku.num_search();
+        for (x, y, expected) in [(0, 0, vec![1, 2]), (1, 2, vec![7, 8, 9])] {
+            match &*sudoku.field[x][y].lock().unwrap() {
+                FieldElement::SUG(set) => {
+                    assert_eq!(set.iter().copied().collect::<Vec<_>>(), expected)
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        let mut sudoku = SudokuSolver::empty(3);
+        assert!(sudoku.add_cage(Cage::new(3, vec![(0, 0), (0, 9)])).is_err());
+        assert!(sudoku.add_cage(Cage::new(3, vec![(0, 0), (0, 0)])).is_err());
+        assert!(sudoku.add_cage(Cage::new(2, vec![(0, 0), (0, 1)])).is_err());
+        sudoku.add_cage(Cage::new(3, vec![(0, 0), (0, 1)])).unwrap();
+        assert!(sudoku.add_cage(Cage::new(3, vec![(0, 1), (0, 2)])).is_err());
+    }
+    #[test]
+    fn jigsaw_test() {
+        let regions = vec![
+            "AAABBBCCC",
+            "AAABBBCCC",
+            "ADBABBCCC",
+            "ADDEEEFFF",
+            "DDEDEEFFF",
+            "DDDEEHFIF",
+            "GGGHHEIIF",
+            "GGGHHHIII",
+            "GGGHHHIII",
+        ];
+        let ans = vec![
+            "123456789",
+            "689237145",
+            "471589236",
+            "752948361",
+            "345162897",
+            "896374512",
+            "937821654",
+            "218695473",
+            "564713928",
+        ];
+        let mut sudoku = SudokuSolver::jigsaw(
+            vec![
+                "020000780",
+                "000000005",
+                "400089200",
+                "050900001",
+                "000002000",
+                "096000002",
+                "000020000",
+                "000000470",
+                "000710000",
+            ],
+            regions.clone(),
+        )
+        .unwrap();
+        assert!(sudoku.has_unique_solution());
+        sudoku.run().unwrap();
+        assert!(sudoku == SudokuSolver::jigsaw(ans.clone(), regions.clone()).unwrap());
+        // 通常のブロックでは解になっていない
+        assert!(SudokuSolver::new(ans.clone()).has_finished().is_err());
+
+        // 領域の数や大きさが違う
+        let mut wrong = regions.clone();
+        wrong[0] = "AAABBBCCJ";
+        assert!(SudokuSolver::jigsaw(ans.clone(), wrong).is_err());
+        let mut wrong = regions.clone();
+        wrong[0] = "AAABBBCCA";
+        assert!(SudokuSolver::jigsaw(ans.clone(), wrong).is_err());
+        assert!(SudokuSolver::jigsaw(ans, regions[..8].to_vec()).is_err());
+    }
+    #[test]
+    fn technique_test() {
+        let easy = SudokuSolver::new(vec![
+            "902304501",
+            "000208000",
+            "758109423",
+            "604005792",
+            "000407000",
+            "217900845",
+            "106703904",
+            "000501000",
+            "509602317",
+        ]);
+        assert_eq!(easy.difficulty().unwrap(), Difficulty::Easy);
+        // 元の盤面には記録が残らない
+        assert!(easy.steps().is_empty());
+        let mut solver = easy.clone();
+        assert!(solver.logical_solve());
+        let steps = solver.steps();
+        assert!(steps
+            .iter()
+            .all(|step| step.technique == Technique::NakedSingle));
+        // 確定したマスは全て記録される
+        let placed = steps.iter().filter(|step| step.cells.len() == 1).count();
+        assert!(placed >= 38);
+        let step = steps
+            .iter()
+            .find(|step| !step.eliminations.is_empty())
+            .unwrap();
+        let (x, y) = step.cells[0];
+        let digit = step.digits[0];
+        for &(ex, ey, n) in &step.eliminations {
+            assert_eq!(n, digit);
+            assert!(
+                ex == x
+                    || ey == y
+                    || solver.xy_to_block_ind(ex, ey) == solver.xy_to_block_ind(x, y)
+            );
+        }
+        assert!(!format!("{}", step).is_empty());
+
+        // 行や列に一か所しか入らない数字が必要
+        let mut solver = SudokuSolver::new(vec![
+            "000007000",
+            "020008040",
+            "103000000",
+            "000150000",
+            "000300070",
+            "000000089",
+            "090000000",
+            "080002000",
+            "000600100",
+        ]);
+        assert!(solver.logical_solve());
+        assert!(solver
+            .steps()
+            .iter()
+            .any(|step| step.technique == Technique::HiddenSingle));
+        assert_eq!(technique::grade(&solver.steps()), Difficulty::Easy);
+
+        // ブロックに一か所しか入らない数字はLocked Candidatesとして記録しない
+        let solver = SudokuSolver::new(vec!["000000000"; 9]);
+        for (x, y) in solver.block_cells(0).skip(1) {
+            if let FieldElement::SUG(set) = &mut *solver.field[x][y].lock().unwrap() {
+                set.remove(&1);
+            }
+        }
+        assert!(!solver.line_confirmed(1));
+        assert!(solver.steps().is_empty());
+        let mut solver = SudokuSolver::new(vec![
+            "000007000",
+            "020008040",
+            "103000000",
+            "000150000",
+            "000300070",
+            "000000089",
+            "090000000",
+            "080002000",
+            "000600100",
+        ]);
+        while solver.num_search() {}
+        assert!(solver
+            .steps()
+            .iter()
+            .all(|step| step.technique != Technique::LockedCandidates || step.cells.len() > 1));
+
+        // 論理だけでは解けず仮置きが必要
+        let hard = SudokuSolver::new(vec![
+            "800000000",
+            "003600000",
+            "070090200",
+            "050007000",
+            "000045700",
+            "000100030",
+            "001000068",
+            "008500010",
+            "090000400",
+        ]);
+        assert!(!hard.clone().logical_solve());
+        assert_eq!(hard.difficulty().unwrap(), Difficulty::Extreme);
+
+        let steps = vec![
+            Step {
+                technique: Technique::HiddenSingle,
+                cells: vec![(0, 0)],
+                digits: vec![1],
+                eliminations: vec![(0, 0, 2)],
+            },
+            Step {
+                technique: Technique::LockedCandidates,
+                cells: vec![(0, 0), (0, 1)],
+                digits: vec![3],
+                eliminations: vec![(0, 5, 3)],
+            },
+        ];
+        assert_eq!(technique::grade(&steps), Difficulty::Medium);
+        assert_eq!(technique::grade(&[]), Difficulty::Easy);
+
+        // 解がない
+        let wrong = SudokuSolver::new(vec![
+            "110000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+        ]);
+        assert!(wrong.difficulty().is_err());
+    }
+    #[test]
+    fn advanced_test() {
+        // X-Wingが必要
+        let mut solver = SudokuSolver::new(vec![
+            "100000569",
+            "492056108",
+            "056109240",
+            "009640801",
+            "064010000",
+            "218035604",
+            "040500016",
+            "905061402",
+            "621000005",
+        ]);
+        assert!(solver.logical_solve());
+        assert!(solver
+            .steps()
+            .iter()
+            .any(|step| step.technique == Technique::XWing));
+        assert_eq!(technique::grade(&solver.steps()), Difficulty::Hard);
+
+        // Swordfishが必要
+        let swordfish = SudokuSolver::new(vec![
+            "529410703",
+            "006003002",
+            "003200000",
+            "052300076",
+            "637050200",
+            "190627530",
+            "300069420",
+            "200830600",
+            "960742305",
+        ]);
+        assert_eq!(swordfish.difficulty().unwrap(), Difficulty::Expert);
+        // 使わない設定にしたテクニックは記録に現れない
+        let mut solver = swordfish.clone();
+        solver.set_technique(Technique::Swordfish, false);
+        assert!(!solver.is_enabled(Technique::Swordfish));
+        solver.logical_solve();
+        assert!(solver
+            .steps()
+            .iter()
+            .all(|step| step.technique != Technique::Swordfish));
+        // 上級のテクニックを全て切ると仮置きが必要になる
+        let mut solver = swordfish.clone();
+        for technique in Technique::ADVANCED {
+            solver.set_technique(technique, false);
+        }
+        assert_eq!(solver.difficulty().unwrap(), Difficulty::Extreme);
+        solver.set_technique(Technique::Swordfish, true);
+        solver.set_technique(Technique::HiddenSubset, true);
+        assert_eq!(solver.difficulty().unwrap(), Difficulty::Expert);
+
+        // XY-Wingが必要
+        let mut solver = SudokuSolver::new(vec![
+            "900040000",
+            "000600031",
+            "020000090",
+            "000700020",
+            "002935600",
+            "070002000",
+            "060000073",
+            "510009000",
+            "000080009",
+        ]);
+        assert!(solver.logical_solve());
+        assert!(solver
+            .steps()
+            .iter()
+            .any(|step| step.technique == Technique::XYWing));
+
+        // 候補が一つのマスの確定は切れない
+        solver.set_technique(Technique::NakedSingle, false);
+        assert!(solver.is_enabled(Technique::NakedSingle));
+    }
+    #[test]
+    fn parse_test() {
+        let rows = vec![
+            "902304501",
+            "000208000",
+            "758109423",
+            "604005792",
+            "000407000",
+            "217900845",
+            "106703904",
+            "000501000",
+            "509602317",
+        ];
+        let mut sudoku = SudokuSolver::parse(rows.clone()).unwrap();
+        sudoku.run().unwrap();
+        assert!(sudoku.has_finished().unwrap());
+
+        let error = |rows: Vec<&str>| SudokuSolver::parse(rows).err().unwrap().to_string();
+        let mut wrong = rows.clone();
+        wrong[3] = "6040057920";
+        assert_eq!(error(wrong), "row 3: wrong length, expected 9 but found 10");
+        let mut wrong = rows.clone();
+        wrong[4] = "000407x00";
+        assert_eq!(error(wrong), "row 4, column 6: bad character 'X'");
+        // 行の数が平方数でない
+        assert!(error(rows[..8].to_vec()).starts_with("wrong length"));
+        assert!(SudokuSolver::parse(Vec::new()).is_err());
+        // 同じ列に9
+        let mut wrong = rows.clone();
+        wrong[1] = "900208000";
+        assert_eq!(
+            error(wrong),
+            "row 1, column 0: conflicting given '9' with row 0, column 0"
+        );
+        // 同じブロックに2
+        let mut wrong = rows.clone();
+        wrong[1] = "020208000";
+        assert_eq!(
+            error(wrong),
+            "row 1, column 1: conflicting given '2' with row 0, column 2"
+        );
+
+        // 規則には反しないが解がない問題は解く途中でPuzzleErrorになる
+        let mut broken = SudokuSolver::parse(vec![
+            "123456780",
+            "000000009",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+        ])
+        .unwrap();
+        assert!(broken.clone().run().is_err());
+        assert!(broken.difficulty().is_err());
+        assert_eq!(broken.count_solutions(2), 0);
+        assert!(!broken.logical_solve());
+
+        let regions = vec![
+            "AAABBBCCC",
+            "AAABBBCCC",
+            "AAABBBCCC",
+            "DDDEEEFFF",
+            "DDDEEEFFF",
+            "DDDEEEFFF",
+            "GGGHHHIII",
+            "GGGHHHIII",
+            "GGGHHHIII",
+        ];
+        let mut wrong = rows.clone();
+        wrong[0] = "90230450?";
+        assert!(SudokuSolver::jigsaw(wrong, regions.clone()).is_err());
+        assert!(SudokuSolver::jigsaw(rows, regions).is_ok());
+    }
+    #[test]
+    fn display_test() {
+        let sudoku = SudokuSolver::new(vec!["1...", "..3.", ".4..", "...2"]);
+        assert_eq!(
+            format!("{}", sudoku),
+            "\
++-----+-----+
+| 1 . | . . |
+| . . | 3 . |
++-----+-----+
+| . 4 | . . |
+| . . | . 2 |
++-----+-----+
+"
+        );
+        let mut sudoku = SudokuSolver::new(vec![
+            "902304501",
+            "000208000",
+            "758109423",
+            "604005792",
+            "000407000",
+            "217900845",
+            "106703904",
+            "000501000",
+            "509602317",
+        ]);
+        let text = format!("{}", sudoku);
+        assert_eq!(text.lines().nth(1), Some("| 9 . 2 | 3 . 4 | 5 . 1 |"));
+        assert_eq!(text.lines().count(), 13);
+
+        // 候補の表示
+        sudoku.num_search();
+        let text = sudoku.pencil_marks();
+        assert_eq!(text, format!("{:#}", sudoku));
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 9 * 3 + 6 + 4);
+        assert_eq!(lines[0], "+-------------+-------------+-------------+");
+        assert_eq!(lines[2], "|  9   6   2  |  3   7   4  |  5   8   1  |");
+        // (1, 0)の候補は{3, 4}
+        assert_eq!(
+            *sudoku.field[1][0].lock().unwrap(),
+            FieldElement::SUG(BTreeSet::from([3, 4]))
+        );
+        assert_eq!(&lines[5][..6], "| ..3 ");
+        assert_eq!(&lines[6][..6], "| 4.. ");
+        assert_eq!(&lines[7][..6], "| ... ");
+
+        assert_eq!(format!("{}", FieldElement::NUM(3)), "3");
+        assert_eq!(
+            format!("{}", FieldElement::SUG(BTreeSet::from([1, 6]))),
+            "{1,6}"
+        );
+    }
+    #[test]
+    fn count_solutions_test() {
+        let sudoku = SudokuSolver::new(vec![
+            "902304501",
+            "000208000",
+            "758109423",
+            "604005792",
+            "000407000",
+            "217900845",
+            "106703904",
+            "000501000",
+            "509602317",
+        ]);
+        assert_eq!(sudoku.count_solutions(2), 1);
+        assert!(sudoku.has_unique_solution());
+        let solutions = sudoku.all_solutions();
+        assert_eq!(solutions.len(), 1);
+        assert!(solutions[0].has_finished().unwrap());
+        // 元の盤面は変わらない
+        assert!(sudoku.is_vacant());
+
+        // (0,2),(0,7),(2,2),(2,7)の2と8は入れ替えられるので解が2つある
+        let sudoku = SudokuSolver::new(vec![
+            "960374501",
+            "341258679",
+            "750169403",
+            "634815792",
+            "895427136",
+            "217936845",
+            "126783954",
+            "473591268",
+            "589642317",
+        ]);
+        assert_eq!(sudoku.count_solutions(10), 2);
+        assert_eq!(sudoku.count_solutions(1), 1);
+        assert!(!sudoku.has_unique_solution());
+        let solutions = sudoku.all_solutions();
+        assert_eq!(solutions.len(), 2);
+        assert!(solutions[0] != solutions[1]);
+        for solution in solutions {
+            assert!(solution.has_finished().unwrap());
+        }
+
+        // 最初から同じ行に1が2つある
+        let sudoku = SudokuSolver::new(vec![
+            "110000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+            "000000000",
+        ]);
+        assert_eq!(sudoku.count_solutions(2), 0);
+        assert!(sudoku.all_solutions().is_empty());
+
+        // 解の多い問題もlimitで打ち切る
+        let sudoku = SudokuSolver::new(vec!["000000000"; 9]);
+        assert_eq!(sudoku.count_solutions(3), 3);
+    }
+}
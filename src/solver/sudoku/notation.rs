@@ -0,0 +1,534 @@
+//! 数独の問題の読み書き
+//! 81文字の一行，SadMan Sudokuの.sdk形式，候補(鉛筆書き)も持てるJSONに対応する
+//! 盤面の数字と候補だけを扱い，追加ルールやケージは含めない
+
+use std::collections::BTreeSet;
+
+use super::super::{PuzzleError, PuzzleResult};
+use super::{Data, FieldElement, SudokuSolver};
+
+/// JSONの配列とオブジェクトを入れ子にできる深さ
+const MAX_DEPTH: usize = 64;
+
+impl SudokuSolver {
+    /// 全てのマスを左上から並べた一行の文字列から作る(9x9なら81文字)
+    /// 空きマスは'.'か'0'か'*'
+    pub fn from_line(line: &str) -> PuzzleResult<Self> {
+        let chars: Vec<char> = line.trim().chars().collect();
+        let width = (1..=chars.len())
+            .find(|x| x * x >= chars.len())
+            .unwrap_or(0);
+        if width * width != chars.len() || width == 0 {
+            return Err(PuzzleError::new(format!(
+                "wrong length: {} characters is not a square grid",
+                chars.len()
+            )));
+        }
+        let rows: Vec<String> = chars
+            .chunks(width)
+            .map(|row| row.iter().collect())
+            .collect();
+        SudokuSolver::parse(rows.iter().map(|row| row.as_str()).collect())
+    }
+    /// 一行の文字列，空きマスは'.'
+    pub fn to_line(&self) -> String {
+        self.rows().concat()
+    }
+
+    /// SadMan Sudokuの.sdk形式から作る
+    /// '#'で始まる行(作者や説明などの情報)は読み飛ばし，最初の盤面(空行か次の節まで)だけを読む
+    /// '['で始まる節は[Puzzle]だけを読み，[State]など他の節は無視する
+    pub fn from_sdk(text: &str) -> PuzzleResult<Self> {
+        let mut rows = Vec::new();
+        let mut skip = false;
+        for line in text.lines().map(|line| line.trim()) {
+            if line.starts_with('[') {
+                if !rows.is_empty() {
+                    break;
+                }
+                skip = !line.eq_ignore_ascii_case("[Puzzle]");
+            } else if line.is_empty() {
+                if !rows.is_empty() {
+                    break;
+                }
+            } else if !skip && !line.starts_with('#') {
+                rows.push(line);
+            }
+        }
+        SudokuSolver::parse(rows)
+    }
+    /// SadMan Sudokuの.sdk形式，空きマスは'.'
+    pub fn to_sdk(&self) -> String {
+        let mut str = String::from("#SSadMan Software Sudoku\n");
+        for row in self.rows() {
+            str += &row;
+            str += "\n";
+        }
+        str
+    }
+
+    /// JSONから作る
+    /// {"size": 3, "grid": ["9.23.45.1", ...], "candidates": [["", "16", ...], ...]}
+    /// candidatesは省略でき，ある場合は空きマスの候補をその記号の集合にする
+    /// 空きマスの候補が空の時，同じ行，列，ブロックなどに既にある数字を含む時，数字のあるマスに候補がある時はPuzzleErrorを返す
+    pub fn from_json(text: &str) -> PuzzleResult<Self> {
+        let json = Json::parse(text)?;
+        let grid = json
+            .get("grid")
+            .and_then(|grid| grid.as_array())
+            .ok_or_else(|| PuzzleError::new("json: \"grid\" must be an array of rows"))?;
+        let rows = grid
+            .iter()
+            .map(|row| {
+                row.as_str()
+                    .ok_or_else(|| PuzzleError::new("json: each row of \"grid\" must be a string"))
+            })
+            .collect::<PuzzleResult<Vec<&str>>>()?;
+        let solver = SudokuSolver::parse(rows)?;
+        if let Some(size) = json.get("size") {
+            if size.as_usize() != Some(solver.size()) {
+                return Err(PuzzleError::new(format!(
+                    "json: \"size\" does not match the grid of size {}",
+                    solver.size()
+                )));
+            }
+        }
+        if let Some(candidates) = json.get("candidates") {
+            solver.set_candidates(candidates)?;
+        }
+        Ok(solver)
+    }
+    /// JSON，空きマスの候補も書き出す
+    /// 候補からは確定した数字と同じ行，列，ブロックなどにある数字を除く
+    pub fn to_json(&self) -> String {
+        let width = self.width();
+        let solver = self.clone();
+        solver.remove_given_suggest();
+        let quote = |str: String| format!("\"{}\"", str);
+        let grid: Vec<String> = self.rows().into_iter().map(quote).collect();
+        let candidates: Vec<String> = (0..width)
+            .map(|x| {
+                let row: Vec<String> = (0..width)
+                    .map(|y| match &*solver.field[x][y].lock().unwrap() {
+                        FieldElement::SUG(set) => {
+                            quote(set.iter().map(|&n| self.symbol(n)).collect())
+                        }
+                        FieldElement::NUM(_) => quote(String::new()),
+                    })
+                    .collect();
+                format!("[{}]", row.join(", "))
+            })
+            .collect();
+        format!(
+            "{{\n  \"size\": {},\n  \"grid\": [\n    {}\n  ],\n  \"candidates\": [\n    {}\n  ]\n}}\n",
+            self.size(),
+            grid.join(",\n    "),
+            candidates.join(",\n    ")
+        )
+    }
+
+    /// 一行ずつの文字列，空きマスは'.'
+    fn rows(&self) -> Vec<String> {
+        self.field
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|elem| match *elem.lock().unwrap() {
+                        FieldElement::NUM(val) => self.symbol(val),
+                        FieldElement::SUG(_) => '.',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+    /// JSONのcandidatesで空きマスの候補を置き換える
+    fn set_candidates(&self, candidates: &Json) -> PuzzleResult<()> {
+        let width = self.width();
+        let rows = candidates
+            .as_array()
+            .filter(|rows| rows.len() == width)
+            .ok_or_else(|| {
+                PuzzleError::new(format!("json: \"candidates\" must have {} rows", width))
+            })?;
+        for (x, row) in rows.iter().enumerate() {
+            let cells = row
+                .as_array()
+                .filter(|cells| cells.len() == width)
+                .ok_or_else(|| {
+                    PuzzleError::new(format!(
+                        "json: row {} of \"candidates\" must have {} cells",
+                        x, width
+                    ))
+                })?;
+            for (y, cell) in cells.iter().enumerate() {
+                let str = cell.as_str().ok_or_else(|| {
+                    PuzzleError::new(format!(
+                        "json: row {}, column {}: candidates must be a string",
+                        x, y
+                    ))
+                })?;
+                let set = str
+                    .chars()
+                    .map(|c| {
+                        let c = c.to_ascii_uppercase();
+                        (1..=width).find(|&n| self.symbol(n) == c).ok_or_else(|| {
+                            PuzzleError::new(format!(
+                                "json: row {}, column {}: bad character '{}'",
+                                x, y, c
+                            ))
+                        })
+                    })
+                    .collect::<PuzzleResult<BTreeSet<Data>>>()?;
+                self.check_candidates(x, y, &set)?;
+                if let FieldElement::SUG(sug) = &mut *self.field[x][y].lock().unwrap() {
+                    *sug = set;
+                }
+            }
+        }
+        Ok(())
+    }
+    /// (x, y)の候補setが盤面の数字と矛盾しないか
+    fn check_candidates(&self, x: usize, y: usize, set: &BTreeSet<Data>) -> PuzzleResult<()> {
+        if let FieldElement::NUM(_) = *self.field[x][y].lock().unwrap() {
+            if set.is_empty() {
+                return Ok(());
+            }
+            return Err(PuzzleError::new(format!(
+                "json: row {}, column {}: candidates on a given cell",
+                x, y
+            )));
+        }
+        if set.is_empty() {
+            return Err(PuzzleError::new(format!(
+                "json: row {}, column {}: no candidates",
+                x, y
+            )));
+        }
+        let width = self.width();
+        let peers = (0..width)
+            .flat_map(|t| [(x, t), (t, y)])
+            .chain(self.block_cells(self.xy_to_block_ind(x, y)))
+            .chain(self.constraint_peers(x, y));
+        for (px, py) in peers {
+            if let FieldElement::NUM(num) = *self.field[px][py].lock().unwrap() {
+                if set.contains(&num) {
+                    return Err(PuzzleError::new(format!(
+                        "json: row {}, column {}: candidate '{}' conflicts with given in row {}, column {}",
+                        x,
+                        y,
+                        self.symbol(num),
+                        px,
+                        py
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 読み込みに必要なだけのJSONの値
+#[derive(PartialEq, Debug)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn parse(text: &str) -> PuzzleResult<Json> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut pos = 0;
+        let json = Json::value(&chars, &mut pos, 0)?;
+        Json::skip_space(&chars, &mut pos);
+        if pos != chars.len() {
+            return Err(PuzzleError::new(format!(
+                "json: unexpected '{}' at {}",
+                chars[pos], pos
+            )));
+        }
+        Ok(json)
+    }
+
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+    fn as_array(&self) -> Option<&Vec<Json>> {
+        match self {
+            Json::Array(vec) => Some(vec),
+            _ => None,
+        }
+    }
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::Str(str) => Some(str),
+            _ => None,
+        }
+    }
+    fn as_usize(&self) -> Option<usize> {
+        match *self {
+            Json::Number(n) if n >= 0.0 && n.fract() == 0.0 => Some(n as usize),
+            _ => None,
+        }
+    }
+
+    fn skip_space(chars: &[char], pos: &mut usize) {
+        while *pos < chars.len() && chars[*pos].is_whitespace() {
+            *pos += 1;
+        }
+    }
+    /// 次の空白でない文字がcであれば読み進めてtrueを返す
+    fn eat(chars: &[char], pos: &mut usize, c: char) -> bool {
+        Json::skip_space(chars, pos);
+        if chars.get(*pos) == Some(&c) {
+            *pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+    fn expect(chars: &[char], pos: &mut usize, c: char) -> PuzzleResult<()> {
+        if Json::eat(chars, pos, c) {
+            Ok(())
+        } else {
+            Err(PuzzleError::new(format!(
+                "json: expected '{}' at {}",
+                c, pos
+            )))
+        }
+    }
+    /// depthは今の値を囲む配列とオブジェクトの数，MAX_DEPTHを超えたらPuzzleErrorを返す
+    fn value(chars: &[char], pos: &mut usize, depth: usize) -> PuzzleResult<Json> {
+        Json::skip_space(chars, pos);
+        if depth > MAX_DEPTH {
+            return Err(PuzzleError::new(format!(
+                "json: nested deeper than {} at {}",
+                MAX_DEPTH, pos
+            )));
+        }
+        let rest: String = chars[*pos..].iter().take(5).collect();
+        match chars.get(*pos) {
+            Some('{') => {
+                *pos += 1;
+                let mut members = Vec::new();
+                if !Json::eat(chars, pos, '}') {
+                    loop {
+                        Json::skip_space(chars, pos);
+                        let key = Json::string(chars, pos)?;
+                        Json::expect(chars, pos, ':')?;
+                        members.push((key, Json::value(chars, pos, depth + 1)?));
+                        if !Json::eat(chars, pos, ',') {
+                            break;
+                        }
+                    }
+                    Json::expect(chars, pos, '}')?;
+                }
+                Ok(Json::Object(members))
+            }
+            Some('[') => {
+                *pos += 1;
+                let mut vec = Vec::new();
+                if !Json::eat(chars, pos, ']') {
+                    loop {
+                        vec.push(Json::value(chars, pos, depth + 1)?);
+                        if !Json::eat(chars, pos, ',') {
+                            break;
+                        }
+                    }
+                    Json::expect(chars, pos, ']')?;
+                }
+                Ok(Json::Array(vec))
+            }
+            Some('"') => Ok(Json::Str(Json::string(chars, pos)?)),
+            Some('-' | '0'..='9') => {
+                let start = *pos;
+                while *pos < chars.len() && "+-.eE0123456789".contains(chars[*pos]) {
+                    *pos += 1;
+                }
+                let str: String = chars[start..*pos].iter().collect();
+                str.parse()
+                    .map(Json::Number)
+                    .map_err(|_| PuzzleError::new(format!("json: bad number at {}", start)))
+            }
+            _ if rest.starts_with("true") => {
+                *pos += 4;
+                Ok(Json::Bool(true))
+            }
+            _ if rest.starts_with("false") => {
+                *pos += 5;
+                Ok(Json::Bool(false))
+            }
+            _ if rest.starts_with("null") => {
+                *pos += 4;
+                Ok(Json::Null)
+            }
+            _ => Err(PuzzleError::new(format!(
+                "json: unexpected value at {}",
+                pos
+            ))),
+        }
+    }
+    fn string(chars: &[char], pos: &mut usize) -> PuzzleResult<String> {
+        if chars.get(*pos) != Some(&'"') {
+            return Err(PuzzleError::new(format!(
+                "json: expected string at {}",
+                pos
+            )));
+        }
+        *pos += 1;
+        let mut str = String::new();
+        loop {
+            let c = *chars
+                .get(*pos)
+                .ok_or_else(|| PuzzleError::new("json: unterminated string"))?;
+            *pos += 1;
+            match c {
+                '"' => return Ok(str),
+                '\\' => {
+                    let e = *chars
+                        .get(*pos)
+                        .ok_or_else(|| PuzzleError::new("json: unterminated string"))?;
+                    *pos += 1;
+                    match e {
+                        'n' => str.push('\n'),
+                        't' => str.push('\t'),
+                        'r' => str.push('\r'),
+                        'b' => str.push('\u{8}'),
+                        'f' => str.push('\u{c}'),
+                        'u' => {
+                            let hex: String = chars.iter().skip(*pos).take(4).collect();
+                            let code = u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| {
+                                    PuzzleError::new(format!("json: bad escape at {}", pos))
+                                })?;
+                            *pos += 4;
+                            str.push(code);
+                        }
+                        _ => str.push(e),
+                    }
+                }
+                _ => str.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{FieldElement, SudokuSolver};
+    use super::Json;
+    use crate::solver::Solver;
+
+    const LINE: &str =
+        "9.23.45.1...2.8...7581.94236.4..5792...4.7...2179..8451.67.39.4...5.1...5.96.2317";
+
+    #[test]
+    fn test_line_and_sdk() {
+        let sudoku = SudokuSolver::from_line(LINE).unwrap();
+        assert_eq!(sudoku.to_line(), LINE);
+        let zeros = LINE.replace('.', "0");
+        assert!(SudokuSolver::from_line(&zeros).unwrap() == sudoku);
+        assert!(SudokuSolver::from_line(&LINE[1..]).is_err());
+        assert!(SudokuSolver::from_line(&LINE.replace('9', "x")).is_err());
+
+        let sdk = sudoku.to_sdk();
+        assert_eq!(sdk.lines().nth(1), Some("9.23.45.1"));
+        assert!(SudokuSolver::from_sdk(&sdk).unwrap() == sudoku);
+        let sdk = "#AAuthor\n#DA puzzle\n[Puzzle]\n\n9.23.45.1\n...2.8...\n7581.9423\n6.4..5792\n...4.7...\n2179..845\n1.67.39.4\n...5.1...\n5.96.2317\n";
+        let mut solver = SudokuSolver::from_sdk(sdk).unwrap();
+        assert_eq!(solver.to_line(), LINE);
+        // 途中の状態の節は読まない
+        let state = format!(
+            "{}\n[State]\n962374581\n341258679\n758169423\n634815792\n895427136\n217936845\n126783954\n473591268\n589642317\n",
+            sdk
+        );
+        assert!(SudokuSolver::from_sdk(&state).unwrap() == solver);
+        solver.run().unwrap();
+        assert_eq!(
+            solver.to_line(),
+            "962374581341258679758169423634815792895427136217936845126783954473591268589642317"
+        );
+    }
+    #[test]
+    fn test_json() {
+        let mut sudoku = SudokuSolver::from_line(LINE).unwrap();
+        sudoku.num_search();
+        let json = sudoku.to_json();
+        assert!(json.starts_with("{\n  \"size\": 3,"));
+        assert!(json.contains("\"candidates\""));
+        let read = SudokuSolver::from_json(&json).unwrap();
+        // 候補まで同じ
+        assert!(read == sudoku);
+        assert_eq!(read.to_json(), json);
+
+        // 候補を省略
+        let json = format!(
+            "{{\"grid\": [{}]}}",
+            (0..9)
+                .map(|i| format!("\"{}\"", &LINE[i * 9..i * 9 + 9]))
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+        let read = SudokuSolver::from_json(&json).unwrap();
+        assert_eq!(read.to_line(), LINE);
+        assert_eq!(
+            *read.field[0][1].lock().unwrap(),
+            FieldElement::SUG((1..=9).collect())
+        );
+
+        assert!(SudokuSolver::from_json("{\"size\": 2, \"grid\": []}").is_err());
+        assert!(SudokuSolver::from_json("[1, 2]").is_err());
+        assert!(SudokuSolver::from_json("{\"grid\": [\"12\"").is_err());
+        // 深すぎる入れ子はスタックを溢れさせずにエラーにする
+        assert!(SudokuSolver::from_json(&"[".repeat(200_000)).is_err());
+        assert!(Json::parse(&format!("{}{}", "[".repeat(64), "]".repeat(64))).is_ok());
+
+        // (x, y)の候補だけを書き換える
+        let base = SudokuSolver::from_line(LINE).unwrap().to_json();
+        let candidates = |x: usize, y: usize, cell: &str| {
+            let mut lines: Vec<String> = base.lines().map(|line| line.to_string()).collect();
+            let start = lines
+                .iter()
+                .position(|line| line.contains("candidates"))
+                .unwrap();
+            let line = &mut lines[start + 1 + x];
+            let end = if line.ends_with(',') { "]," } else { "]" };
+            let inner = line.trim().trim_start_matches('[').trim_end_matches(end);
+            let mut cells: Vec<String> = inner.split(", ").map(|c| c.to_string()).collect();
+            cells[y] = format!("\"{}\"", cell);
+            *line = format!("    [{}{}", cells.join(", "), end);
+            lines.join("\n")
+        };
+        assert!(SudokuSolver::from_json(&base).is_ok());
+        assert!(SudokuSolver::from_json(&candidates(0, 1, "6")).is_ok());
+        // 同じ行に9がある
+        assert!(SudokuSolver::from_json(&candidates(0, 1, "9")).is_err());
+        assert!(SudokuSolver::from_json(&candidates(0, 1, "")).is_err());
+        // 数字のあるマスの候補
+        assert!(SudokuSolver::from_json(&candidates(0, 0, "9")).is_err());
+
+        assert_eq!(
+            Json::parse(" {\"a\": [1, -2.5e1, true, null], \"b\": \"x\\\"\\u0041\"} ").unwrap(),
+            Json::Object(vec![
+                (
+                    "a".to_string(),
+                    Json::Array(vec![
+                        Json::Number(1.0),
+                        Json::Number(-25.0),
+                        Json::Bool(true),
+                        Json::Null
+                    ])
+                ),
+                ("b".to_string(), Json::Str("x\"A".to_string())),
+            ])
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! 数独の解き方(テクニック)の記録と難易度
+//! 一つの推論ごとに使ったテクニック，注目したマス，数字，消した候補をStepとして残す
+
+use std::fmt::Display;
+
+use super::Data;
+
+/// テクニックの種類
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum Technique {
+    /// 候補が一つになったマスを確定し，同じ行，列，ブロックから消す
+    NakedSingle,
+    /// 行，列，ブロックの中で数字の入るマスが一つしかない
+    HiddenSingle,
+    /// ブロックの中の候補が一つの行(列)に並んでいるので，その行(列)の他のブロックから消す
+    LockedCandidates,
+    /// ブロックの中のn個のマスにn種類の数字しか入らないので，他のマスから消す
+    NakedSubset,
+    /// キラー数独のケージの和を作れない数字を消す
+    CageCombination,
+    /// 行，列，ブロックの中でn種類の数字がn個のマスにしか入らないので，そのマスの他の候補を消す
+    HiddenSubset,
+    /// 2本の行(列)で数字の入るマスが2本の列(行)に収まる
+    XWing,
+    /// 3本の行(列)で数字の入るマスが3本の列(行)に収まる
+    Swordfish,
+    /// 4本の行(列)で数字の入るマスが4本の列(行)に収まる
+    Jellyfish,
+    /// 候補が2つのマス3つで作る翼
+    XYWing,
+    /// 候補が3つのマスと2つのマス2つで作る翼
+    XYZWing,
+    /// 数字の入るマスが2つしかない領域をたどって2色に塗る
+    SimpleColoring,
+    /// 論理では進まないので仮に数字を置く
+    Guess,
+}
+
+/// 難易度，必要なテクニックのうち最も難しいもので決める
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+    /// 仮置きが必要
+    Extreme,
+}
+
+impl Technique {
+    /// 論理で解く時に試す順番(簡単なものから)
+    pub const LOGICAL: [Technique; 12] = [
+        Technique::NakedSingle,
+        Technique::HiddenSingle,
+        Technique::LockedCandidates,
+        Technique::CageCombination,
+        Technique::NakedSubset,
+        Technique::HiddenSubset,
+        Technique::XWing,
+        Technique::XYWing,
+        Technique::Swordfish,
+        Technique::XYZWing,
+        Technique::SimpleColoring,
+        Technique::Jellyfish,
+    ];
+    /// num_searchで使わない上級のテクニック
+    pub const ADVANCED: [Technique; 7] = [
+        Technique::HiddenSubset,
+        Technique::XWing,
+        Technique::XYWing,
+        Technique::Swordfish,
+        Technique::XYZWing,
+        Technique::SimpleColoring,
+        Technique::Jellyfish,
+    ];
+
+    pub fn difficulty(&self) -> Difficulty {
+        match self {
+            Technique::NakedSingle | Technique::HiddenSingle => Difficulty::Easy,
+            Technique::LockedCandidates | Technique::NakedSubset | Technique::CageCombination => {
+                Difficulty::Medium
+            }
+            Technique::HiddenSubset | Technique::XWing | Technique::XYWing => Difficulty::Hard,
+            Technique::Swordfish
+            | Technique::Jellyfish
+            | Technique::XYZWing
+            | Technique::SimpleColoring => Difficulty::Expert,
+            Technique::Guess => Difficulty::Extreme,
+        }
+    }
+}
+
+/// 一回の推論
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+pub struct Step {
+    pub technique: Technique,
+    /// 推論の根拠になったマス(x, y)，NakedSingle，HiddenSingle，Guessでは数字を入れたマス
+    pub cells: Vec<(usize, usize)>,
+    /// 推論に使った数字
+    pub digits: Vec<Data>,
+    /// 消した候補(x, y, 数字)
+    pub eliminations: Vec<(usize, usize, Data)>,
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} {:?} at {:?}",
+            self.technique, self.digits, self.cells
+        )?;
+        if !self.eliminations.is_empty() {
+            write!(f, ", eliminate {:?}", self.eliminations)?;
+        }
+        Ok(())
+    }
+}
+
+/// 手順全体の難易度
+pub fn grade(steps: &[Step]) -> Difficulty {
+    steps
+        .iter()
+        .map(|step| step.technique.difficulty())
+        .max()
+        .unwrap_or(Difficulty::Easy)
+}